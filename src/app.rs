@@ -1,12 +1,33 @@
 pub mod data_collection;
-use data_collection::{processes, temperature};
-use std::time::Instant;
+use audit_log::{AuditLog, ProcessAction};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use data_collection::{
+	processes::{self, details::ProcessDetails, threads::ThreadCollector, ProcessData},
+	temperature,
+};
+use keymap::{Action, KeyMap};
+use layout_manager::{BottomLayout, WidgetDirection};
+use process_search::ProcessSearchState;
 
-use crate::{canvas, constants, data_conversion::ConvertedProcessData, utils::error::Result};
+use std::{
+	collections::{HashMap, HashSet},
+	time::{Duration, Instant},
+};
 
-mod process_killer;
+use crate::{
+	canvas, constants,
+	data_conversion::{convert_process_data, ConvertedProcessData},
+	utils::error::{BottomError, Result},
+};
 
-#[derive(Clone, Copy)]
+pub mod audit_log;
+pub mod keymap;
+pub mod layout_manager;
+pub mod process_killer;
+pub mod process_scheduler;
+pub mod process_search;
+
+#[derive(Clone, Copy, Debug)]
 pub enum ApplicationPosition {
 	Cpu,
 	Mem,
@@ -24,6 +45,15 @@ pub enum ScrollDirection {
 	DOWN,
 }
 
+/// Which process (or group) the selection of the process table is on, so that it can be kept on
+/// it when the rows move around.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SelectedProcess {
+	Pid(u32),
+	/// The name of a group, which is whatever its processes share.
+	Group(String),
+}
+
 pub struct App {
 	// Sorting
 	pub process_sorting_type: processes::ProcessSorting,
@@ -32,39 +62,135 @@ pub struct App {
 	// Positioning
 	pub scroll_direction: ScrollDirection,
 	pub currently_selected_process_position: i64,
+	/// What the selection was last on, to find it again after the process table is re-sorted.
+	selected_process: Option<SelectedProcess>,
 	pub currently_selected_disk_position: i64,
 	pub currently_selected_temperature_position: i64,
 	pub currently_selected_cpu_table_position: i64,
-	pub previous_disk_position: i64,
-	pub previous_temp_position: i64,
-	pub previous_process_position: i64,
-	pub previous_cpu_table_position: i64,
+	/// The first row shown by each table widget, by widget ID, so that widgets of the same type
+	/// scroll on their own.
+	pub previous_positions: HashMap<u64, i64>,
 	pub temperature_type: temperature::TemperatureType,
 	pub update_rate_in_milliseconds: u64,
 	pub show_average_cpu: bool,
 	pub current_application_position: ApplicationPosition,
+	pub current_widget_id: u64,
+	pub layout: BottomLayout,
 	pub data: data_collection::Data,
-	awaiting_second_char: bool,
-	second_char: char,
+	pub keymap: KeyMap,
+	pub process_search_state: ProcessSearchState,
 	pub use_dot: bool,
 	pub show_help: bool,
 	pub show_dd: bool,
+	/// Why acting on the processes of the open process dialog (dd, renice or CPU affinity)
+	/// failed, if it did.
 	pub dd_err: Option<String>,
-	to_delete_process_list: Option<Vec<ConvertedProcessData>>,
+	/// The index into `process_killer::ALL_SIGNALS` of the signal picked in the dd dialog.
+	pub dd_signal_index: usize,
+	/// Whether the dd dialog acts on the whole subtree of the selected processes, in which case
+	/// its processes are listed children first.
+	pub dd_kill_tree: bool,
+	/// How long processes sent SIGTERM from the dd dialog get to exit before they are sent
+	/// SIGKILL, if they are sent it at all.
+	pub kill_grace_period: Option<Duration>,
+	/// The processes being terminated from the dd dialog, while it shows how that is going.
+	pub kill_escalation: Option<process_killer::KillEscalation>,
+	/// The processes that the open process dialog (dd, renice or CPU affinity) acts on.
+	dialog_process_list: Option<Vec<ConvertedProcessData>>,
+	pub show_renice: bool,
+	/// The niceness picked in the renice dialog.
+	pub renice_value: i64,
+	pub show_affinity: bool,
+	/// Which CPUs are picked in the CPU affinity dialog, by CPU number.
+	pub affinity_cpus: Vec<bool>,
+	/// The CPU that the cursor is on in the CPU affinity dialog.
+	pub affinity_cursor: usize,
+	/// The details of the process picked with Enter, which are shown while this is set.
+	pub process_details: Option<ProcessDetails>,
+	/// How many lines the process detail pane is scrolled down by.
+	pub process_details_scroll: u16,
 	pub is_frozen: bool,
 	pub left_legend: bool,
 	pub use_current_cpu_total: bool,
-	last_key_press: Instant,
+	pub use_basic_mode: bool,
 	pub canvas_data: canvas::CanvasData,
-	enable_grouping: bool,
+	pub canvas_colours: canvas::CanvasColours,
+	process_grouping: Option<processes::grouping::ProcessGrouping>,
+	enable_tree: bool,
+	show_full_command: bool,
+	show_process_history: bool,
+	/// How many characters of the full commands are scrolled past in the process table.
+	pub process_command_scroll: usize,
+	/// The PIDs of the processes whose subtrees are collapsed in the tree.
+	pub collapsed_process_pids: HashSet<u32>,
+	/// The PIDs of the marked processes, which process dialogs act on instead of the selected
+	/// process while there are any.
+	pub marked_process_pids: HashSet<u32>,
+	/// Collects the threads of the process whose threads are shown in place of the process
+	/// table, while they are.
+	thread_collector: Option<ThreadCollector>,
+	/// The threads shown in the thread view, with their thread IDs in place of PIDs.
+	pub thread_list: Vec<ProcessData>,
+	/// Why the threads of the process in the thread view couldn't be read, if they couldn't.
+	pub thread_err: Option<String>,
+	/// Where actions taken on processes are recorded, if anywhere.
+	pub audit_log: Option<AuditLog>,
+	/// Whether processes are only watched, and never killed, reniced or otherwise changed.
+	pub read_only: bool,
+}
+
+/// Why nothing can be done to processes in read-only mode.
+const READ_ONLY_MESSAGE: &str = "bottom is in read-only mode, so processes can't be killed, reniced or have their CPU affinity changed.";
+
+/// Identifies a process in a process dialog by its PID and when it started, so that it isn't
+/// mistaken for another process given the same PID while the dialog is open.
+fn get_process_identity(process: &ConvertedProcessData) -> process_killer::ProcessIdentity {
+	process_killer::ProcessIdentity {
+		pid: process.pid,
+		start_ticks: process.start_ticks,
+	}
+}
+
+/// Records an action taken on a process of a process dialog in the audit log, if there is one.
+fn record_process_action(
+	audit_log: &Option<AuditLog>, process: &ConvertedProcessData, action: &ProcessAction,
+	result: &Result<()>,
+) {
+	if let Some(audit_log) = audit_log {
+		audit_log.record(action, process.pid, &process.name, &process.command, result);
+	}
+}
+
+/// Sends a signal to a process of the dd dialog while terminating them with escalation, and
+/// records it in the audit log.
+fn signal_escalating_process(
+	audit_log: &Option<AuditLog>, process_list: &[ConvertedProcessData],
+	identity: process_killer::ProcessIdentity, signal: process_killer::Signal,
+) -> Result<()> {
+	let result = process_killer::kill_process_given_identity(identity, signal);
+	if let Some(process) = process_list
+		.iter()
+		.find(|process| process.pid == identity.pid)
+	{
+		record_process_action(audit_log, process, &ProcessAction::Signal(signal), &result);
+	}
+	result
 }
 
 impl App {
 	pub fn new(
 		show_average_cpu: bool, temperature_type: temperature::TemperatureType,
 		update_rate_in_milliseconds: u64, use_dot: bool, left_legend: bool,
-		use_current_cpu_total: bool,
+		use_current_cpu_total: bool, use_basic_mode: bool,
 	) -> App {
+		let layout = BottomLayout::default();
+		let (current_application_position, current_widget_id) =
+			if let Some(initial_widget) = layout.get_initial_widget() {
+				(initial_widget.widget_type, initial_widget.widget_id)
+			} else {
+				(ApplicationPosition::Process, 0)
+			};
+
 		App {
 			process_sorting_type: processes::ProcessSorting::CPU,
 			process_sorting_reverse: true,
@@ -72,296 +198,1139 @@ impl App {
 			temperature_type,
 			update_rate_in_milliseconds,
 			show_average_cpu,
-			current_application_position: ApplicationPosition::Process,
+			current_application_position,
+			current_widget_id,
+			layout,
 			scroll_direction: ScrollDirection::DOWN,
 			currently_selected_process_position: 0,
+			selected_process: None,
 			currently_selected_disk_position: 0,
 			currently_selected_temperature_position: 0,
 			currently_selected_cpu_table_position: 0,
-			previous_process_position: 0,
-			previous_disk_position: 0,
-			previous_temp_position: 0,
-			previous_cpu_table_position: 0,
+			previous_positions: HashMap::new(),
 			data: data_collection::Data::default(),
-			awaiting_second_char: false,
-			second_char: ' ',
+			keymap: KeyMap::default(),
+			process_search_state: ProcessSearchState::default(),
 			use_dot,
 			show_help: false,
 			show_dd: false,
 			dd_err: None,
-			to_delete_process_list: None,
+			dd_signal_index: 0,
+			dd_kill_tree: false,
+			kill_grace_period: None,
+			kill_escalation: None,
+			dialog_process_list: None,
+			show_renice: false,
+			renice_value: 0,
+			show_affinity: false,
+			affinity_cpus: Vec::new(),
+			affinity_cursor: 0,
+			process_details: None,
+			process_details_scroll: 0,
 			is_frozen: false,
 			left_legend,
 			use_current_cpu_total,
-			last_key_press: Instant::now(),
+			use_basic_mode,
 			canvas_data: canvas::CanvasData::default(),
-			enable_grouping: false,
+			canvas_colours: canvas::CanvasColours::default(),
+			process_grouping: None,
+			enable_tree: false,
+			show_full_command: false,
+			show_process_history: true,
+			process_command_scroll: 0,
+			collapsed_process_pids: HashSet::new(),
+			marked_process_pids: HashSet::new(),
+			thread_collector: None,
+			thread_list: Vec::new(),
+			thread_err: None,
+			audit_log: None,
+			read_only: false,
 		}
 	}
 
+	/// Replaces the default layout, and selects the initial widget of the new one.
+	pub fn set_layout(&mut self, layout: BottomLayout) {
+		if let Some(initial_widget) = layout.get_initial_widget() {
+			self.current_application_position = initial_widget.widget_type;
+			self.current_widget_id = initial_widget.widget_id;
+		}
+		self.layout = layout;
+	}
+
 	pub fn reset(&mut self) {
 		self.reset_multi_tap_keys();
 		self.show_help = false;
+		self.process_details = None;
+		// Processes still being terminated keep the dd dialog open, as only dismissing it
+		// cancels the SIGKILL still to be sent.
+		if self.is_escalating() {
+			return;
+		}
 		self.show_dd = false;
-		self.to_delete_process_list = None;
+		self.show_renice = false;
+		self.show_affinity = false;
+		self.dialog_process_list = None;
 		self.dd_err = None;
+		self.kill_escalation = None;
+	}
+
+	/// Whether processes from the dd dialog are still being terminated.
+	fn is_escalating(&self) -> bool {
+		self.kill_escalation
+			.as_ref()
+			.map_or(false, |kill_escalation| !kill_escalation.is_done())
+	}
+
+	/// Stops SIGKILL from being sent to the processes of the dd dialog still waiting for it, and
+	/// records that in the audit log.  The dialog stays open to show what was cancelled.
+	fn cancel_kill_escalation(&mut self) {
+		let audit_log = &self.audit_log;
+		let process_list = self.dialog_process_list.as_deref().unwrap_or_default();
+		if let Some(kill_escalation) = &mut self.kill_escalation {
+			for identity in kill_escalation.cancel() {
+				if let Some(process) = process_list
+					.iter()
+					.find(|process| process.pid == identity.pid)
+				{
+					record_process_action(
+						audit_log,
+						process,
+						&ProcessAction::CancelSignal(process_killer::Signal::Kill),
+						&Ok(()),
+					);
+				}
+			}
+		}
 	}
 
 	fn reset_multi_tap_keys(&mut self) {
-		self.awaiting_second_char = false;
-		self.second_char = ' ';
+		self.keymap.reset_pending_keys();
 	}
 
 	fn is_in_dialog(&self) -> bool {
-		self.show_help || self.show_dd
+		self.show_help || self.is_in_process_dialog() || self.process_details.is_some()
+	}
+
+	/// Whether a dialog that acts on the selected processes (dd, renice or CPU affinity) is open.
+	fn is_in_process_dialog(&self) -> bool {
+		self.show_dd || self.show_renice || self.show_affinity
 	}
 
+	/// Cycles through what processes are grouped by, and then back to not grouping them.
 	pub fn toggle_grouping(&mut self) {
 		// Disallow usage whilst in a dialog and only in processes
 		if !self.is_in_dialog() {
 			if let ApplicationPosition::Process = self.current_application_position {
-				self.enable_grouping = !(self.enable_grouping);
+				self.process_grouping = match self.process_grouping {
+					Some(grouping) => grouping.next(),
+					None => Some(processes::grouping::ProcessGrouping::Name),
+				};
+				if self.process_grouping.is_some() {
+					self.enable_tree = false;
+				}
+				self.to_be_resorted = true;
 			}
 		}
 	}
 
-	pub fn on_tab(&mut self) {
-		match self.current_application_position {
-			ApplicationPosition::Process => self.toggle_grouping(),
-			ApplicationPosition::Disk => {}
-			_ => {}
+	pub fn is_grouped(&self) -> bool {
+		self.process_grouping.is_some()
+	}
+
+	pub fn get_grouping(&self) -> Option<processes::grouping::ProcessGrouping> {
+		self.process_grouping
+	}
+
+	pub fn toggle_tree(&mut self) {
+		if !self.is_in_dialog() {
+			if let ApplicationPosition::Process = self.current_application_position {
+				self.enable_tree = !self.enable_tree;
+				if self.enable_tree {
+					self.process_grouping = None;
+				}
+				self.to_be_resorted = true;
+			}
 		}
 	}
 
-	pub fn is_grouped(&self) -> bool {
-		self.enable_grouping
+	pub fn is_tree_mode(&self) -> bool {
+		self.enable_tree
+	}
+
+	/// Switches the process table between showing names and full command lines.  Grouping and
+	/// sorting by name follow what is shown.
+	pub fn toggle_full_command(&mut self) {
+		if !self.is_in_dialog() {
+			if let ApplicationPosition::Process = self.current_application_position {
+				self.show_full_command = !self.show_full_command;
+				self.process_command_scroll = 0;
+				self.process_sorting_type = match self.process_sorting_type {
+					processes::ProcessSorting::NAME if self.show_full_command => {
+						processes::ProcessSorting::COMMAND
+					}
+					processes::ProcessSorting::COMMAND if !self.show_full_command => {
+						processes::ProcessSorting::NAME
+					}
+					ref sorting_type => sorting_type.clone(),
+				};
+				self.to_be_resorted = true;
+			}
+		}
+	}
+
+	pub fn is_showing_full_command(&self) -> bool {
+		self.show_full_command
+	}
+
+	/// Shows or hides the recent CPU and memory usage of the selected process.
+	pub fn toggle_process_history(&mut self) {
+		if !self.is_in_dialog() {
+			if let ApplicationPosition::Process = self.current_application_position {
+				self.show_process_history = !self.show_process_history;
+			}
+		}
+	}
+
+	pub fn is_showing_process_history(&self) -> bool {
+		self.show_process_history
+	}
+
+	/// Shows the threads of the selected process in place of the process table, or goes back to
+	/// the process table.  For a group, this is its first process.
+	pub fn toggle_thread_view(&mut self) {
+		if !self.is_in_dialog() {
+			if let ApplicationPosition::Process = self.current_application_position {
+				if self.is_showing_threads() {
+					self.close_thread_view();
+				} else if let Some(pid) = self.get_selected_pid() {
+					self.thread_collector = Some(ThreadCollector::new(pid));
+					self.refresh_threads();
+					// Start on the main thread, whose ID is the PID.
+					self.selected_process = Some(SelectedProcess::Pid(pid));
+					self.to_be_resorted = true;
+				}
+			}
+		}
+	}
+
+	fn close_thread_view(&mut self) {
+		if let Some(thread_collector) = self.thread_collector.take() {
+			self.thread_list.clear();
+			self.thread_err = None;
+			// Go back to the process whose threads were shown.
+			self.selected_process = Some(SelectedProcess::Pid(thread_collector.get_pid()));
+			self.to_be_resorted = true;
+		}
+	}
+
+	pub fn is_showing_threads(&self) -> bool {
+		self.thread_collector.is_some()
+	}
+
+	/// The PID of the process whose threads are shown, if they are.
+	pub fn get_thread_view_pid(&self) -> Option<u32> {
+		self.thread_collector
+			.as_ref()
+			.map(|thread_collector| thread_collector.get_pid())
+	}
+
+	/// Reads the threads in the thread view again, if it is open.
+	pub fn refresh_threads(&mut self) {
+		if let Some(thread_collector) = &mut self.thread_collector {
+			match thread_collector.get_threads(self.use_current_cpu_total) {
+				Ok(thread_list) => {
+					self.thread_list = thread_list;
+					self.thread_err = None;
+				}
+				Err(err) => {
+					self.thread_list.clear();
+					self.thread_err = Some(err.to_string());
+				}
+			}
+		}
+	}
+
+	/// Whether the process table shows groups, which it doesn't while showing threads.
+	pub fn is_showing_groups(&self) -> bool {
+		self.is_grouped() && !self.is_showing_threads()
+	}
+
+	/// Remembers which process (or group) is selected, to keep the selection on it the next time
+	/// the process table changes.  Should be called whenever the selection moves, and not in
+	/// between changing how the table is shown and rebuilding it.
+	pub fn pin_selected_process(&mut self) {
+		self.selected_process = self.get_selected_row().map(|process| {
+			if self.is_showing_groups() {
+				SelectedProcess::Group(process.name.clone())
+			} else {
+				SelectedProcess::Pid(process.pid)
+			}
+		});
+	}
+
+	/// Moves the selection to wherever the pinned process (or group) now is, after the process
+	/// table has been rebuilt.  A process in a group or collapsed subtree is found in there.  If
+	/// it can't be found, such as if it exited or the search filters it out, it is unpinned and
+	/// the selection goes back to the top, rather than landing on whatever took its place.
+	pub fn restore_selected_process(&mut self) {
+		let selected_process = match &self.selected_process {
+			Some(selected_process) => selected_process,
+			None => return,
+		};
+		let rows = self.get_displayed_rows();
+		let new_position = match selected_process {
+			SelectedProcess::Pid(pid) => rows
+				.iter()
+				.position(|process| process.pid == *pid)
+				.or_else(|| rows.iter().position(|process| process.group.contains(pid))),
+			SelectedProcess::Group(name) if self.is_showing_groups() => {
+				rows.iter().position(|process| process.name == *name)
+			}
+			SelectedProcess::Group(_) => None,
+		};
+
+		match new_position.map(|position| position as i64) {
+			Some(new_position) => {
+				// The table only scrolls to follow the selection in the direction it moved.
+				if new_position > self.currently_selected_process_position {
+					self.scroll_direction = ScrollDirection::DOWN;
+				} else if new_position < self.currently_selected_process_position {
+					self.scroll_direction = ScrollDirection::UP;
+				}
+				self.currently_selected_process_position = new_position;
+			}
+			None => {
+				self.selected_process = None;
+				self.currently_selected_process_position = 0;
+				self.reset_process_scroll();
+				self.scroll_direction = ScrollDirection::UP;
+			}
+		}
+	}
+
+	/// Scrolls every process widget back to the top.
+	fn reset_process_scroll(&mut self) {
+		for widget in self.layout.widgets() {
+			if let ApplicationPosition::Process = widget.widget_type {
+				self.previous_positions.remove(&widget.widget_id);
+			}
+		}
+	}
+
+	/// Keeps every process widget from being scrolled past the selection, such as after the
+	/// list has shrunk.
+	pub fn clamp_process_scroll(&mut self) {
+		for widget in self.layout.widgets() {
+			if let ApplicationPosition::Process = widget.widget_type {
+				if let Some(position) = self.previous_positions.get_mut(&widget.widget_id) {
+					*position = std::cmp::min(*position, self.currently_selected_process_position);
+				}
+			}
+		}
+	}
+
+	/// The rows of the process table, which are threads, groups, the tree or plain processes.
+	pub fn get_displayed_rows(&self) -> &[ConvertedProcessData] {
+		if self.is_showing_threads() {
+			&self.canvas_data.thread_process_data
+		} else if self.is_grouped() {
+			&self.canvas_data.grouped_process_data
+		} else if self.is_tree_mode() {
+			&self.canvas_data.tree_process_data
+		} else {
+			&self.canvas_data.process_data
+		}
+	}
+
+	/// The row of the process table that is selected, which may be a group or a thread.
+	pub fn get_selected_row(&self) -> Option<&ConvertedProcessData> {
+		self.get_displayed_rows()
+			.get(self.currently_selected_process_position as usize)
+	}
+
+	/// The PID of the selected process, or of the first process of the selected group.  For a
+	/// thread, this is its thread ID.
+	fn get_selected_pid(&self) -> Option<u32> {
+		self.get_selected_row().and_then(|process| {
+			if self.is_showing_groups() {
+				process.group.first().copied()
+			} else {
+				Some(process.pid)
+			}
+		})
+	}
+
+	/// Scrolls the full commands in the process table sideways.  How far they can go is
+	/// limited when drawn, as that depends on how wide the column is.
+	fn scroll_command(&mut self, num_to_change_by: i64) {
+		if self.is_in_dialog() || !self.show_full_command {
+			return;
+		}
+
+		if let ApplicationPosition::Process = self.current_application_position {
+			self.process_command_scroll =
+				std::cmp::max(self.process_command_scroll as i64 + num_to_change_by, 0) as usize;
+		}
+	}
+
+	/// Collapses or expands the subtree of the selected process in the tree.
+	fn set_selected_subtree_collapsed(&mut self, is_collapsed: bool) {
+		if self.is_in_dialog() || !self.enable_tree || self.is_showing_threads() {
+			return;
+		}
+
+		if let ApplicationPosition::Process = self.current_application_position {
+			if let Some(process) = self
+				.canvas_data
+				.tree_process_data
+				.get(self.currently_selected_process_position as usize)
+			{
+				if is_collapsed {
+					self.collapsed_process_pids.insert(process.pid);
+				} else {
+					self.collapsed_process_pids.remove(&process.pid);
+				}
+				self.to_be_resorted = true;
+			}
+		}
+	}
+
+	fn is_searching_processes(&self) -> bool {
+		self.process_search_state.is_typing
+			&& !self.is_in_dialog()
+			&& matches!(
+				self.current_application_position,
+				ApplicationPosition::Process
+			)
+	}
+
+	/// Sends the key press to the process search bar if it is being typed in, returning whether
+	/// it was used.  Keys that don't edit the search are left to be handled as actions.
+	pub fn on_search_key_event(&mut self, key_event: KeyEvent) -> bool {
+		if !self.is_searching_processes() {
+			return false;
+		}
+
+		match key_event {
+			KeyEvent {
+				code: KeyCode::Char(c),
+				modifiers,
+			} if modifiers.is_empty() || modifiers == KeyModifiers::SHIFT => {
+				self.process_search_state.push_char(c)
+			}
+			KeyEvent {
+				code: KeyCode::Char('u'),
+				modifiers: KeyModifiers::CONTROL,
+			} => self.process_search_state.clear_query(),
+			KeyEvent {
+				code: KeyCode::Backspace,
+				..
+			} => self.process_search_state.pop_char(),
+			_ => return false,
+		}
+
+		self.on_search_changed();
+		self.reset_multi_tap_keys();
+		true
+	}
+
+	/// The filtered list changes with the search.  The selection stays on the selected process
+	/// if it still matches, and goes back to the top otherwise.
+	fn on_search_changed(&mut self) {
+		self.to_be_resorted = true;
+	}
+
+	fn open_search(&mut self) {
+		if !self.is_in_dialog() {
+			if let ApplicationPosition::Process = self.current_application_position {
+				self.process_search_state.open();
+			}
+		}
+	}
+
+	fn toggle_search_option(&mut self, toggle: fn(&mut ProcessSearchState)) {
+		if self.process_search_state.is_enabled && !self.is_in_dialog() {
+			toggle(&mut self.process_search_state);
+			self.on_search_changed();
+		}
+	}
+
+	/// Closes a dialog if one is open, otherwise goes back from the thread view to the process
+	/// table, or closes the process search bar.  While processes from the dd dialog are being
+	/// terminated, this cancels sending them SIGKILL instead.
+	pub fn on_esc(&mut self) {
+		if self.is_escalating() {
+			self.cancel_kill_escalation();
+		} else if self.is_in_dialog() {
+			self.reset();
+		} else if self.is_showing_threads() {
+			if let ApplicationPosition::Process = self.current_application_position {
+				self.close_thread_view();
+			}
+		} else if self.process_search_state.is_enabled {
+			if let ApplicationPosition::Process = self.current_application_position {
+				self.process_search_state.close();
+				self.on_search_changed();
+			}
+		}
 	}
 
 	/// One of two functions allowed to run while in a dialog...
 	pub fn on_enter(&mut self) {
-		if self.show_dd {
-			// If within dd...
-			if self.dd_err.is_none() {
+		if self.is_searching_processes() {
+			self.process_search_state.is_typing = false;
+		} else if self.is_in_process_dialog() {
+			// If within dd (or another process dialog)...
+			if let Some(kill_escalation) = &self.kill_escalation {
+				// Once every process is dealt with, the dialog can be closed.
+				if kill_escalation.is_done() {
+					self.reset();
+				}
+			} else if self.dd_err.is_none() {
 				// Also ensure that we didn't just fail a dd...
-				let dd_result = self.kill_highlighted_process();
+				let dd_result = if self.show_dd {
+					self.kill_highlighted_process()
+				} else if self.show_renice {
+					self.renice_highlighted_process()
+				} else {
+					self.set_highlighted_process_affinity()
+				};
 				if let Err(dd_err) = dd_result {
 					// There was an issue... inform the user...
 					self.dd_err = Some(dd_err.to_string());
-				} else {
+				} else if self.kill_escalation.is_none() {
 					self.show_dd = false;
+					self.show_renice = false;
+					self.show_affinity = false;
 				}
 			}
+		} else if !self.is_in_dialog() {
+			self.open_process_details();
+		}
+	}
+
+	/// Opens the detail pane for the selected process.  For a group, this is its first process.
+	fn open_process_details(&mut self) {
+		if let ApplicationPosition::Process = self.current_application_position {
+			if let Some(pid) = self.get_selected_pid() {
+				self.process_details = Some(processes::details::read_process_details(pid));
+				self.process_details_scroll = 0;
+			}
+		}
+	}
+
+	/// Reads the details of the process in the detail pane again, if it is open.
+	pub fn refresh_process_details(&mut self) {
+		if let Some(process_details) = &mut self.process_details {
+			*process_details = processes::details::read_process_details(process_details.pid);
+		}
+	}
+
+	/// Performs an action triggered by a key sequence.  Quitting and resetting the collected
+	/// data are handled by the caller, as they are not part of the app state.
+	pub fn perform_action(&mut self, action: Action) {
+		if self.read_only && action.is_mutating() {
+			self.show_read_only_notice(action);
+			return;
+		}
+
+		match action {
+			Action::Quit | Action::ResetData => {}
+			Action::Freeze => self.toggle_freeze(),
+			Action::ShowHelp => self.show_help(),
+			Action::CloseDialog => self.on_esc(),
+			Action::Confirm => self.on_enter(),
+			Action::MoveLeft => self.on_left(),
+			Action::MoveRight => self.on_right(),
+			Action::MoveUp => self.on_up(),
+			Action::MoveDown => self.on_down(),
+			Action::ScrollUp => self.decrement_position_count(),
+			Action::ScrollDown => self.increment_position_count(),
+			Action::SkipToFirst => self.skip_to_first(),
+			Action::SkipToLast => self.skip_to_last(),
+			Action::KillProcess => self.start_dd(false),
+			Action::KillProcessTree => self.start_dd(true),
+			Action::ReniceProcess => self.start_renice(),
+			Action::SetProcessAffinity => self.start_affinity(),
+			Action::ToggleSelected => self.toggle_selected(),
+			Action::MarkAllProcesses => self.mark_all_processes(),
+			Action::UnmarkAllProcesses => self.unmark_all_processes(),
+			Action::SortByCpu => self.sort_by(processes::ProcessSorting::CPU),
+			Action::SortByMem => self.sort_by(processes::ProcessSorting::MEM),
+			Action::SortByPid => self.sort_by(processes::ProcessSorting::PID),
+			Action::SortByName => self.sort_by(if self.show_full_command {
+				processes::ProcessSorting::COMMAND
+			} else {
+				processes::ProcessSorting::NAME
+			}),
+			Action::SortByUser => self.sort_by(processes::ProcessSorting::USER),
+			Action::SortByState => self.sort_by(processes::ProcessSorting::STATE),
+			Action::SortByThreads => self.sort_by(processes::ProcessSorting::THREADS),
+			Action::SortByRss => self.sort_by(processes::ProcessSorting::RSS),
+			Action::SortByVsz => self.sort_by(processes::ProcessSorting::VSZ),
+			Action::SortByNice => self.sort_by(processes::ProcessSorting::NICE),
+			Action::SortByStartTime => self.sort_by(processes::ProcessSorting::START),
+			Action::SortByReadRate => self.sort_by(processes::ProcessSorting::READ),
+			Action::SortByWriteRate => self.sort_by(processes::ProcessSorting::WRITE),
+			Action::SortByContainer => self.sort_by(processes::ProcessSorting::CONTAINER),
+			Action::ToggleGrouping => self.toggle_grouping(),
+			Action::ToggleTree => self.toggle_tree(),
+			Action::ToggleFullCommand => self.toggle_full_command(),
+			Action::ToggleHistory => self.toggle_process_history(),
+			Action::ToggleThreads => self.toggle_thread_view(),
+			Action::ScrollLeft => self.scroll_command(-constants::COMMAND_SCROLL_STEP),
+			Action::ScrollRight => self.scroll_command(constants::COMMAND_SCROLL_STEP),
+			Action::CollapseSubtree => self.set_selected_subtree_collapsed(true),
+			Action::ExpandSubtree => self.set_selected_subtree_collapsed(false),
+			Action::OpenSearch => self.open_search(),
+			Action::ToggleSearchRegex => {
+				self.toggle_search_option(ProcessSearchState::toggle_regex)
+			}
+			Action::ToggleSearchCaseSensitive => {
+				self.toggle_search_option(ProcessSearchState::toggle_case_sensitive)
+			}
+			Action::ToggleSearchWholeWord => {
+				self.toggle_search_option(ProcessSearchState::toggle_whole_word)
+			}
+		}
+	}
+
+	fn toggle_freeze(&mut self) {
+		if !self.is_in_dialog() {
+			self.is_frozen = !self.is_frozen;
 		}
 	}
 
-	pub fn on_char_key(&mut self, caught_char: char) {
-		// Forbid any char key presses when showing a dialog box...
+	fn show_help(&mut self) {
 		if !self.is_in_dialog() {
-			let current_key_press_inst = Instant::now();
-			if current_key_press_inst
-				.duration_since(self.last_key_press)
-				.as_millis() > constants::MAX_KEY_TIMEOUT_IN_MILLISECONDS
+			self.show_help = true;
+		}
+	}
+
+	/// Gets the processes that a process dialog should act on: the marked processes if there
+	/// are any, else the selected process, every process of the selected group, a collapsed
+	/// process along with its subtree, or the process whose threads are shown.
+	fn get_selected_process_list(&self) -> Vec<ConvertedProcessData> {
+		if !self.marked_process_pids.is_empty() {
+			self.data
+				.list_of_processes
+				.iter()
+				.filter(|process| self.marked_process_pids.contains(&process.pid))
+				.map(|process| convert_process_data(process, &self.data))
+				.collect()
+		} else if let Some(pid) = self.get_thread_view_pid() {
+			self.data
+				.list_of_processes
+				.iter()
+				.filter(|process| process.pid == pid)
+				.map(|process| convert_process_data(process, &self.data))
+				.collect()
+		} else if self.is_grouped() {
+			let mut res: Vec<ConvertedProcessData> = Vec::new();
+			if let Some(grouped_process) = self
+				.canvas_data
+				.grouped_process_data
+				.get(self.currently_selected_process_position as usize)
 			{
-				self.reset_multi_tap_keys();
-			}
-			self.last_key_press = current_key_press_inst;
-
-			match caught_char {
-				'd' => {
-					if let ApplicationPosition::Process = self.current_application_position {
-						if self.awaiting_second_char && self.second_char == 'd' {
-							self.awaiting_second_char = false;
-							self.second_char = ' ';
-
-							let current_process = if self.is_grouped() {
-								let mut res: Vec<ConvertedProcessData> = Vec::new();
-								for pid in &self.canvas_data.grouped_process_data
-									[self.currently_selected_process_position as usize]
-									.group
-								{
-									let result = self
-										.canvas_data
-										.process_data
-										.iter()
-										.find(|p| p.pid == *pid);
-
-									if let Some(process) = result {
-										res.push((*process).clone());
-									}
-								}
-								res
-							} else {
-								vec![self.canvas_data.process_data
-									[self.currently_selected_process_position as usize]
-									.clone()]
-							};
-							self.to_delete_process_list = Some(current_process);
-							self.show_dd = true;
-							self.reset_multi_tap_keys();
-						} else {
-							self.awaiting_second_char = true;
-							self.second_char = 'd';
-						}
-					}
-				}
-				'g' => {
-					if self.awaiting_second_char && self.second_char == 'g' {
-						self.awaiting_second_char = false;
-						self.second_char = ' ';
-						self.skip_to_first();
-					} else {
-						self.awaiting_second_char = true;
-						self.second_char = 'g';
-					}
-				}
-				'f' => {
-					self.is_frozen = !self.is_frozen;
-				}
-				'c' => {
-					match self.process_sorting_type {
-						processes::ProcessSorting::CPU => {
-							self.process_sorting_reverse = !self.process_sorting_reverse
-						}
-						_ => {
-							self.process_sorting_type = processes::ProcessSorting::CPU;
-							self.process_sorting_reverse = true;
-						}
-					}
-					self.to_be_resorted = true;
-					self.currently_selected_process_position = 0;
-				}
-				'm' => {
-					match self.process_sorting_type {
-						processes::ProcessSorting::MEM => {
-							self.process_sorting_reverse = !self.process_sorting_reverse
-						}
-						_ => {
-							self.process_sorting_type = processes::ProcessSorting::MEM;
-							self.process_sorting_reverse = true;
-						}
-					}
-					self.to_be_resorted = true;
-					self.currently_selected_process_position = 0;
-				}
-				'p' => {
-					// Disable if grouping
-					if !self.enable_grouping {
-						match self.process_sorting_type {
-							processes::ProcessSorting::PID => {
-								self.process_sorting_reverse = !self.process_sorting_reverse
-							}
-							_ => {
-								self.process_sorting_type = processes::ProcessSorting::PID;
-								self.process_sorting_reverse = false;
-							}
-						}
-						self.to_be_resorted = true;
-						self.currently_selected_process_position = 0;
+				// Exactly the processes in the group, whatever they were grouped by.
+				for pid in &grouped_process.group {
+					let result = self.data.list_of_processes.iter().find(|p| p.pid == *pid);
+
+					if let Some(process) = result {
+						res.push(convert_process_data(process, &self.data));
 					}
 				}
-				'n' => {
-					match self.process_sorting_type {
-						processes::ProcessSorting::NAME => {
-							self.process_sorting_reverse = !self.process_sorting_reverse
-						}
-						_ => {
-							self.process_sorting_type = processes::ProcessSorting::NAME;
-							self.process_sorting_reverse = false;
+			}
+			res
+		} else if self.enable_tree {
+			// A collapsed process stands for its whole subtree, which may not all be shown.
+			match self
+				.canvas_data
+				.tree_process_data
+				.get(self.currently_selected_process_position as usize)
+			{
+				Some(tree_process) if !tree_process.group.is_empty() => tree_process
+					.group
+					.iter()
+					.filter_map(|pid| {
+						self.data
+							.list_of_processes
+							.iter()
+							.find(|process| process.pid == *pid)
+					})
+					.map(|process| convert_process_data(process, &self.data))
+					.collect(),
+				tree_process => tree_process.cloned().into_iter().collect(),
+			}
+		} else {
+			self.canvas_data
+				.process_data
+				.get(self.currently_selected_process_position as usize)
+				.cloned()
+				.into_iter()
+				.collect()
+		}
+	}
+
+	/// Opens the dialog of an action that would change processes, saying that it can't be done
+	/// in read-only mode instead of offering to do it.
+	fn show_read_only_notice(&mut self, action: Action) {
+		if self.is_in_dialog() {
+			return;
+		}
+
+		if let ApplicationPosition::Process = self.current_application_position {
+			match action {
+				Action::ReniceProcess => self.show_renice = true,
+				Action::SetProcessAffinity => self.show_affinity = true,
+				_ => self.show_dd = true,
+			}
+			self.dd_err = Some(READ_ONLY_MESSAGE.to_string());
+		}
+	}
+
+	/// Opens the dd dialog to confirm killing the selected process (or group of processes).  If
+	/// `kill_tree` is set, every descendant of them is killed too, children before parents.
+	/// bottom itself is left out, so that it can report how that went.
+	fn start_dd(&mut self, kill_tree: bool) {
+		if self.is_in_dialog() {
+			return;
+		}
+
+		if let ApplicationPosition::Process = self.current_application_position {
+			let process_list = self.get_selected_process_list();
+			self.dialog_process_list = Some(if kill_tree {
+				let root_pids = process_list
+					.iter()
+					.map(|process| process.pid)
+					.collect::<Vec<_>>();
+				let own_pid = std::process::id();
+				processes::subtree::get_subtree_pids(&self.data.list_of_processes, &root_pids)
+					.into_iter()
+					.filter(|pid| *pid != own_pid)
+					.filter_map(|pid| {
+						self.data
+							.list_of_processes
+							.iter()
+							.find(|process| process.pid == pid)
+					})
+					.map(|process| convert_process_data(process, &self.data))
+					.collect()
+			} else {
+				process_list
+			});
+			self.dd_signal_index = 0;
+			self.dd_kill_tree = kill_tree;
+			self.show_dd = true;
+		}
+	}
+
+	/// Opens the renice dialog for the selected process (or group of processes), starting from
+	/// the niceness of the first one.
+	fn start_renice(&mut self) {
+		if self.is_in_dialog() {
+			return;
+		}
+
+		if let ApplicationPosition::Process = self.current_application_position {
+			let process_list = self.get_selected_process_list();
+			if let Some(first_process) = process_list.first() {
+				self.renice_value = self
+					.data
+					.list_of_processes
+					.iter()
+					.find(|process| process.pid == first_process.pid)
+					.and_then(|process| process.nice)
+					.unwrap_or(0);
+				self.dialog_process_list = Some(process_list);
+				self.show_renice = true;
+			}
+		}
+	}
+
+	/// The number of CPUs, not counting the average of them all that is listed first.
+	pub fn get_num_cpus(&self) -> usize {
+		self.data
+			.list_of_cpu_packages
+			.last()
+			.map(|cpu_package| cpu_package.cpu_vec.len().saturating_sub(1))
+			.unwrap_or(0)
+	}
+
+	/// Opens the CPU affinity dialog for the selected process (or group of processes), starting
+	/// from the CPUs that the first one may run on.
+	fn start_affinity(&mut self) {
+		if self.is_in_dialog() {
+			return;
+		}
+
+		if let ApplicationPosition::Process = self.current_application_position {
+			let process_list = self.get_selected_process_list();
+			if let Some(first_process) = process_list.first() {
+				let num_cpus = self.get_num_cpus();
+				self.affinity_cpus =
+					process_scheduler::get_process_affinity(first_process.pid, num_cpus)
+						.unwrap_or_else(|_| vec![true; num_cpus]);
+				self.affinity_cursor = 0;
+				self.dialog_process_list = Some(process_list);
+				self.show_affinity = true;
+			}
+		}
+	}
+
+	/// Picks or unpicks the CPU under the cursor in the CPU affinity dialog, or marks or unmarks
+	/// the selected process (or every process of a group or collapsed subtree) and moves on to
+	/// the next one.  Threads can't be marked, as only whole processes are acted on.
+	fn toggle_selected(&mut self) {
+		if self.show_affinity && self.dd_err.is_none() {
+			if let Some(is_allowed) = self.affinity_cpus.get_mut(self.affinity_cursor) {
+				*is_allowed = !*is_allowed;
+			}
+		} else if !self.is_in_dialog() && !self.is_showing_threads() {
+			if let ApplicationPosition::Process = self.current_application_position {
+				let pids = match self.get_selected_row() {
+					Some(process) if !process.group.is_empty() => process.group.clone(),
+					Some(process) => vec![process.pid],
+					None => return,
+				};
+				self.set_marked(&pids);
+				self.increment_position_count();
+			}
+		}
+	}
+
+	/// Marks the given processes, or unmarks them if they all already are.
+	fn set_marked(&mut self, pids: &[u32]) {
+		if pids
+			.iter()
+			.all(|pid| self.marked_process_pids.contains(pid))
+		{
+			for pid in pids {
+				self.marked_process_pids.remove(pid);
+			}
+		} else {
+			self.marked_process_pids.extend(pids);
+		}
+	}
+
+	/// Marks every process listed in the process table, including every process of each group
+	/// or collapsed subtree, or unmarks them if they all already are.
+	fn mark_all_processes(&mut self) {
+		if !self.is_in_dialog() && !self.is_showing_threads() {
+			if let ApplicationPosition::Process = self.current_application_position {
+				let pids = self
+					.get_displayed_rows()
+					.iter()
+					.flat_map(|process| {
+						if process.group.is_empty() {
+							vec![process.pid]
+						} else {
+							process.group.clone()
 						}
-					}
-					self.to_be_resorted = true;
-					self.currently_selected_process_position = 0;
-				}
-				'?' => {
-					self.show_help = true;
-				}
-				_ => {}
+					})
+					.collect::<Vec<_>>();
+				self.set_marked(&pids);
+			}
+		}
+	}
+
+	fn unmark_all_processes(&mut self) {
+		if !self.is_in_dialog() {
+			if let ApplicationPosition::Process = self.current_application_position {
+				self.marked_process_pids.clear();
 			}
+		}
+	}
 
-			if self.awaiting_second_char && caught_char != self.second_char {
-				self.awaiting_second_char = false;
+	/// Whether every one of the given processes is marked.
+	pub fn is_marked(&self, pids: &[u32]) -> bool {
+		!pids.is_empty()
+			&& pids
+				.iter()
+				.all(|pid| self.marked_process_pids.contains(pid))
+	}
+
+	/// Sorts by the given column, or reverses the sort order if already sorting by it.
+	fn sort_by(&mut self, sorting_type: processes::ProcessSorting) {
+		if self.is_in_dialog() {
+			return;
+		}
+
+		// PID sorting makes no sense for grouped processes
+		if let processes::ProcessSorting::PID = sorting_type {
+			if self.is_showing_groups() {
+				return;
 			}
 		}
+
+		if self.process_sorting_type == sorting_type {
+			self.process_sorting_reverse = !self.process_sorting_reverse;
+		} else {
+			// Numbers are sorted in descending order by default, everything else ascending.
+			self.process_sorting_reverse = match sorting_type {
+				processes::ProcessSorting::CPU
+				| processes::ProcessSorting::MEM
+				| processes::ProcessSorting::THREADS
+				| processes::ProcessSorting::RSS
+				| processes::ProcessSorting::VSZ
+				| processes::ProcessSorting::READ
+				| processes::ProcessSorting::WRITE => true,
+				processes::ProcessSorting::PID
+				| processes::ProcessSorting::NAME
+				| processes::ProcessSorting::COMMAND
+				| processes::ProcessSorting::USER
+				| processes::ProcessSorting::STATE
+				| processes::ProcessSorting::NICE
+				| processes::ProcessSorting::START
+				| processes::ProcessSorting::CONTAINER => false,
+			};
+			self.process_sorting_type = sorting_type;
+		}
+		self.to_be_resorted = true;
+	}
+
+	/// Checks that actions can be recorded in the audit log before any are taken, if there is
+	/// one.
+	fn check_audit_log(&self) -> Result<()> {
+		match &self.audit_log {
+			Some(audit_log) => audit_log.check_writable(),
+			None => Ok(()),
+		}
+	}
+
+	/// Runs an action on every process of the open process dialog, carrying on after a failure
+	/// so that one process exiting early doesn't stop the rest of a group.  Each attempt is
+	/// recorded in the audit log.  Fails with a list of what went wrong for each process that
+	/// failed.
+	fn apply_to_dialog_processes(
+		&self, action: &ProcessAction, apply: impl Fn(&ConvertedProcessData) -> Result<()>,
+	) -> std::result::Result<(), String> {
+		if let Some(process_list) = &self.dialog_process_list {
+			let failures = process_list
+				.iter()
+				.filter_map(|process| {
+					let result = apply(process);
+					record_process_action(&self.audit_log, process, action, &result);
+					result.err().map(|err| match err {
+						BottomError::SignalError { message }
+						| BottomError::SchedulingError { message } => {
+							format!("{} - {}", process.name, message)
+						}
+						err => format!("{} - {}", process.name, err),
+					})
+				})
+				.collect::<Vec<_>>();
+
+			if !failures.is_empty() {
+				return Err(if process_list.len() == 1 {
+					failures.join("")
+				} else {
+					format!(
+						"{} of {} processes failed:\n{}",
+						failures.len(),
+						process_list.len(),
+						failures.join("\n")
+					)
+				});
+			}
+		}
+
+		Ok(())
 	}
 
 	pub fn kill_highlighted_process(&mut self) -> Result<()> {
+		if self.read_only {
+			return Err(BottomError::SignalError {
+				message: READ_ONLY_MESSAGE.to_string(),
+			});
+		}
+
 		// Technically unnecessary but this is a good check...
 		if let ApplicationPosition::Process = self.current_application_position {
-			if let Some(current_selected_processes) = &(self.to_delete_process_list) {
-				for current_selected_process in current_selected_processes {
-					process_killer::kill_process_given_pid(current_selected_process.pid)?;
+			self.check_audit_log()?;
+			let signal = self.get_dd_signal();
+			if let (process_killer::Signal::Term, Some(grace_period)) =
+				(signal, self.kill_grace_period)
+			{
+				// The dialog stays open to show how terminating each process goes.
+				if let Some(process_list) = &self.dialog_process_list {
+					let processes = process_list
+						.iter()
+						.map(|process| (get_process_identity(process), process.name.clone()))
+						.collect::<Vec<_>>();
+					let audit_log = &self.audit_log;
+					let list_of_processes = &self.data.list_of_processes;
+					self.kill_escalation = Some(process_killer::KillEscalation::start(
+						&processes,
+						grace_period,
+						Instant::now(),
+						|identity| {
+							processes::is_process_running(
+								identity.pid,
+								identity.start_ticks,
+								list_of_processes,
+							)
+						},
+						|identity| {
+							signal_escalating_process(audit_log, process_list, identity, signal)
+						},
+					));
 				}
+				return Ok(());
 			}
-			self.to_delete_process_list = None;
+
+			self.apply_to_dialog_processes(&ProcessAction::Signal(signal), |process| {
+				process_killer::kill_process_given_identity(get_process_identity(process), signal)
+			})
+			.map_err(|message| BottomError::SignalError { message })?;
+			self.dialog_process_list = None;
 		}
 		Ok(())
 	}
 
+	pub fn renice_highlighted_process(&mut self) -> Result<()> {
+		if self.read_only {
+			return Err(BottomError::SchedulingError {
+				message: READ_ONLY_MESSAGE.to_string(),
+			});
+		}
+
+		if let ApplicationPosition::Process = self.current_application_position {
+			self.check_audit_log()?;
+			let nice = self.renice_value;
+			self.apply_to_dialog_processes(&ProcessAction::Renice(nice), |process| {
+				process_scheduler::set_process_nice(process.pid, nice)
+			})
+			.map_err(|message| BottomError::SchedulingError { message })?;
+			self.dialog_process_list = None;
+		}
+		Ok(())
+	}
+
+	pub fn set_highlighted_process_affinity(&mut self) -> Result<()> {
+		if self.read_only {
+			return Err(BottomError::SchedulingError {
+				message: READ_ONLY_MESSAGE.to_string(),
+			});
+		}
+
+		if let ApplicationPosition::Process = self.current_application_position {
+			self.check_audit_log()?;
+			let cpus = &self.affinity_cpus;
+			let allowed_cpus = cpus
+				.iter()
+				.enumerate()
+				.filter(|(_, is_allowed)| **is_allowed)
+				.map(|(cpu, _)| cpu)
+				.collect();
+			self.apply_to_dialog_processes(&ProcessAction::SetAffinity(allowed_cpus), |process| {
+				process_scheduler::set_process_affinity(process.pid, cpus)
+			})
+			.map_err(|message| BottomError::SchedulingError { message })?;
+			self.dialog_process_list = None;
+		}
+		Ok(())
+	}
+
+	/// Checks on the processes being terminated from the dd dialog, sending SIGKILL to those that
+	/// have outlasted the grace period.  Called on every refresh, even while frozen, with the
+	/// processes that were just collected.
+	pub fn update_kill_escalation(&mut self, list_of_processes: &[ProcessData]) {
+		let audit_log = &self.audit_log;
+		let process_list = self.dialog_process_list.as_deref().unwrap_or_default();
+		if let Some(kill_escalation) = &mut self.kill_escalation {
+			kill_escalation.update(
+				Instant::now(),
+				|identity| {
+					processes::is_process_running(
+						identity.pid,
+						identity.start_ticks,
+						list_of_processes,
+					)
+				},
+				|identity| {
+					signal_escalating_process(
+						audit_log,
+						process_list,
+						identity,
+						process_killer::Signal::Kill,
+					)
+				},
+			);
+		}
+	}
+
+	pub fn get_dd_signal(&self) -> process_killer::Signal {
+		process_killer::ALL_SIGNALS
+			.get(self.dd_signal_index)
+			.copied()
+			.unwrap_or_default()
+	}
+
+	/// Moves the signal picked in the dd dialog, wrapping around at either end.
+	fn change_dd_signal(&mut self, num_to_change_by: i64) {
+		let num_signals = process_killer::ALL_SIGNALS.len() as i64;
+		self.dd_signal_index =
+			(self.dd_signal_index as i64 + num_to_change_by).rem_euclid(num_signals) as usize;
+	}
+
+	/// Changes the niceness picked in the renice dialog, keeping it within what is allowed.
+	fn change_renice_value(&mut self, num_to_change_by: i64) {
+		self.renice_value = process_scheduler::clamp_nice(self.renice_value + num_to_change_by);
+	}
+
+	fn change_affinity_cursor(&mut self, num_to_change_by: i64) {
+		let new_cursor = self.affinity_cursor as i64 + num_to_change_by;
+		if new_cursor >= 0 && new_cursor < self.affinity_cpus.len() as i64 {
+			self.affinity_cursor = new_cursor as usize;
+		}
+	}
+
 	pub fn get_current_highlighted_process_list(&self) -> Option<Vec<ConvertedProcessData>> {
-		self.to_delete_process_list.clone()
-	}
-
-	// For now, these are hard coded --- in the future, they shouldn't be!
-	//
-	// General idea for now:
-	// CPU -(down)> MEM
-	// MEM -(down)> Network, -(right)> TEMP
-	// TEMP -(down)> Disk, -(left)> MEM, -(up)> CPU
-	// Disk -(down)> Processes, -(left)> MEM, -(up)> TEMP
-	// Network -(up)> MEM, -(right)> PROC
-	// PROC -(up)> Disk, -(left)> Network
+		self.dialog_process_list.clone()
+	}
+
+	/// Moves the selection to the neighbouring widget in the given direction, as worked out
+	/// by the layout.
+	fn move_widget_selection(&mut self, direction: WidgetDirection) {
+		if let Some(new_widget) = self.layout.get_neighbour(self.current_widget_id, direction) {
+			self.current_application_position = new_widget.widget_type;
+			self.current_widget_id = new_widget.widget_id;
+		}
+	}
+
 	pub fn on_left(&mut self) {
 		if !self.is_in_dialog() {
-			self.current_application_position = match self.current_application_position {
-				ApplicationPosition::Process => ApplicationPosition::Network,
-				ApplicationPosition::Disk => ApplicationPosition::Mem,
-				ApplicationPosition::Temp => ApplicationPosition::Mem,
-				_ => self.current_application_position,
-			};
+			self.move_widget_selection(WidgetDirection::Left);
 			self.reset_multi_tap_keys();
 		}
 	}
 
 	pub fn on_right(&mut self) {
 		if !self.is_in_dialog() {
-			self.current_application_position = match self.current_application_position {
-				ApplicationPosition::Mem => ApplicationPosition::Temp,
-				ApplicationPosition::Network => ApplicationPosition::Process,
-				_ => self.current_application_position,
-			};
+			self.move_widget_selection(WidgetDirection::Right);
 			self.reset_multi_tap_keys();
 		}
 	}
 
 	pub fn on_up(&mut self) {
 		if !self.is_in_dialog() {
-			self.current_application_position = match self.current_application_position {
-				ApplicationPosition::Mem => ApplicationPosition::Cpu,
-				ApplicationPosition::Network => ApplicationPosition::Mem,
-				ApplicationPosition::Process => ApplicationPosition::Disk,
-				ApplicationPosition::Temp => ApplicationPosition::Cpu,
-				ApplicationPosition::Disk => ApplicationPosition::Temp,
-				_ => self.current_application_position,
-			};
+			self.move_widget_selection(WidgetDirection::Up);
 			self.reset_multi_tap_keys();
 		}
 	}
 
 	pub fn on_down(&mut self) {
 		if !self.is_in_dialog() {
-			self.current_application_position = match self.current_application_position {
-				ApplicationPosition::Cpu => ApplicationPosition::Mem,
-				ApplicationPosition::Mem => ApplicationPosition::Network,
-				ApplicationPosition::Temp => ApplicationPosition::Disk,
-				ApplicationPosition::Disk => ApplicationPosition::Process,
-				_ => self.current_application_position,
-			};
+			self.move_widget_selection(WidgetDirection::Down);
 			self.reset_multi_tap_keys();
 		}
 	}
 
 	pub fn skip_to_first(&mut self) {
-		if !self.is_in_dialog() {
+		if self.process_details.is_some() {
+			self.process_details_scroll = 0;
+		} else if !self.is_in_dialog() {
 			match self.current_application_position {
 				ApplicationPosition::Process => self.currently_selected_process_position = 0,
 				ApplicationPosition::Temp => self.currently_selected_temperature_position = 0,
@@ -376,11 +1345,14 @@ impl App {
 	}
 
 	pub fn skip_to_last(&mut self) {
-		if !self.is_in_dialog() {
+		if self.process_details.is_some() {
+			// This is brought back to the last line when drawn, as only then is the height known.
+			self.process_details_scroll = u16::MAX;
+		} else if !self.is_in_dialog() {
 			match self.current_application_position {
 				ApplicationPosition::Process => {
 					self.currently_selected_process_position =
-						self.data.list_of_processes.len() as i64 - 1
+						std::cmp::max(self.get_displayed_process_count() as i64 - 1, 0)
 				}
 				ApplicationPosition::Temp => {
 					self.currently_selected_temperature_position =
@@ -408,7 +1380,15 @@ impl App {
 	}
 
 	pub fn decrement_position_count(&mut self) {
-		if !self.is_in_dialog() {
+		if self.show_dd && self.dd_err.is_none() && self.kill_escalation.is_none() {
+			self.change_dd_signal(-1);
+		} else if self.show_renice && self.dd_err.is_none() {
+			self.change_renice_value(1);
+		} else if self.show_affinity && self.dd_err.is_none() {
+			self.change_affinity_cursor(-1);
+		} else if self.process_details.is_some() {
+			self.process_details_scroll = self.process_details_scroll.saturating_sub(1);
+		} else if !self.is_in_dialog() {
 			match self.current_application_position {
 				ApplicationPosition::Process => self.change_process_position(-1),
 				ApplicationPosition::Temp => self.change_temp_position(-1),
@@ -422,7 +1402,15 @@ impl App {
 	}
 
 	pub fn increment_position_count(&mut self) {
-		if !self.is_in_dialog() {
+		if self.show_dd && self.dd_err.is_none() && self.kill_escalation.is_none() {
+			self.change_dd_signal(1);
+		} else if self.show_renice && self.dd_err.is_none() {
+			self.change_renice_value(-1);
+		} else if self.show_affinity && self.dd_err.is_none() {
+			self.change_affinity_cursor(1);
+		} else if self.process_details.is_some() {
+			self.process_details_scroll = self.process_details_scroll.saturating_add(1);
+		} else if !self.is_in_dialog() {
 			match self.current_application_position {
 				ApplicationPosition::Process => self.change_process_position(1),
 				ApplicationPosition::Temp => self.change_temp_position(1),
@@ -450,10 +1438,15 @@ impl App {
 		}
 	}
 
+	/// The number of processes in the table, which may be grouped or filtered, or of threads.
+	pub fn get_displayed_process_count(&self) -> usize {
+		self.get_displayed_rows().len()
+	}
+
 	fn change_process_position(&mut self, num_to_change_by: i64) {
 		if self.currently_selected_process_position + num_to_change_by >= 0
 			&& self.currently_selected_process_position + num_to_change_by
-				< self.data.list_of_processes.len() as i64
+				< self.get_displayed_process_count() as i64
 		{
 			self.currently_selected_process_position += num_to_change_by;
 		}
@@ -477,3 +1470,105 @@ impl App {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_test_app() -> App {
+		App::new(
+			false,
+			temperature::TemperatureType::Celsius,
+			1000,
+			false,
+			false,
+			false,
+			false,
+		)
+	}
+
+	fn get_rows(pids: &[u32]) -> Vec<ConvertedProcessData> {
+		pids.iter()
+			.map(|pid| ConvertedProcessData {
+				pid: *pid,
+				name: format!("process {}", pid),
+				group: vec![*pid],
+				..ConvertedProcessData::default()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_restore_selected_process() {
+		let mut app = get_test_app();
+		app.canvas_data.process_data = get_rows(&[10, 20, 30, 40]);
+		app.currently_selected_process_position = 2;
+		app.pin_selected_process();
+
+		// Re-sorting moves the selection along with the process.
+		app.canvas_data.process_data = get_rows(&[30, 40, 20, 10]);
+		app.restore_selected_process();
+		assert_eq!(app.currently_selected_process_position, 0);
+		assert_eq!(app.get_selected_pid(), Some(30));
+
+		// So does the search filtering out other processes.
+		app.canvas_data.process_data = get_rows(&[40, 30]);
+		app.restore_selected_process();
+		assert_eq!(app.currently_selected_process_position, 1);
+		assert_eq!(app.get_selected_pid(), Some(30));
+
+		// Once it exits, or no longer matches the search, the selection goes back to the top
+		// instead of moving on to whatever takes its place.
+		let widget_id = app.layout.get_initial_widget().unwrap().widget_id;
+		app.previous_positions.insert(widget_id, 1);
+		app.canvas_data.process_data = get_rows(&[40, 20, 10]);
+		app.restore_selected_process();
+		assert_eq!(app.selected_process, None);
+		assert_eq!(app.currently_selected_process_position, 0);
+		assert!(app.previous_positions.is_empty());
+	}
+
+	#[test]
+	fn test_mark_all_processes() {
+		let mut app = get_test_app();
+		app.current_application_position = ApplicationPosition::Process;
+
+		// Only what is listed is marked, such as the processes that match the search.
+		app.canvas_data.process_data = get_rows(&[10, 20]);
+		app.mark_all_processes();
+		assert_eq!(app.marked_process_pids, vec![10, 20].into_iter().collect());
+		app.mark_all_processes();
+		assert!(app.marked_process_pids.is_empty());
+
+		// Every process of a group is marked.
+		app.process_grouping = Some(processes::grouping::ProcessGrouping::Name);
+		app.canvas_data.grouped_process_data = vec![ConvertedProcessData {
+			name: "bash".to_string(),
+			group: vec![10, 11],
+			..ConvertedProcessData::default()
+		}];
+		app.mark_all_processes();
+		assert_eq!(app.marked_process_pids, vec![10, 11].into_iter().collect());
+		app.unmark_all_processes();
+
+		// And so is every process of a collapsed subtree, as well as the processes that aren't.
+		app.process_grouping = None;
+		app.enable_tree = true;
+		app.canvas_data.tree_process_data = vec![
+			ConvertedProcessData {
+				pid: 10,
+				group: vec![10, 11, 12],
+				..ConvertedProcessData::default()
+			},
+			ConvertedProcessData {
+				pid: 20,
+				..ConvertedProcessData::default()
+			},
+		];
+		app.mark_all_processes();
+		assert_eq!(
+			app.marked_process_pids,
+			vec![10, 11, 12, 20].into_iter().collect()
+		);
+	}
+}
@@ -0,0 +1,269 @@
+//! Keeps a record of every action taken on processes (sending them signals, renicing them and
+//! changing their CPU affinity), so that what was done to which process, by whom, and whether it
+//! worked can be traced afterwards.  Each entry is a line of JSON appended to the audit log.
+
+use serde::Serialize;
+use std::{
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+use super::process_killer::Signal;
+use crate::utils::error::{BottomError, Result};
+
+/// An action taken on a process.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProcessAction {
+	Signal(Signal),
+	/// Not sending a signal that was going to be sent, such as SIGKILL once a grace period is over.
+	CancelSignal(Signal),
+	Renice(i64),
+	/// Restricting the process to the given CPUs, by CPU number.
+	SetAffinity(Vec<usize>),
+}
+
+/// A line of the audit log.  Only the fields that apply to the action are written.
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+	timestamp: String,
+	user: &'a str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	uid: Option<u32>,
+	/// Who ran bottom through sudo, if it was.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	sudo_user: Option<&'a str>,
+	pid: u32,
+	name: &'a str,
+	command: &'a str,
+	action: &'static str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	signal: Option<&'static str>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	nice: Option<i64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	cpus: Option<&'a [usize]>,
+	result: &'static str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<String>,
+}
+
+/// Gets the name of whoever is running bottom, along with their UID where there is one.
+fn get_current_user() -> (String, Option<u32>) {
+	#[cfg(target_family = "unix")]
+	{
+		// This is safe, as getuid takes nothing and always succeeds.
+		let uid = unsafe { libc::getuid() };
+		let mut user_table = super::data_collection::processes::users::UserTable::default();
+		user_table.refresh();
+		(user_table.get_user_name(uid), Some(uid))
+	}
+	#[cfg(not(target_family = "unix"))]
+	{
+		(std::env::var("USERNAME").unwrap_or_default(), None)
+	}
+}
+
+/// The audit log, which records actions as taken by whoever is running bottom.
+#[derive(Debug)]
+pub struct AuditLog {
+	path: PathBuf,
+	user: String,
+	uid: Option<u32>,
+	sudo_user: Option<String>,
+}
+
+impl AuditLog {
+	pub fn new(path: PathBuf) -> Self {
+		let (user, uid) = get_current_user();
+		AuditLog {
+			path,
+			user,
+			uid,
+			sudo_user: std::env::var("SUDO_USER").ok(),
+		}
+	}
+
+	pub fn get_path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Opens the audit log for appending, creating it if needed.  As it holds the command lines
+	/// of other users' processes, only its owner may read it.
+	fn open(&self) -> io::Result<File> {
+		if let Some(parent) = self.path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		let mut options = OpenOptions::new();
+		options.append(true).create(true);
+		#[cfg(target_family = "unix")]
+		{
+			use std::os::unix::fs::OpenOptionsExt;
+			options.mode(0o600);
+		}
+		options.open(&self.path)
+	}
+
+	/// Checks that entries can be written to the audit log, so that nothing is done to a process
+	/// that can't be recorded.
+	pub fn check_writable(&self) -> Result<()> {
+		self.open()
+			.map(|_| ())
+			.map_err(|err| BottomError::InvalidIO {
+				message: format!(
+					"Unable to write to the audit log at {}, so nothing was done: {}",
+					self.path.display(),
+					err
+				),
+			})
+	}
+
+	/// Appends an entry for an action taken on a process, and how it went.  The action has
+	/// already been taken by now, so failing to record it can only be written to the debug log.
+	pub fn record(
+		&self, action: &ProcessAction, pid: u32, name: &str, command: &str, result: &Result<()>,
+	) {
+		let (action_name, signal, nice, cpus) = match action {
+			ProcessAction::Signal(signal) => ("signal", Some(signal.name()), None, None),
+			ProcessAction::CancelSignal(signal) => {
+				("cancel_signal", Some(signal.name()), None, None)
+			}
+			ProcessAction::Renice(nice) => ("renice", None, Some(*nice), None),
+			ProcessAction::SetAffinity(cpus) => ("set_affinity", None, None, Some(cpus.as_slice())),
+		};
+		let entry = AuditEntry {
+			timestamp: chrono::Local::now().to_rfc3339(),
+			user: &self.user,
+			uid: self.uid,
+			sudo_user: self.sudo_user.as_deref(),
+			pid,
+			name,
+			command,
+			action: action_name,
+			signal,
+			nice,
+			cpus,
+			result: if result.is_ok() { "ok" } else { "failed" },
+			error: result.as_ref().err().map(|err| match err {
+				BottomError::SignalError { message } | BottomError::SchedulingError { message } => {
+					message.clone()
+				}
+				err => err.to_string(),
+			}),
+		};
+
+		if let Err(err) = serde_json::to_string(&entry)
+			.map_err(io::Error::from)
+			.and_then(|line| writeln!(self.open()?, "{}", line))
+		{
+			error!(
+				"Unable to write to the audit log at {}: {}",
+				self.path.display(),
+				err
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_test_audit_log(name: &str) -> AuditLog {
+		let path = std::env::temp_dir()
+			.join(format!("bottom-test-{}", std::process::id()))
+			.join(name);
+		let _ = fs::remove_file(&path);
+		AuditLog::new(path)
+	}
+
+	fn read_entries(audit_log: &AuditLog) -> Vec<serde_json::Value> {
+		fs::read_to_string(audit_log.get_path())
+			.unwrap()
+			.lines()
+			.map(|line| serde_json::from_str(line).unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn test_record_actions() {
+		let audit_log = get_test_audit_log("audit.log");
+		audit_log.check_writable().unwrap();
+		audit_log.record(
+			&ProcessAction::Signal(Signal::Term),
+			1234,
+			"bash",
+			"/bin/bash --login",
+			&Ok(()),
+		);
+		audit_log.record(
+			&ProcessAction::Renice(10),
+			1,
+			"init",
+			"/sbin/init",
+			&Err(BottomError::SchedulingError {
+				message: "Permission denied".to_string(),
+			}),
+		);
+		audit_log.record(
+			&ProcessAction::SetAffinity(vec![0, 2]),
+			1234,
+			"bash",
+			"/bin/bash --login",
+			&Ok(()),
+		);
+
+		audit_log.record(
+			&ProcessAction::CancelSignal(Signal::Kill),
+			1234,
+			"bash",
+			"/bin/bash --login",
+			&Ok(()),
+		);
+
+		let entries = read_entries(&audit_log);
+		assert_eq!(entries.len(), 4);
+		assert_eq!(entries[0]["action"], "signal");
+		assert_eq!(entries[0]["signal"], "SIGTERM");
+		assert_eq!(entries[0]["pid"], 1234);
+		assert_eq!(entries[0]["command"], "/bin/bash --login");
+		assert_eq!(entries[0]["result"], "ok");
+		assert!(entries[0].get("error").is_none());
+		assert!(entries[0].get("nice").is_none());
+		assert!(entries[0]["timestamp"].is_string());
+		assert_eq!(entries[0]["user"], audit_log.user.as_str());
+
+		assert_eq!(entries[1]["action"], "renice");
+		assert_eq!(entries[1]["nice"], 10);
+		assert_eq!(entries[1]["result"], "failed");
+		assert_eq!(entries[1]["error"], "Permission denied");
+
+		assert_eq!(entries[2]["action"], "set_affinity");
+		assert_eq!(entries[2]["cpus"], serde_json::json!([0, 2]));
+
+		assert_eq!(entries[3]["action"], "cancel_signal");
+		assert_eq!(entries[3]["signal"], "SIGKILL");
+
+		// Entries are appended, never overwritten.
+		let audit_log = AuditLog::new(audit_log.get_path().to_path_buf());
+		audit_log.record(
+			&ProcessAction::Signal(Signal::Kill),
+			1234,
+			"bash",
+			"/bin/bash --login",
+			&Ok(()),
+		);
+		assert_eq!(read_entries(&audit_log).len(), 5);
+	}
+
+	#[test]
+	fn test_unwritable_audit_log() {
+		// Nothing can be created under a file.
+		let file_path = get_test_audit_log("file").get_path().to_path_buf();
+		fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+		fs::write(&file_path, "").unwrap();
+		let audit_log = AuditLog::new(file_path.join("audit.log"));
+		assert!(audit_log.check_writable().is_err());
+	}
+}
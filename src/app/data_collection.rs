@@ -34,6 +34,8 @@ pub struct Data {
 	pub network: Vec<network::NetworkData>,
 	pub list_of_processes: Vec<processes::ProcessData>,
 	pub grouped_list_of_processes: Option<Vec<processes::ProcessData>>,
+	/// The recent CPU and memory usage of each process, by PID.
+	pub process_history: HashMap<u32, processes::history::ProcessHistory>,
 	pub list_of_disks: Vec<disks::DiskData>, // Only need to keep a list of disks and their data
 }
 
@@ -43,6 +45,9 @@ pub struct DataState {
 	sys: System,
 	stale_max_seconds: u64,
 	prev_pid_stats: HashMap<String, (f64, Instant)>,
+	/// The bytes read and written by each process when last refreshed.
+	prev_pid_io: HashMap<u32, (u64, u64, Instant)>,
+	user_table: processes::users::UserTable,
 	prev_idle: f64,
 	prev_non_idle: f64,
 	prev_net_rx_bytes: u64,
@@ -61,6 +66,8 @@ impl Default for DataState {
 			sys: System::new(),
 			stale_max_seconds: constants::STALE_MAX_MILLISECONDS / 1000,
 			prev_pid_stats: HashMap::new(),
+			prev_pid_io: HashMap::new(),
+			user_table: processes::users::UserTable::default(),
 			prev_idle: 0_f64,
 			prev_non_idle: 0_f64,
 			prev_net_rx_bytes: 0,
@@ -122,17 +129,26 @@ impl DataState {
 			&mem::get_swap_data_list(&current_instant).await,
 			&mut self.data.swap,
 		);
-		set_if_valid(
-			&processes::get_sorted_processes_list(
-				&self.sys,
-				&mut self.prev_idle,
-				&mut self.prev_non_idle,
-				&mut self.prev_pid_stats,
-				self.use_current_cpu_total,
-				&current_instant,
-			),
-			&mut self.data.list_of_processes,
+		let mut process_list = processes::get_sorted_processes_list(
+			&self.sys,
+			&mut self.prev_idle,
+			&mut self.prev_non_idle,
+			&mut self.prev_pid_stats,
+			&mut self.user_table,
+			self.use_current_cpu_total,
+			&current_instant,
 		);
+		if let Ok(process_list) = &mut process_list {
+			processes::set_io_rates(process_list, &mut self.prev_pid_io, &current_instant);
+			processes::history::update_process_history(
+				process_list,
+				&mut self.data.process_history,
+				self.sys.get_total_memory(),
+				self.stale_max_seconds,
+				&current_instant,
+			);
+		}
+		set_if_valid(&process_list, &mut self.data.list_of_processes);
 
 		set_if_valid(
 			&disks::get_disk_usage_list().await,
@@ -166,6 +182,13 @@ impl DataState {
 			for stale in stale_list {
 				self.prev_pid_stats.remove(&stale);
 			}
+			let stale_max_seconds = self.stale_max_seconds;
+			self.prev_pid_io.retain(|_, (_, _, last_time)| {
+				clean_instant.duration_since(*last_time).as_secs() <= stale_max_seconds
+			});
+			self.data.process_history.retain(|_, history| {
+				!processes::history::is_stale(history, stale_max_seconds, &clean_instant)
+			});
 
 			self.data.list_of_cpu_packages = self
 				.data
@@ -1,14 +1,34 @@
 use crate::utils::error;
 use std::cmp::Ordering;
-use std::{collections::HashMap, process::Command, time::Instant};
+use std::{collections::HashMap, time::Instant};
 use sysinfo::{ProcessExt, System, SystemExt};
 
-#[derive(Clone)]
+pub mod container;
+pub mod details;
+pub mod grouping;
+pub mod history;
+pub mod procfs;
+pub mod subtree;
+pub mod threads;
+pub mod users;
+
+#[derive(Clone, PartialEq)]
 pub enum ProcessSorting {
 	CPU,
 	MEM,
 	PID,
 	NAME,
+	COMMAND,
+	USER,
+	STATE,
+	THREADS,
+	RSS,
+	VSZ,
+	NICE,
+	START,
+	READ,
+	WRITE,
+	CONTAINER,
 }
 
 impl Default for ProcessSorting {
@@ -20,11 +40,40 @@ impl Default for ProcessSorting {
 #[derive(Debug, Clone, Default)]
 pub struct ProcessData {
 	pub pid: u32,
+	/// The parent PID, if the process has one.
+	pub ppid: Option<u32>,
 	pub cpu_usage_percent: f64,
 	pub mem_usage_percent: Option<f64>,
 	pub mem_usage_kb: Option<u64>,
 	pub name: String,
+	pub command: String,
 	pub pid_vec: Option<Vec<u32>>,
+	/// The name of the owning user, or their UID if it has no name.
+	pub user: Option<String>,
+	/// The run state, like R (running), S (sleeping), D (uninterruptible), Z (zombie) or T (stopped).
+	pub state: Option<char>,
+	pub threads: Option<u64>,
+	pub rss_bytes: Option<u64>,
+	pub vsz_bytes: Option<u64>,
+	pub priority: Option<i64>,
+	pub nice: Option<i64>,
+	/// When the process started, in seconds since the epoch.
+	pub start_time: Option<u64>,
+	/// When the process started, in clock ticks since boot (field 22 of `/proc/<pid>/stat`).
+	/// Unlike `start_time`, this is precise enough to tell a process apart from one that is
+	/// later given the same PID.  Only known on Linux.
+	pub start_ticks: Option<u64>,
+	/// Bytes read from storage per second since the last refresh.
+	pub read_bytes_per_sec: Option<f64>,
+	/// Bytes written to storage per second since the last refresh.
+	pub write_bytes_per_sec: Option<f64>,
+	/// The path of the executable.
+	pub exe: Option<String>,
+	/// The path of the cgroup the process is in.
+	pub cgroup: Option<String>,
+	/// The container the process runs in (like `docker:0123456789ab`), or else the systemd unit
+	/// it belongs to (like `cron.service`).
+	pub container: Option<String>,
 }
 
 fn cpu_usage_calculation(
@@ -126,28 +175,12 @@ fn get_ordering<T: std::cmp::PartialOrd>(
 	}
 }
 
-fn get_process_cpu_stats(pid: u32) -> std::io::Result<f64> {
-	let mut path = std::path::PathBuf::new();
-	path.push("/proc");
-	path.push(&pid.to_string());
-	path.push("stat");
-
-	let stat_results = std::fs::read_to_string(path)?;
-	let val = stat_results.split_whitespace().collect::<Vec<&str>>();
-	let utime = val[13].parse::<f64>().unwrap_or(0_f64);
-	let stime = val[14].parse::<f64>().unwrap_or(0_f64);
-
-	//debug!("PID: {}, utime: {}, stime: {}", pid, utime, stime);
-
-	Ok(utime + stime) // This seems to match top...
-}
-
 /// Note that cpu_percentage should be represented WITHOUT the \times 100 factor!
 fn linux_cpu_usage(
-	pid: u32, cpu_usage: f64, cpu_percentage: f64,
+	pid: u32, after_proc_val: f64, cpu_usage: f64, cpu_percentage: f64,
 	previous_pid_stats: &mut HashMap<String, (f64, Instant)>, use_current_cpu_total: bool,
 	curr_time: &Instant,
-) -> std::io::Result<f64> {
+) -> f64 {
 	// Based heavily on https://stackoverflow.com/a/23376195 and https://stackoverflow.com/a/1424556
 	let before_proc_val: f64 = if previous_pid_stats.contains_key(&pid.to_string()) {
 		previous_pid_stats
@@ -157,8 +190,6 @@ fn linux_cpu_usage(
 	} else {
 		0_f64
 	};
-	let after_proc_val = get_process_cpu_stats(pid)?;
-
 	/*debug!(
 		"PID - {} - Before: {}, After: {}, CPU: {}, Percentage: {}",
 		pid,
@@ -173,91 +204,101 @@ fn linux_cpu_usage(
 		.or_insert((after_proc_val, *curr_time));
 	*entry = (after_proc_val, *curr_time);
 	if use_current_cpu_total {
-		Ok((after_proc_val - before_proc_val) / cpu_usage * 100_f64)
+		(after_proc_val - before_proc_val) / cpu_usage * 100_f64
 	} else {
-		Ok((after_proc_val - before_proc_val) / cpu_usage * 100_f64 * cpu_percentage)
+		(after_proc_val - before_proc_val) / cpu_usage * 100_f64 * cpu_percentage
 	}
 }
 
-fn convert_ps(
-	process: &str, cpu_usage: f64, cpu_percentage: f64,
-	prev_pid_stats: &mut HashMap<String, (f64, Instant)>, use_current_cpu_total: bool,
-	curr_time: &Instant,
-) -> std::io::Result<ProcessData> {
-	if process.trim().to_string().is_empty() {
-		return Ok(ProcessData {
-			pid: 0,
-			name: "".to_string(),
-			mem_usage_percent: None,
-			mem_usage_kb: None,
-			cpu_usage_percent: 0_f64,
-			pid_vec: None,
-		});
-	}
+/// System-wide values needed to convert what is read from procfs.
+struct ProcfsContext<'a> {
+	mem_total_kb: u64,
+	page_size_kb: u64,
+	boot_time: Option<u64>,
+	clock_ticks_per_second: u64,
+	user_table: &'a users::UserTable,
+}
 
-	let pid = (&process[..11])
-		.trim()
-		.to_string()
-		.parse::<u32>()
-		.unwrap_or(0);
-	let name = (&process[11..61]).trim().to_string();
-	let mem_usage_percent = Some(
-		(&process[62..])
-			.trim()
-			.to_string()
-			.parse::<f64>()
-			.unwrap_or(0_f64),
-	);
-
-	Ok(ProcessData {
-		pid,
-		name,
-		mem_usage_percent,
-		mem_usage_kb: None,
-		cpu_usage_percent: linux_cpu_usage(
-			pid,
-			cpu_usage,
-			cpu_percentage,
-			prev_pid_stats,
-			use_current_cpu_total,
-			curr_time,
-		)?,
+fn convert_procfs_process(
+	process: procfs::ProcProcess, cpu_usage_percent: f64, context: &ProcfsContext<'_>,
+) -> ProcessData {
+	let rss_kb = process.statm.resident * context.page_size_kb;
+	let start_ticks = process.stat.start_time;
+
+	ProcessData {
+		pid: process.stat.pid,
+		ppid: Some(process.stat.ppid).filter(|ppid| *ppid != 0),
+		name: process.stat.comm,
+		command: process.command,
+		mem_usage_percent: Some(if context.mem_total_kb > 0 {
+			rss_kb as f64 / context.mem_total_kb as f64 * 100_f64
+		} else {
+			0_f64
+		}),
+		mem_usage_kb: Some(rss_kb),
+		cpu_usage_percent,
 		pid_vec: None,
-	})
+		user: Some(context.user_table.get_user_name(process.status.uid)),
+		state: Some(process.stat.state),
+		threads: Some(process.stat.num_threads),
+		rss_bytes: Some(rss_kb * 1024),
+		vsz_bytes: Some(process.statm.size * context.page_size_kb * 1024),
+		priority: Some(process.stat.priority),
+		nice: Some(process.stat.nice),
+		start_time: context
+			.boot_time
+			.map(|boot_time| boot_time + start_ticks / context.clock_ticks_per_second),
+		start_ticks: Some(start_ticks),
+		// These are worked out afterwards by set_io_rates.
+		read_bytes_per_sec: None,
+		write_bytes_per_sec: None,
+		exe: process.exe,
+		cgroup: process.cgroup,
+		container: process.container,
+	}
 }
 
 pub fn get_sorted_processes_list(
 	sys: &System, prev_idle: &mut f64, prev_non_idle: &mut f64,
 	prev_pid_stats: &mut std::collections::HashMap<String, (f64, Instant)>,
-	use_current_cpu_total: bool, curr_time: &Instant,
+	user_table: &mut users::UserTable, use_current_cpu_total: bool, curr_time: &Instant,
 ) -> crate::utils::error::Result<Vec<ProcessData>> {
 	let mut process_vector: Vec<ProcessData> = Vec::new();
 
 	if cfg!(target_os = "linux") {
 		// Linux specific - this is a massive pain... ugh.
 
-		let ps_result = Command::new("ps")
-			.args(&["-axo", "pid:10,comm:50,%mem:5", "--noheader"])
-			.output()?;
-		let ps_stdout = String::from_utf8_lossy(&ps_result.stdout);
-		let split_string = ps_stdout.split('\n');
-		//debug!("{:?}", split_string);
 		let cpu_calc = cpu_usage_calculation(prev_idle, prev_non_idle);
 		if let Ok((cpu_usage, cpu_percentage)) = cpu_calc {
-			let process_stream = split_string.collect::<Vec<&str>>();
-
-			for process in process_stream {
-				if let Ok(process_object) = convert_ps(
-					process,
-					cpu_usage,
-					cpu_percentage,
-					prev_pid_stats,
-					use_current_cpu_total,
-					curr_time,
-				) {
-					if !process_object.name.is_empty() {
-						process_vector.push(process_object);
-					}
+			user_table.refresh();
+			let context = ProcfsContext {
+				mem_total_kb: sys.get_total_memory(),
+				page_size_kb: procfs::get_page_size_kb(),
+				boot_time: std::fs::read_to_string("/proc/stat")
+					.ok()
+					.and_then(|system_stat| procfs::parse_boot_time(&system_stat)),
+				clock_ticks_per_second: procfs::get_clock_ticks_per_second(),
+				user_table,
+			};
+
+			for pid in procfs::get_pids()? {
+				// Processes can exit while we read them, so just skip any we can't read.
+				let process_path = std::path::PathBuf::from("/proc").join(pid.to_string());
+				if let Ok(process) = procfs::read_process(&process_path) {
+					let cpu_usage_percent = linux_cpu_usage(
+						pid,
+						(process.stat.utime + process.stat.stime) as f64, // This seems to match top...
+						cpu_usage,
+						cpu_percentage,
+						prev_pid_stats,
+						use_current_cpu_total,
+						curr_time,
+					);
+					process_vector.push(convert_procfs_process(
+						process,
+						cpu_usage_percent,
+						&context,
+					));
 				}
 			}
 		} else {
@@ -290,11 +331,18 @@ pub fn get_sorted_processes_list(
 
 			process_vector.push(ProcessData {
 				pid: process_val.pid() as u32,
+				ppid: process_val.parent().map(|ppid| ppid as u32),
 				name,
+				command: process_val.cmd().join(" "),
 				mem_usage_percent: None,
 				mem_usage_kb: Some(process_val.memory()),
 				cpu_usage_percent: f64::from(process_val.cpu_usage()),
 				pid_vec: None,
+				rss_bytes: Some(process_val.memory() * 1024),
+				start_time: Some(process_val.start_time()),
+				exe: Some(process_val.exe().to_string_lossy().into_owned())
+					.filter(|exe| !exe.is_empty()),
+				..ProcessData::default()
 			});
 		}
 	}
@@ -302,6 +350,70 @@ pub fn get_sorted_processes_list(
 	Ok(process_vector)
 }
 
+/// Whether a process is still running, and hasn't been replaced by another process given the
+/// same PID if when it started is known.  On Linux this is read straight from procfs, as the
+/// processes that were last collected may have been collected before it was signalled; elsewhere
+/// it is whether it is among them.
+pub fn is_process_running(
+	pid: u32, start_ticks: Option<u64>, list_of_processes: &[ProcessData],
+) -> bool {
+	if cfg!(target_os = "linux") {
+		procfs::read_stat(&std::path::PathBuf::from("/proc").join(pid.to_string())).map_or(
+			false,
+			|stat| {
+				stat.state != 'Z'
+					&& start_ticks.map_or(true, |start_ticks| start_ticks == stat.start_time)
+			},
+		)
+	} else {
+		list_of_processes.iter().any(|process| process.pid == pid)
+	}
+}
+
+/// When a running process started, in clock ticks since boot; see `ProcessData::start_ticks`.
+/// Only supported on Linux.
+pub fn get_start_ticks(pid: u32) -> Option<u64> {
+	if cfg!(target_os = "linux") {
+		procfs::read_stat(&std::path::PathBuf::from("/proc").join(pid.to_string()))
+			.map(|stat| stat.start_time)
+	} else {
+		None
+	}
+}
+
+/// Works out how fast each process is reading from and writing to storage, from how much its
+/// I/O counters have gone up since the last refresh.  The rates are left empty for processes
+/// seen for the first time, and for those whose counters can't be read (such as those of
+/// other users).  Only supported on Linux.
+pub fn set_io_rates(
+	process_vector: &mut [ProcessData], prev_pid_io: &mut HashMap<u32, (u64, u64, Instant)>,
+	curr_time: &Instant,
+) {
+	if !cfg!(target_os = "linux") {
+		return;
+	}
+
+	for process in process_vector {
+		let process_path = std::path::PathBuf::from("/proc").join(process.pid.to_string());
+		if let Ok(io) = procfs::read_io(&process_path) {
+			if let Some((prev_read_bytes, prev_write_bytes, prev_time)) =
+				prev_pid_io.get(&process.pid)
+			{
+				let elapsed_secs = curr_time.duration_since(*prev_time).as_secs_f64();
+				if elapsed_secs > 0_f64 {
+					// The counters only go down if the PID was reused by a new process.
+					process.read_bytes_per_sec =
+						Some(io.read_bytes.saturating_sub(*prev_read_bytes) as f64 / elapsed_secs);
+					process.write_bytes_per_sec = Some(
+						io.write_bytes.saturating_sub(*prev_write_bytes) as f64 / elapsed_secs,
+					);
+				}
+			}
+			prev_pid_io.insert(process.pid, (io.read_bytes, io.write_bytes, *curr_time));
+		}
+	}
+}
+
 pub fn sort_processes(
 	process_vector: &mut Vec<ProcessData>, sorting_method: &ProcessSorting, reverse_order: bool,
 ) {
@@ -325,5 +437,38 @@ pub fn sort_processes(
 		ProcessSorting::NAME => {
 			process_vector.sort_by(|a, b| get_ordering(&a.name, &b.name, reverse_order))
 		}
+		ProcessSorting::COMMAND => {
+			process_vector.sort_by(|a, b| get_ordering(&a.command, &b.command, reverse_order))
+		}
+		ProcessSorting::USER => {
+			process_vector.sort_by(|a, b| get_ordering(&a.user, &b.user, reverse_order))
+		}
+		ProcessSorting::STATE => {
+			process_vector.sort_by(|a, b| get_ordering(a.state, b.state, reverse_order))
+		}
+		ProcessSorting::THREADS => {
+			process_vector.sort_by(|a, b| get_ordering(a.threads, b.threads, reverse_order))
+		}
+		ProcessSorting::RSS => {
+			process_vector.sort_by(|a, b| get_ordering(a.rss_bytes, b.rss_bytes, reverse_order))
+		}
+		ProcessSorting::VSZ => {
+			process_vector.sort_by(|a, b| get_ordering(a.vsz_bytes, b.vsz_bytes, reverse_order))
+		}
+		ProcessSorting::NICE => process_vector.sort_by(|a, b| {
+			get_ordering((a.nice, a.priority), (b.nice, b.priority), reverse_order)
+		}),
+		ProcessSorting::START => {
+			process_vector.sort_by(|a, b| get_ordering(a.start_time, b.start_time, reverse_order))
+		}
+		ProcessSorting::READ => process_vector.sort_by(|a, b| {
+			get_ordering(a.read_bytes_per_sec, b.read_bytes_per_sec, reverse_order)
+		}),
+		ProcessSorting::WRITE => process_vector.sort_by(|a, b| {
+			get_ordering(a.write_bytes_per_sec, b.write_bytes_per_sec, reverse_order)
+		}),
+		ProcessSorting::CONTAINER => {
+			process_vector.sort_by(|a, b| get_ordering(&a.container, &b.container, reverse_order))
+		}
 	}
 }
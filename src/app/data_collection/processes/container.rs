@@ -0,0 +1,197 @@
+//! Works out which container or systemd unit a process belongs to from its cgroups, as read from
+//! `/proc/<pid>/cgroup`.  Container runtimes and systemd both name cgroups after what is in them,
+//! and this holds for cgroup v1 and v2 alike; only where in the path the name is differs.
+
+use super::procfs::{self, ProcCgroup};
+
+/// How many characters of a container ID are shown, as `docker ps` does.
+const SHORT_ID_LENGTH: usize = 12;
+
+/// Cgroup names that hold a container, by the prefix before the ID and the runtime they belong
+/// to.  These are the names used with the systemd cgroup driver, like `docker-<id>.scope`.
+const SCOPE_PREFIXES: [(&str, &str); 5] = [
+	("docker-", "docker"),
+	("libpod-", "podman"),
+	("cri-containerd-", "containerd"),
+	("crio-", "cri-o"),
+	("containerd-", "containerd"),
+];
+
+/// Whether a cgroup name is a full container ID, which is 64 hexadecimal characters.
+fn is_container_id(name: &str) -> bool {
+	name.len() == 64 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn format_container(runtime: &str, id: &str) -> String {
+	format!(
+		"{}:{}",
+		runtime,
+		id.chars().take(SHORT_ID_LENGTH).collect::<String>()
+	)
+}
+
+/// Finds the container in a cgroup path, like `docker:0123456789ab`.  The innermost container
+/// is picked, for containers that are nested.
+fn get_container_from_path(path: &str) -> Option<String> {
+	let names = path
+		.split('/')
+		.filter(|name| !name.is_empty())
+		.collect::<Vec<_>>();
+
+	names.iter().enumerate().rev().find_map(|(index, name)| {
+		let scope_name = name.trim_end_matches(".scope");
+		if let Some((id, runtime)) = SCOPE_PREFIXES
+			.iter()
+			.find_map(|(prefix, runtime)| scope_name.strip_prefix(prefix).map(|id| (id, *runtime)))
+		{
+			// The conmon monitor of a podman or cri-o container sits next to it, but isn't in it.
+			return if is_container_id(id) {
+				Some(format_container(runtime, id))
+			} else {
+				None
+			};
+		}
+
+		// With the cgroupfs driver, a container is just its ID under a cgroup of its runtime.
+		if is_container_id(name) {
+			let runtime = names[..index]
+				.iter()
+				.find_map(|parent| match *parent {
+					"docker" => Some("docker"),
+					"libpod_parent" => Some("podman"),
+					parent if parent.starts_with("kubepods") => Some("kubepods"),
+					_ => None,
+				})
+				.unwrap_or("container");
+			return Some(format_container(runtime, name));
+		}
+
+		// LXC names its containers rather than giving them IDs.
+		if let Some(lxc_name) = name.strip_prefix("lxc.payload.") {
+			return Some(format!("lxc:{}", lxc_name));
+		}
+		if index > 0 && names[index - 1] == "lxc" {
+			return Some(format!("lxc:{}", name));
+		}
+
+		None
+	})
+}
+
+/// Finds the systemd unit in a cgroup path, which is the innermost service or scope.
+fn get_unit_from_path(path: &str) -> Option<String> {
+	path.split('/')
+		.rev()
+		.find(|name| name.ends_with(".service") || name.ends_with(".scope"))
+		.map(String::from)
+}
+
+/// Works out the container a process runs in, like `docker:0123456789ab`, or failing that the
+/// systemd unit it belongs to, like `cron.service`.  Any cgroup may show a container (with cgroup
+/// v1, only some hierarchies might), while the unit is taken from the main one; see
+/// `procfs::get_main_cgroup_path`.
+pub fn get_container(cgroups: &[ProcCgroup]) -> Option<String> {
+	let main_path = procfs::get_main_cgroup_path(cgroups);
+	main_path
+		.into_iter()
+		.chain(cgroups.iter().map(|cgroup| cgroup.path.as_str()))
+		.find_map(get_container_from_path)
+		.or_else(|| main_path.and_then(get_unit_from_path))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const DOCKER_ID: &str = "3f4e5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f";
+
+	fn get_fixture_container(cgroup: &str) -> Option<String> {
+		get_container(&procfs::parse_cgroup(cgroup))
+	}
+
+	#[test]
+	fn test_systemd_unit() {
+		assert_eq!(
+			get_fixture_container(include_str!("../../../../tests/process_fixtures/cgroup")),
+			Some("session-2.scope".to_string())
+		);
+		assert_eq!(
+			get_fixture_container(include_str!(
+				"../../../../tests/process_fixtures/cgroup_v2_user_service"
+			)),
+			Some("vte-spawn-1a2b.scope".to_string())
+		);
+		assert_eq!(
+			get_fixture_container("0::/system.slice/cron.service\n"),
+			Some("cron.service".to_string())
+		);
+	}
+
+	#[test]
+	fn test_docker_container() {
+		// cgroup v1, with the cgroupfs driver.
+		assert_eq!(
+			get_fixture_container(include_str!(
+				"../../../../tests/process_fixtures/cgroup_v1_docker"
+			)),
+			Some("docker:3f4e5a6b7c8d".to_string())
+		);
+		// cgroup v2, with the systemd driver.
+		assert_eq!(
+			get_fixture_container(include_str!(
+				"../../../../tests/process_fixtures/cgroup_v2_docker"
+			)),
+			Some("docker:3f4e5a6b7c8d".to_string())
+		);
+		assert_eq!(
+			get_fixture_container(&format!("0::/docker/{}\n", DOCKER_ID)),
+			Some("docker:3f4e5a6b7c8d".to_string())
+		);
+	}
+
+	#[test]
+	fn test_other_runtimes() {
+		assert_eq!(
+			get_fixture_container(include_str!(
+				"../../../../tests/process_fixtures/cgroup_v2_podman"
+			)),
+			Some("podman:9a8b7c6d5e4f".to_string())
+		);
+		assert_eq!(
+			get_fixture_container(include_str!(
+				"../../../../tests/process_fixtures/cgroup_v2_kubernetes"
+			)),
+			Some("containerd:0a1b2c3d4e5f".to_string())
+		);
+		assert_eq!(
+			get_fixture_container(include_str!(
+				"../../../../tests/process_fixtures/cgroup_v1_kubernetes"
+			)),
+			Some("kubepods:0a1b2c3d4e5f".to_string())
+		);
+		assert_eq!(
+			get_fixture_container("0::/lxc.payload.web/system.slice/nginx.service\n"),
+			Some("lxc:web".to_string())
+		);
+		assert_eq!(
+			get_fixture_container("4:pids:/lxc/web\n"),
+			Some("lxc:web".to_string())
+		);
+	}
+
+	#[test]
+	fn test_not_in_a_container() {
+		// The monitor of a podman container isn't in the container itself.
+		assert_eq!(
+			get_fixture_container(&format!(
+				"0::/machine.slice/libpod-conmon-{}.scope\n",
+				DOCKER_ID
+			)),
+			Some(format!("libpod-conmon-{}.scope", DOCKER_ID))
+		);
+		// Inside a container, its cgroup is usually all that can be seen.
+		assert_eq!(get_fixture_container("0::/\n"), None);
+		assert_eq!(get_fixture_container("4:cpu,cpuacct:/user.slice\n"), None);
+		assert_eq!(get_fixture_container(""), None);
+	}
+}
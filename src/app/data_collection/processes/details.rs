@@ -0,0 +1,282 @@
+//! Reads everything about a single process that is shown in the process detail pane.  Each part
+//! is read on its own, so a part that can't be read (usually because the process belongs to
+//! another user) doesn't stop the rest from being shown.
+
+use std::{
+	collections::HashSet,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use super::procfs::{self, ProcCgroup, ProcStat};
+
+/// A summary of what is mapped into the memory of a process, from `/proc/<pid>/maps`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MapsSummary {
+	pub num_mappings: usize,
+	pub total_bytes: u64,
+	/// Mapped from files, like the executable and its libraries.
+	pub file_bytes: u64,
+	/// The number of different files mapped.
+	pub num_files: usize,
+	pub anonymous_bytes: u64,
+	pub heap_bytes: u64,
+	pub stack_bytes: u64,
+}
+
+/// A line of `/proc/<pid>/limits`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcLimit {
+	pub name: String,
+	pub soft: String,
+	pub hard: String,
+	/// Empty for limits without units, like the nice priority.
+	pub units: String,
+}
+
+/// An open file descriptor, and the file, socket or pipe it refers to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcFd {
+	pub fd: u32,
+	pub target: String,
+}
+
+#[derive(Debug)]
+pub struct ProcessDetails {
+	pub pid: u32,
+	pub stat: io::Result<ProcStat>,
+	pub command: io::Result<String>,
+	pub exe: io::Result<PathBuf>,
+	pub cwd: io::Result<PathBuf>,
+	pub environ: io::Result<Vec<String>>,
+	pub fds: io::Result<Vec<ProcFd>>,
+	pub maps: io::Result<MapsSummary>,
+	pub limits: io::Result<Vec<ProcLimit>>,
+	pub cgroups: io::Result<Vec<ProcCgroup>>,
+	/// The PIDs and names of the ancestors of the process, starting from the furthest one.
+	pub parent_chain: Vec<(u32, String)>,
+}
+
+/// Unlike the command line, each variable is kept apart, as values can hold spaces.
+pub fn parse_environ(environ: &[u8]) -> Vec<String> {
+	environ
+		.split(|byte| *byte == 0)
+		.filter(|var| !var.is_empty())
+		.map(|var| String::from_utf8_lossy(var).into_owned())
+		.collect()
+}
+
+/// Each line is `start-end perms offset dev inode path`, where the path is left out for
+/// anonymous memory, and is in brackets for special mappings like the heap and stack.
+pub fn parse_maps(maps: &str) -> MapsSummary {
+	let mut summary = MapsSummary::default();
+	let mut files = HashSet::new();
+
+	for line in maps.lines() {
+		let mut fields = line.split_whitespace();
+		let size = match fields.next().and_then(|range| {
+			let mut bounds = range.splitn(2, '-');
+			let start = u64::from_str_radix(bounds.next()?, 16).ok()?;
+			let end = u64::from_str_radix(bounds.next()?, 16).ok()?;
+			end.checked_sub(start)
+		}) {
+			Some(size) => size,
+			None => continue,
+		};
+		// Paths can hold spaces, so the path is whatever is left after the first five fields.
+		let mut path = line;
+		for _ in 0..5 {
+			path = path.trim_start();
+			path = &path[path.find(char::is_whitespace).unwrap_or(path.len())..];
+		}
+		let path = Some(path.trim()).filter(|path| !path.is_empty());
+
+		summary.num_mappings += 1;
+		summary.total_bytes += size;
+		match path {
+			None => summary.anonymous_bytes += size,
+			Some("[heap]") => summary.heap_bytes += size,
+			Some(path) if path.starts_with("[stack") => summary.stack_bytes += size,
+			// Other special mappings, like the vDSO, are only counted in the total.
+			Some(path) if path.starts_with('[') => {}
+			Some(path) => {
+				summary.file_bytes += size;
+				files.insert(path);
+			}
+		}
+	}
+
+	summary.num_files = files.len();
+	summary
+}
+
+/// The file is a table, where the name takes up the first 26 columns and the values are split
+/// by spaces.
+pub fn parse_limits(limits: &str) -> Vec<ProcLimit> {
+	const NAME_WIDTH: usize = 26;
+
+	limits
+		.lines()
+		.skip(1)
+		.filter_map(|line| {
+			let name = line.get(..NAME_WIDTH)?.trim();
+			let mut values = line.get(NAME_WIDTH..)?.split_whitespace();
+			Some(ProcLimit {
+				name: name.to_string(),
+				soft: values.next()?.to_string(),
+				hard: values.next()?.to_string(),
+				units: values.next().unwrap_or_default().to_string(),
+			})
+		})
+		.collect()
+}
+
+fn read_stat(pid_dir: &Path) -> io::Result<ProcStat> {
+	procfs::parse_stat(&String::from_utf8_lossy(&fs::read(pid_dir.join("stat"))?)).ok_or_else(
+		|| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("Unable to parse {}", pid_dir.join("stat").display()),
+			)
+		},
+	)
+}
+
+fn read_fds(pid_dir: &Path) -> io::Result<Vec<ProcFd>> {
+	let mut fds = fs::read_dir(pid_dir.join("fd"))?
+		.filter_map(|entry| {
+			let entry = entry.ok()?;
+			let fd = entry.file_name().to_str()?.parse().ok()?;
+			// The file may have been closed since the directory was read.
+			let target = fs::read_link(entry.path()).ok()?;
+			Some(ProcFd {
+				fd,
+				target: target.to_string_lossy().into_owned(),
+			})
+		})
+		.collect::<Vec<_>>();
+	fds.sort_by_key(|fd| fd.fd);
+	Ok(fds)
+}
+
+/// Follows the parent PIDs up to the root of the tree, stopping early if a parent can't be read
+/// (such as if it has just exited).
+fn read_parent_chain(proc_dir: &Path, ppid: u32) -> Vec<(u32, String)> {
+	let mut parent_chain = Vec::new();
+	let mut seen_pids = HashSet::new();
+	let mut ppid = ppid;
+
+	while ppid != 0 && seen_pids.insert(ppid) {
+		match read_stat(&proc_dir.join(ppid.to_string())) {
+			Ok(stat) => {
+				ppid = stat.ppid;
+				parent_chain.push((stat.pid, stat.comm));
+			}
+			Err(_) => break,
+		}
+	}
+
+	parent_chain.reverse();
+	parent_chain
+}
+
+/// Reads the details of a process from a procfs mounted at `proc_dir`.
+fn read_process_details_from(proc_dir: &Path, pid: u32) -> ProcessDetails {
+	let pid_dir = proc_dir.join(pid.to_string());
+	let stat = read_stat(&pid_dir);
+	let parent_chain = match &stat {
+		Ok(stat) => read_parent_chain(proc_dir, stat.ppid),
+		Err(_) => Vec::new(),
+	};
+
+	ProcessDetails {
+		pid,
+		command: fs::read(pid_dir.join("cmdline")).map(|cmdline| procfs::parse_cmdline(&cmdline)),
+		exe: fs::read_link(pid_dir.join("exe")),
+		cwd: fs::read_link(pid_dir.join("cwd")),
+		environ: fs::read(pid_dir.join("environ")).map(|environ| parse_environ(&environ)),
+		fds: read_fds(&pid_dir),
+		maps: fs::read(pid_dir.join("maps"))
+			.map(|maps| parse_maps(&String::from_utf8_lossy(&maps))),
+		limits: fs::read_to_string(pid_dir.join("limits")).map(|limits| parse_limits(&limits)),
+		cgroups: fs::read_to_string(pid_dir.join("cgroup"))
+			.map(|cgroup| procfs::parse_cgroup(&cgroup)),
+		stat,
+		parent_chain,
+	}
+}
+
+/// Reads the details of a process.  These are only available on Linux; elsewhere, every part
+/// fails as if the process did not exist.
+pub fn read_process_details(pid: u32) -> ProcessDetails {
+	read_process_details_from(Path::new("/proc"), pid)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_environ() {
+		assert_eq!(
+			parse_environ(include_bytes!("../../../../tests/process_fixtures/environ")),
+			vec![
+				"HOME=/home/alice",
+				"LANG=en_GB.UTF-8",
+				"PATH=/usr/local/bin:/usr/bin"
+			]
+		);
+		assert!(parse_environ(b"").is_empty());
+	}
+
+	#[test]
+	fn test_parse_maps() {
+		assert_eq!(
+			parse_maps(include_str!("../../../../tests/process_fixtures/maps")),
+			MapsSummary {
+				num_mappings: 11,
+				total_bytes: 0x004d_4000,
+				file_bytes: 0x0030_8000,
+				num_files: 3,
+				anonymous_bytes: 0x0002_1000,
+				heap_bytes: 0x0018_4000,
+				stack_bytes: 0x0002_1000,
+			}
+		);
+		assert_eq!(parse_maps("not a mapping\n"), MapsSummary::default());
+	}
+
+	#[test]
+	fn test_parse_limits() {
+		let limits = parse_limits(include_str!("../../../../tests/process_fixtures/limits"));
+		assert_eq!(limits.len(), 16);
+		assert_eq!(
+			limits[7],
+			ProcLimit {
+				name: "Max open files".to_string(),
+				soft: "1024".to_string(),
+				hard: "524288".to_string(),
+				units: "files".to_string(),
+			}
+		);
+		assert_eq!(limits[13].name, "Max nice priority");
+		assert_eq!(limits[13].units, "");
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn test_read_own_process_details() {
+		let details = read_process_details(std::process::id());
+		assert_eq!(details.stat.unwrap().pid, std::process::id());
+		assert!(details.exe.is_ok());
+		assert!(!details.fds.unwrap().is_empty());
+		assert!(details.maps.unwrap().num_mappings > 0);
+	}
+
+	#[test]
+	fn test_read_missing_process_details() {
+		let details = read_process_details_from(Path::new("/nonexistent"), 1234);
+		assert_eq!(details.stat.unwrap_err().kind(), io::ErrorKind::NotFound);
+		assert!(details.parent_chain.is_empty());
+	}
+}
@@ -0,0 +1,382 @@
+//! Combines processes that share something (like their name or user) into one entry each, with
+//! the usage of all the processes in it added up.
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::ProcessData;
+
+/// What processes are grouped together by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProcessGrouping {
+	Name,
+	User,
+	ParentPid,
+	Exe,
+	Cgroup,
+	Container,
+}
+
+impl ProcessGrouping {
+	/// The grouping that comes after this one when cycling through them, or `None` after the
+	/// last one, to go back to not grouping.
+	pub fn next(self) -> Option<ProcessGrouping> {
+		match self {
+			ProcessGrouping::Name => Some(ProcessGrouping::User),
+			ProcessGrouping::User => Some(ProcessGrouping::ParentPid),
+			ProcessGrouping::ParentPid => Some(ProcessGrouping::Exe),
+			ProcessGrouping::Exe => Some(ProcessGrouping::Cgroup),
+			ProcessGrouping::Cgroup => Some(ProcessGrouping::Container),
+			ProcessGrouping::Container => None,
+		}
+	}
+
+	/// What the groups are called, for the column they are shown in.
+	pub fn column_name(self) -> &'static str {
+		match self {
+			ProcessGrouping::Name => "Name",
+			ProcessGrouping::User => "User",
+			ProcessGrouping::ParentPid => "Parent",
+			ProcessGrouping::Exe => "Executable",
+			ProcessGrouping::Cgroup => "Cgroup",
+			ProcessGrouping::Container => "Container",
+		}
+	}
+}
+
+/// Shown in place of a group key that couldn't be read, such as the executable of a process
+/// that belongs to another user.
+const UNKNOWN_KEY: &str = "<unknown>";
+
+fn get_group_key(
+	process: &ProcessData, grouping: ProcessGrouping, use_full_command: bool,
+	names: &HashMap<u32, &str>,
+) -> String {
+	match grouping {
+		ProcessGrouping::Name if use_full_command => process.command.clone(),
+		ProcessGrouping::Name => process.name.clone(),
+		ProcessGrouping::User => process.user.as_deref().unwrap_or(UNKNOWN_KEY).to_string(),
+		ProcessGrouping::ParentPid => match process.ppid {
+			Some(ppid) => match names.get(&ppid) {
+				Some(name) => format!("{} ({})", ppid, name),
+				None => ppid.to_string(),
+			},
+			None => "<none>".to_string(),
+		},
+		ProcessGrouping::Exe => process.exe.as_deref().unwrap_or(UNKNOWN_KEY).to_string(),
+		ProcessGrouping::Cgroup => process.cgroup.as_deref().unwrap_or(UNKNOWN_KEY).to_string(),
+		// Processes outside of any container or unit are told apart from those whose cgroup
+		// couldn't be read.
+		ProcessGrouping::Container => match (&process.container, &process.cgroup) {
+			(Some(container), _) => container.clone(),
+			(None, Some(_)) => "<none>".to_string(),
+			(None, None) => UNKNOWN_KEY.to_string(),
+		},
+	}
+}
+
+/// Adds a value to a total, where the total is only left empty if every value is unknown.
+fn add_known<T: std::ops::Add<Output = T> + Copy>(total: &mut Option<T>, value: Option<T>) {
+	if let Some(value) = value {
+		*total = Some(total.map_or(value, |total| total + value));
+	}
+}
+
+/// Groups the processes that `is_match` accepts.  Each group is named after what its processes
+/// share, holds the PIDs of exactly those processes, and adds up their CPU, memory, thread and
+/// disk usage.  A group has a user if all of its processes have the same one, so that groups
+/// can be sorted by user.  When grouping by name, `use_full_command` groups by the full command
+/// instead.
+pub fn group_processes(
+	processes: &[ProcessData], grouping: ProcessGrouping, use_full_command: bool,
+	is_match: impl Fn(&ProcessData) -> bool,
+) -> Vec<ProcessData> {
+	// Parents are named even if they themselves don't match.
+	let names: HashMap<u32, &str> = if let ProcessGrouping::ParentPid = grouping {
+		processes
+			.iter()
+			.map(|process| (process.pid, process.name.as_str()))
+			.collect()
+	} else {
+		HashMap::new()
+	};
+
+	let mut groups: BTreeMap<String, ProcessData> = BTreeMap::new();
+	for process in processes.iter().filter(|process| is_match(process)) {
+		let key = get_group_key(process, grouping, use_full_command, &names);
+		let group = groups.entry(key).or_insert_with(|| ProcessData {
+			pid_vec: Some(Vec::new()),
+			user: process.user.clone(),
+			..ProcessData::default()
+		});
+		if group.user != process.user {
+			group.user = None;
+		}
+
+		group.cpu_usage_percent += process.cpu_usage_percent;
+		add_known(&mut group.mem_usage_percent, process.mem_usage_percent);
+		add_known(&mut group.mem_usage_kb, process.mem_usage_kb);
+		add_known(&mut group.rss_bytes, process.rss_bytes);
+		add_known(&mut group.vsz_bytes, process.vsz_bytes);
+		add_known(&mut group.threads, process.threads);
+		add_known(&mut group.read_bytes_per_sec, process.read_bytes_per_sec);
+		add_known(&mut group.write_bytes_per_sec, process.write_bytes_per_sec);
+		if let Some(pid_vec) = &mut group.pid_vec {
+			pid_vec.push(process.pid);
+		}
+	}
+
+	groups
+		.into_iter()
+		.map(|(key, group)| ProcessData {
+			name: key.clone(),
+			command: key,
+			..group
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::{sort_processes, ProcessSorting};
+	use super::*;
+
+	fn get_test_processes() -> Vec<ProcessData> {
+		vec![
+			ProcessData {
+				pid: 1,
+				name: "systemd".to_string(),
+				command: "/sbin/init".to_string(),
+				cpu_usage_percent: 0.5,
+				mem_usage_percent: Some(1.0),
+				user: Some("root".to_string()),
+				threads: Some(1),
+				exe: Some("/usr/lib/systemd/systemd".to_string()),
+				cgroup: Some("/init.scope".to_string()),
+				container: Some("init.scope".to_string()),
+				..ProcessData::default()
+			},
+			ProcessData {
+				pid: 100,
+				ppid: Some(1),
+				name: "bash".to_string(),
+				command: "bash --login".to_string(),
+				cpu_usage_percent: 1.0,
+				mem_usage_percent: Some(2.0),
+				user: Some("alice".to_string()),
+				threads: Some(1),
+				vsz_bytes: Some(8_000),
+				read_bytes_per_sec: Some(100.0),
+				exe: Some("/usr/bin/bash".to_string()),
+				cgroup: Some("/user.slice/user-1000.slice/session-2.scope".to_string()),
+				container: Some("session-2.scope".to_string()),
+				..ProcessData::default()
+			},
+			ProcessData {
+				pid: 200,
+				ppid: Some(100),
+				name: "bash".to_string(),
+				command: "bash".to_string(),
+				cpu_usage_percent: 2.0,
+				mem_usage_percent: Some(3.0),
+				user: Some("alice".to_string()),
+				threads: Some(1),
+				vsz_bytes: Some(4_000),
+				exe: Some("/usr/bin/bash".to_string()),
+				cgroup: Some("/user.slice/user-1000.slice/session-2.scope".to_string()),
+				..ProcessData::default()
+			},
+			ProcessData {
+				pid: 300,
+				ppid: Some(100),
+				name: "vim".to_string(),
+				command: "vim notes.txt".to_string(),
+				cpu_usage_percent: 4.0,
+				mem_usage_percent: Some(5.0),
+				user: Some("alice".to_string()),
+				threads: Some(2),
+				read_bytes_per_sec: Some(50.0),
+				..ProcessData::default()
+			},
+		]
+	}
+
+	fn get_summary(groups: &[ProcessData]) -> Vec<(&str, Vec<u32>)> {
+		groups
+			.iter()
+			.map(|group| (group.name.as_str(), group.pid_vec.clone().unwrap()))
+			.collect()
+	}
+
+	#[test]
+	fn test_group_by_name() {
+		let groups = group_processes(&get_test_processes(), ProcessGrouping::Name, false, |_| {
+			true
+		});
+		assert_eq!(
+			get_summary(&groups),
+			vec![
+				("bash", vec![100, 200]),
+				("systemd", vec![1]),
+				("vim", vec![300])
+			]
+		);
+
+		let bash = &groups[0];
+		assert_eq!(bash.command, "bash");
+		assert_eq!(bash.cpu_usage_percent, 3.0);
+		assert_eq!(bash.mem_usage_percent, Some(5.0));
+		assert_eq!(bash.threads, Some(2));
+		assert_eq!(bash.vsz_bytes, Some(12_000));
+		// Only the known rates are added up, and a total is only unknown if they all are.
+		assert_eq!(bash.read_bytes_per_sec, Some(100.0));
+		assert_eq!(bash.write_bytes_per_sec, None);
+		assert_eq!(bash.mem_usage_kb, None);
+	}
+
+	#[test]
+	fn test_group_by_full_command() {
+		let groups = group_processes(&get_test_processes(), ProcessGrouping::Name, true, |_| true);
+		assert_eq!(
+			get_summary(&groups),
+			vec![
+				("/sbin/init", vec![1]),
+				("bash", vec![200]),
+				("bash --login", vec![100]),
+				("vim notes.txt", vec![300])
+			]
+		);
+	}
+
+	#[test]
+	fn test_group_by_user() {
+		let groups = group_processes(&get_test_processes(), ProcessGrouping::User, false, |_| {
+			true
+		});
+		assert_eq!(
+			get_summary(&groups),
+			vec![("alice", vec![100, 200, 300]), ("root", vec![1])]
+		);
+		assert_eq!(groups[0].cpu_usage_percent, 7.0);
+		assert_eq!(groups[0].mem_usage_percent, Some(10.0));
+
+		// Each group has its user, so the groups can be sorted by it.
+		let mut groups = groups;
+		sort_processes(&mut groups, &ProcessSorting::USER, true);
+		assert_eq!(
+			groups
+				.iter()
+				.map(|group| group.user.as_deref())
+				.collect::<Vec<_>>(),
+			vec![Some("root"), Some("alice")]
+		);
+	}
+
+	#[test]
+	fn test_group_user() {
+		let mut processes = get_test_processes();
+		processes[1].user = Some("bob".to_string());
+		let groups = group_processes(&processes, ProcessGrouping::Name, false, |_| true);
+		assert_eq!(get_summary(&groups)[0], ("bash", vec![100, 200]));
+
+		// Only a group whose processes all have the same user has one.
+		assert_eq!(groups[0].user, None);
+		assert_eq!(groups[1].user.as_deref(), Some("root"));
+		assert_eq!(groups[2].user.as_deref(), Some("alice"));
+	}
+
+	#[test]
+	fn test_group_by_parent_pid() {
+		let groups = group_processes(
+			&get_test_processes(),
+			ProcessGrouping::ParentPid,
+			false,
+			|_| true,
+		);
+		assert_eq!(
+			get_summary(&groups),
+			vec![
+				("1 (systemd)", vec![100]),
+				("100 (bash)", vec![200, 300]),
+				("<none>", vec![1])
+			]
+		);
+
+		// Parents are still named when they are filtered out.
+		let groups = group_processes(
+			&get_test_processes(),
+			ProcessGrouping::ParentPid,
+			false,
+			|process| process.pid == 300,
+		);
+		assert_eq!(get_summary(&groups), vec![("100 (bash)", vec![300])]);
+	}
+
+	#[test]
+	fn test_group_by_exe_and_cgroup() {
+		let groups = group_processes(&get_test_processes(), ProcessGrouping::Exe, false, |_| true);
+		assert_eq!(
+			get_summary(&groups),
+			vec![
+				("/usr/bin/bash", vec![100, 200]),
+				("/usr/lib/systemd/systemd", vec![1]),
+				("<unknown>", vec![300])
+			]
+		);
+
+		let groups = group_processes(
+			&get_test_processes(),
+			ProcessGrouping::Cgroup,
+			false,
+			|process| process.pid != 1,
+		);
+		assert_eq!(
+			get_summary(&groups),
+			vec![
+				(
+					"/user.slice/user-1000.slice/session-2.scope",
+					vec![100, 200]
+				),
+				("<unknown>", vec![300])
+			]
+		);
+	}
+
+	#[test]
+	fn test_group_by_container() {
+		let mut processes = get_test_processes();
+		processes[2].cgroup = Some("/".to_string());
+		let groups = group_processes(&processes, ProcessGrouping::Container, false, |_| true);
+		// Processes outside of any container or unit are kept apart from those whose cgroup
+		// couldn't be read.
+		assert_eq!(
+			get_summary(&groups),
+			vec![
+				("<none>", vec![200]),
+				("<unknown>", vec![300]),
+				("init.scope", vec![1]),
+				("session-2.scope", vec![100])
+			]
+		);
+	}
+
+	#[test]
+	fn test_grouping_cycle() {
+		let mut grouping = Some(ProcessGrouping::Name);
+		let mut seen = Vec::new();
+		while let Some(current) = grouping {
+			seen.push(current);
+			grouping = current.next();
+		}
+		assert_eq!(
+			seen,
+			vec![
+				ProcessGrouping::Name,
+				ProcessGrouping::User,
+				ProcessGrouping::ParentPid,
+				ProcessGrouping::Exe,
+				ProcessGrouping::Cgroup,
+				ProcessGrouping::Container
+			]
+		);
+	}
+}
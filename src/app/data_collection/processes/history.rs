@@ -0,0 +1,227 @@
+//! Keeps how much CPU and memory each process has recently used, so a spike can be told apart
+//! from sustained usage.
+
+use std::{
+	collections::{BTreeMap, HashMap, VecDeque},
+	time::Instant,
+};
+
+use super::ProcessData;
+
+/// How much CPU and memory a process was using at one refresh.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessHistoryPoint {
+	pub instant: Instant,
+	pub cpu_usage_percent: f64,
+	pub mem_usage_percent: f64,
+}
+
+/// The recent usage of a process, oldest first.
+#[derive(Clone, Debug, Default)]
+pub struct ProcessHistory {
+	/// When the process started, to tell if its PID has since been reused by another process.
+	pub start_time: Option<u64>,
+	pub points: VecDeque<ProcessHistoryPoint>,
+}
+
+/// Adds the current usage of each process to its history, dropping points older than
+/// `stale_max_seconds`.  Processes that have exited are left alone, and are removed along with
+/// the rest of the stale data.  Memory is worked out from `mem_total_kb` where there is no
+/// percentage.
+pub fn update_process_history(
+	process_vector: &[ProcessData], process_history: &mut HashMap<u32, ProcessHistory>,
+	mem_total_kb: u64, stale_max_seconds: u64, curr_time: &Instant,
+) {
+	for process in process_vector {
+		let history = process_history.entry(process.pid).or_default();
+		if history.start_time != process.start_time {
+			history.start_time = process.start_time;
+			history.points.clear();
+		}
+
+		let mem_usage_percent =
+			process
+				.mem_usage_percent
+				.unwrap_or_else(|| match process.mem_usage_kb {
+					Some(mem_usage_kb) if mem_total_kb > 0 => {
+						mem_usage_kb as f64 / mem_total_kb as f64 * 100_f64
+					}
+					_ => 0_f64,
+				});
+		history.points.push_back(ProcessHistoryPoint {
+			instant: *curr_time,
+			cpu_usage_percent: process.cpu_usage_percent,
+			mem_usage_percent,
+		});
+
+		while let Some(oldest) = history.points.front() {
+			if curr_time.duration_since(oldest.instant).as_secs() > stale_max_seconds {
+				history.points.pop_front();
+			} else {
+				break;
+			}
+		}
+	}
+}
+
+/// Whether a history has had nothing added for longer than `stale_max_seconds`, so its process
+/// has most likely exited.
+pub fn is_stale(history: &ProcessHistory, stale_max_seconds: u64, curr_time: &Instant) -> bool {
+	history.points.back().map_or(true, |newest| {
+		curr_time.duration_since(newest.instant).as_secs() > stale_max_seconds
+	})
+}
+
+/// Adds up the histories of several processes (like those in a group), oldest first.  As every
+/// process is refreshed at once, the points line up by when they were taken.
+pub fn combine_process_history(
+	process_history: &HashMap<u32, ProcessHistory>, pids: &[u32],
+) -> Vec<ProcessHistoryPoint> {
+	let mut combined: BTreeMap<Instant, (f64, f64)> = BTreeMap::new();
+	for history in pids.iter().filter_map(|pid| process_history.get(pid)) {
+		for point in &history.points {
+			let total = combined.entry(point.instant).or_insert((0_f64, 0_f64));
+			total.0 += point.cpu_usage_percent;
+			total.1 += point.mem_usage_percent;
+		}
+	}
+
+	combined
+		.into_iter()
+		.map(
+			|(instant, (cpu_usage_percent, mem_usage_percent))| ProcessHistoryPoint {
+				instant,
+				cpu_usage_percent,
+				mem_usage_percent,
+			},
+		)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	fn get_process(pid: u32, cpu_usage_percent: f64, start_time: u64) -> ProcessData {
+		ProcessData {
+			pid,
+			cpu_usage_percent,
+			mem_usage_percent: Some(cpu_usage_percent / 2_f64),
+			start_time: Some(start_time),
+			..ProcessData::default()
+		}
+	}
+
+	fn get_cpu_history(history: &ProcessHistory) -> Vec<f64> {
+		history
+			.points
+			.iter()
+			.map(|point| point.cpu_usage_percent)
+			.collect()
+	}
+
+	#[test]
+	fn test_update_process_history() {
+		let start = Instant::now();
+		let mut process_history = HashMap::new();
+		for (seconds, cpu_usage_percent) in [(0, 10.0), (30, 20.0), (60, 30.0)].iter() {
+			update_process_history(
+				&[get_process(1, *cpu_usage_percent, 100)],
+				&mut process_history,
+				1024,
+				45,
+				&(start + Duration::from_secs(*seconds)),
+			);
+		}
+
+		// The first point is more than 45 seconds old by the last refresh.
+		assert_eq!(get_cpu_history(&process_history[&1]), vec![20.0, 30.0]);
+		assert_eq!(process_history[&1].points[1].mem_usage_percent, 15.0);
+
+		let end = start + Duration::from_secs(60);
+		assert!(!is_stale(&process_history[&1], 45, &end));
+		assert!(is_stale(
+			&process_history[&1],
+			45,
+			&(end + Duration::from_secs(46))
+		));
+	}
+
+	#[test]
+	fn test_reused_pid_starts_new_history() {
+		let start = Instant::now();
+		let mut process_history = HashMap::new();
+		update_process_history(
+			&[get_process(1, 10.0, 100)],
+			&mut process_history,
+			1024,
+			180,
+			&start,
+		);
+		update_process_history(
+			&[get_process(1, 20.0, 200)],
+			&mut process_history,
+			1024,
+			180,
+			&(start + Duration::from_secs(1)),
+		);
+		assert_eq!(get_cpu_history(&process_history[&1]), vec![20.0]);
+	}
+
+	#[test]
+	fn test_memory_from_kilobytes() {
+		let mut process_history = HashMap::new();
+		let process = ProcessData {
+			pid: 1,
+			mem_usage_kb: Some(256),
+			..ProcessData::default()
+		};
+		update_process_history(&[process], &mut process_history, 1024, 180, &Instant::now());
+		assert_eq!(process_history[&1].points[0].mem_usage_percent, 25.0);
+	}
+
+	#[test]
+	fn test_combine_process_history() {
+		let start = Instant::now();
+		let later = start + Duration::from_secs(1);
+		let mut process_history = HashMap::new();
+		update_process_history(
+			&[get_process(1, 10.0, 100), get_process(2, 20.0, 100)],
+			&mut process_history,
+			1024,
+			180,
+			&start,
+		);
+		// The third process only started in time for the second refresh.
+		update_process_history(
+			&[
+				get_process(1, 30.0, 100),
+				get_process(2, 40.0, 100),
+				get_process(3, 50.0, 100),
+			],
+			&mut process_history,
+			1024,
+			180,
+			&later,
+		);
+
+		let combined = combine_process_history(&process_history, &[1, 3, 4]);
+		assert_eq!(
+			combined,
+			vec![
+				ProcessHistoryPoint {
+					instant: start,
+					cpu_usage_percent: 10.0,
+					mem_usage_percent: 5.0,
+				},
+				ProcessHistoryPoint {
+					instant: later,
+					cpu_usage_percent: 80.0,
+					mem_usage_percent: 40.0,
+				},
+			]
+		);
+		assert!(combine_process_history(&process_history, &[]).is_empty());
+	}
+}
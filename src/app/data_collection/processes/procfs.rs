@@ -0,0 +1,493 @@
+//! Reads processes straight from procfs on Linux, rather than spawning `ps` every refresh.
+//! See proc(5) for what each of the files holds.
+
+use std::{fs, io, path::Path};
+
+/// The fields used from `/proc/<pid>/stat`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcStat {
+	pub pid: u32,
+	/// The name of the executable, which the kernel truncates to 15 bytes.
+	pub comm: String,
+	/// One of R, S, D, Z, T and so on.
+	pub state: char,
+	pub ppid: u32,
+	/// Time spent in user mode, in clock ticks.
+	pub utime: u64,
+	/// Time spent in kernel mode, in clock ticks.
+	pub stime: u64,
+	pub priority: i64,
+	pub nice: i64,
+	pub num_threads: u64,
+	/// When the process started, in clock ticks since boot.
+	pub start_time: u64,
+	/// The virtual memory size, in bytes.
+	pub vsize: u64,
+}
+
+/// The fields used from `/proc/<pid>/status`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcStatus {
+	pub name: String,
+	/// The real UID.
+	pub uid: u32,
+}
+
+/// The fields used from `/proc/<pid>/statm`, all in pages.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcStatm {
+	pub size: u64,
+	pub resident: u64,
+	pub shared: u64,
+}
+
+/// The fields used from `/proc/<pid>/io`.  Only the owner of a process (or root) can read this.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcIo {
+	/// Bytes read from storage, so not counting reads served from the page cache.
+	pub read_bytes: u64,
+	/// Bytes written to storage, or that will be once flushed from the page cache.
+	pub write_bytes: u64,
+}
+
+/// A line of `/proc/<pid>/cgroup`.  With cgroup v2 there is a single line, with a hierarchy ID
+/// of 0 and no controllers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcCgroup {
+	pub hierarchy_id: u32,
+	pub controllers: Vec<String>,
+	pub path: String,
+}
+
+/// Everything read about a process in one pass.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcProcess {
+	pub stat: ProcStat,
+	pub status: ProcStatus,
+	pub statm: ProcStatm,
+	/// The full command line, or the name in brackets for kernel threads (as `ps` does).
+	pub command: String,
+	/// The path of the executable, if it can be read.  Only the owner of a process (or root) can
+	/// read this, and kernel threads have none.
+	pub exe: Option<String>,
+	/// The path of the cgroup the process is in; see `get_main_cgroup_path`.
+	pub cgroup: Option<String>,
+	/// The container or systemd unit the process is in; see `container::get_container`.
+	pub container: Option<String>,
+}
+
+pub fn parse_stat(stat: &str) -> Option<ProcStat> {
+	// The name is in parentheses but can itself hold spaces and parentheses, so the PID is
+	// whatever comes before the first "(", and the rest of the fields come after the last ")".
+	let name_start = stat.find('(')?;
+	let name_end = stat.rfind(')')?;
+	if name_end < name_start {
+		return None;
+	}
+
+	let fields = stat[name_end + 1..].split_whitespace().collect::<Vec<_>>();
+	// Fields are numbered from 1 in proc(5), and the first two are the PID and name.
+	let get_field = |field_num: usize| fields.get(field_num - 3).copied();
+
+	Some(ProcStat {
+		pid: stat[..name_start].trim().parse().ok()?,
+		comm: stat[name_start + 1..name_end].to_string(),
+		state: get_field(3)?.chars().next()?,
+		ppid: get_field(4)?.parse().ok()?,
+		utime: get_field(14)?.parse().ok()?,
+		stime: get_field(15)?.parse().ok()?,
+		priority: get_field(18)?.parse().ok()?,
+		nice: get_field(19)?.parse().ok()?,
+		num_threads: get_field(20)?.parse().ok()?,
+		start_time: get_field(22)?.parse().ok()?,
+		vsize: get_field(23)?.parse().ok()?,
+	})
+}
+
+pub fn parse_status(status: &str) -> Option<ProcStatus> {
+	let mut name = None;
+	let mut uid = None;
+	for line in status.lines() {
+		let mut split_line = line.splitn(2, ':');
+		match (split_line.next(), split_line.next()) {
+			(Some("Name"), Some(value)) => name = Some(value.trim().to_string()),
+			// Real, effective, saved and filesystem UIDs, in that order.
+			(Some("Uid"), Some(value)) => uid = value.split_whitespace().next()?.parse().ok(),
+			_ => {}
+		}
+	}
+
+	Some(ProcStatus {
+		name: name?,
+		uid: uid?,
+	})
+}
+
+pub fn parse_statm(statm: &str) -> Option<ProcStatm> {
+	let mut fields = statm.split_whitespace();
+	Some(ProcStatm {
+		size: fields.next()?.parse().ok()?,
+		resident: fields.next()?.parse().ok()?,
+		shared: fields.next()?.parse().ok()?,
+	})
+}
+
+pub fn parse_io(io: &str) -> Option<ProcIo> {
+	let mut read_bytes = None;
+	let mut write_bytes = None;
+	for line in io.lines() {
+		let mut split_line = line.splitn(2, ':');
+		match (split_line.next(), split_line.next()) {
+			(Some("read_bytes"), Some(value)) => read_bytes = value.trim().parse().ok(),
+			(Some("write_bytes"), Some(value)) => write_bytes = value.trim().parse().ok(),
+			_ => {}
+		}
+	}
+
+	Some(ProcIo {
+		read_bytes: read_bytes?,
+		write_bytes: write_bytes?,
+	})
+}
+
+/// Arguments are separated (and usually ended) by NULs, and are joined with spaces instead.
+pub fn parse_cmdline(cmdline: &[u8]) -> String {
+	cmdline
+		.split(|byte| *byte == 0)
+		.filter(|arg| !arg.is_empty())
+		.map(String::from_utf8_lossy)
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+/// Each line is `hierarchy-ID:controller-list:cgroup-path`.
+pub fn parse_cgroup(cgroup: &str) -> Vec<ProcCgroup> {
+	cgroup
+		.lines()
+		.filter_map(|line| {
+			let mut fields = line.splitn(3, ':');
+			Some(ProcCgroup {
+				hierarchy_id: fields.next()?.parse().ok()?,
+				controllers: fields
+					.next()?
+					.split(',')
+					.filter(|controller| !controller.is_empty())
+					.map(String::from)
+					.collect(),
+				path: fields.next()?.to_string(),
+			})
+		})
+		.collect()
+}
+
+/// Picks the cgroup that best says where a process belongs.  That is the cgroup v2 one if there
+/// is one, else the one systemd organises processes with, else the first.
+pub fn get_main_cgroup_path(cgroups: &[ProcCgroup]) -> Option<&str> {
+	cgroups
+		.iter()
+		.find(|cgroup| cgroup.hierarchy_id == 0)
+		.or_else(|| {
+			cgroups.iter().find(|cgroup| {
+				cgroup
+					.controllers
+					.iter()
+					.any(|controller| controller == "name=systemd")
+			})
+		})
+		.or_else(|| cgroups.first())
+		.map(|cgroup| cgroup.path.as_str())
+}
+
+/// Gets the time the system booted, in seconds since the epoch, from `/proc/stat`.
+pub fn parse_boot_time(system_stat: &str) -> Option<u64> {
+	system_stat
+		.lines()
+		.find(|line| line.starts_with("btime "))?
+		.split_whitespace()
+		.nth(1)?
+		.parse()
+		.ok()
+}
+
+/// Reads a file that should be text, without failing on names that are not valid UTF-8.
+fn read_lossy(path: &Path) -> io::Result<String> {
+	Ok(String::from_utf8_lossy(&fs::read(path)?).into_owned())
+}
+
+fn invalid_data(pid_dir: &Path, file_name: &str) -> io::Error {
+	io::Error::new(
+		io::ErrorKind::InvalidData,
+		format!("Unable to parse {}", pid_dir.join(file_name).display()),
+	)
+}
+
+/// Reads a process, given its directory in procfs (like `/proc/1234`).
+pub fn read_process(pid_dir: &Path) -> io::Result<ProcProcess> {
+	let stat = parse_stat(&read_lossy(&pid_dir.join("stat"))?)
+		.ok_or_else(|| invalid_data(pid_dir, "stat"))?;
+	let status = parse_status(&read_lossy(&pid_dir.join("status"))?)
+		.ok_or_else(|| invalid_data(pid_dir, "status"))?;
+	let statm = parse_statm(&read_lossy(&pid_dir.join("statm"))?)
+		.ok_or_else(|| invalid_data(pid_dir, "statm"))?;
+
+	// A cmdline that can't be read is treated like an empty one, rather than losing the process.
+	let command = parse_cmdline(&fs::read(pid_dir.join("cmdline")).unwrap_or_default());
+	let command = if command.is_empty() {
+		format!("[{}]", stat.comm)
+	} else {
+		command
+	};
+
+	// Unlike the files above, these two can't be read for every process, which is not a failure.
+	let exe = fs::read_link(pid_dir.join("exe"))
+		.ok()
+		.map(|exe| exe.to_string_lossy().into_owned());
+	let cgroups = fs::read_to_string(pid_dir.join("cgroup"))
+		.ok()
+		.map(|cgroup| parse_cgroup(&cgroup));
+	let cgroup = cgroups
+		.as_ref()
+		.and_then(|cgroups| get_main_cgroup_path(cgroups).map(String::from));
+	let container = cgroups.and_then(|cgroups| super::container::get_container(&cgroups));
+
+	Ok(ProcProcess {
+		stat,
+		status,
+		statm,
+		command,
+		exe,
+		cgroup,
+		container,
+	})
+}
+
+/// Reads just the stat of a process, given its directory in procfs, for checking on a single
+/// process without reading everything else about it.
+pub fn read_stat(pid_dir: &Path) -> Option<ProcStat> {
+	parse_stat(&read_lossy(&pid_dir.join("stat")).ok()?)
+}
+
+/// Reads the I/O counters of a process, given its directory in procfs.
+pub fn read_io(pid_dir: &Path) -> io::Result<ProcIo> {
+	parse_io(&read_lossy(&pid_dir.join("io"))?).ok_or_else(|| invalid_data(pid_dir, "io"))
+}
+
+/// Gets the PIDs of every process, which are the numeric directories in `/proc`.
+pub fn get_pids() -> io::Result<Vec<u32>> {
+	Ok(fs::read_dir("/proc")?
+		.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+		.collect())
+}
+
+/// Gets the size of a page in kilobytes, which is what statm counts in.
+pub fn get_page_size_kb() -> u64 {
+	#[cfg(target_family = "unix")]
+	{
+		// This is safe, as sysconf only reads a value.
+		let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+		if page_size > 0 {
+			return page_size as u64 / 1024;
+		}
+	}
+
+	4
+}
+
+/// Gets how many clock ticks there are per second, which is what stat counts times in.
+pub fn get_clock_ticks_per_second() -> u64 {
+	#[cfg(target_family = "unix")]
+	{
+		// This is safe, as sysconf only reads a value.
+		let clock_ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+		if clock_ticks > 0 {
+			return clock_ticks as u64;
+		}
+	}
+
+	100
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_stat() {
+		let stat = parse_stat(include_str!("../../../../tests/process_fixtures/stat")).unwrap();
+		assert_eq!(
+			stat,
+			ProcStat {
+				pid: 1234,
+				comm: "bash".to_string(),
+				state: 'S',
+				ppid: 1200,
+				utime: 5,
+				stime: 2,
+				priority: 20,
+				nice: 0,
+				num_threads: 1,
+				start_time: 40213,
+				vsize: 12_386_304,
+			}
+		);
+	}
+
+	#[test]
+	fn test_parse_stat_with_unusual_name() {
+		let stat = parse_stat(include_str!(
+			"../../../../tests/process_fixtures/stat_unusual_name"
+		))
+		.unwrap();
+		assert_eq!(stat.pid, 4321);
+		assert_eq!(stat.comm, "Web (Content) ü");
+		assert_eq!(stat.state, 'R');
+		assert_eq!(stat.ppid, 1234);
+		assert_eq!((stat.utime, stat.stime), (1200, 340));
+		assert_eq!((stat.priority, stat.nice), (10, -10));
+		assert_eq!(stat.num_threads, 27);
+		assert_eq!(stat.vsize, 2_147_483_648);
+	}
+
+	#[test]
+	fn test_parse_stat_of_kernel_thread() {
+		let stat = parse_stat(include_str!(
+			"../../../../tests/process_fixtures/stat_kernel_thread"
+		))
+		.unwrap();
+		assert_eq!(stat.pid, 2);
+		assert_eq!(stat.comm, "kthreadd");
+		assert_eq!(stat.ppid, 0);
+		assert_eq!(stat.vsize, 0);
+	}
+
+	#[test]
+	fn test_parse_truncated_stat() {
+		assert_eq!(parse_stat("1234 (bash) S 1200 1234"), None);
+		assert_eq!(parse_stat("1234 bash) S (1200"), None);
+		assert_eq!(parse_stat(""), None);
+	}
+
+	#[test]
+	fn test_parse_status() {
+		let status =
+			parse_status(include_str!("../../../../tests/process_fixtures/status")).unwrap();
+		assert_eq!(
+			status,
+			ProcStatus {
+				name: "bash".to_string(),
+				uid: 1000,
+			}
+		);
+		assert_eq!(parse_status("Name:\tbash\n"), None);
+	}
+
+	#[test]
+	fn test_parse_statm() {
+		let statm = parse_statm(include_str!("../../../../tests/process_fixtures/statm")).unwrap();
+		assert_eq!(
+			statm,
+			ProcStatm {
+				size: 3025,
+				resident: 1320,
+				shared: 870,
+			}
+		);
+		assert_eq!(parse_statm("3025"), None);
+	}
+
+	#[test]
+	fn test_parse_io() {
+		assert_eq!(
+			parse_io(include_str!("../../../../tests/process_fixtures/io")),
+			Some(ProcIo {
+				read_bytes: 4_096_000,
+				write_bytes: 8192,
+			})
+		);
+		assert_eq!(parse_io("rchar: 2012345\nwchar: 1048576\n"), None);
+	}
+
+	#[test]
+	fn test_parse_cgroup() {
+		let cgroups = parse_cgroup(include_str!("../../../../tests/process_fixtures/cgroup"));
+		assert_eq!(cgroups.len(), 4);
+		assert_eq!(
+			cgroups[1],
+			ProcCgroup {
+				hierarchy_id: 4,
+				controllers: vec!["cpu".to_string(), "cpuacct".to_string()],
+				path: "/user.slice".to_string(),
+			}
+		);
+		assert_eq!(
+			cgroups[3],
+			ProcCgroup {
+				hierarchy_id: 0,
+				controllers: vec![],
+				path: "/user.slice/user-1000.slice/session-2.scope".to_string(),
+			}
+		);
+	}
+
+	#[test]
+	fn test_get_main_cgroup_path() {
+		let cgroups = parse_cgroup(include_str!("../../../../tests/process_fixtures/cgroup"));
+		assert_eq!(
+			get_main_cgroup_path(&cgroups),
+			Some("/user.slice/user-1000.slice/session-2.scope")
+		);
+
+		// Without cgroup v2, the one systemd uses is picked over the first.
+		let cgroups = parse_cgroup("4:cpu,cpuacct:/\n1:name=systemd:/system.slice/cron.service\n");
+		assert_eq!(
+			get_main_cgroup_path(&cgroups),
+			Some("/system.slice/cron.service")
+		);
+		assert_eq!(
+			get_main_cgroup_path(&parse_cgroup("4:cpu:/docker\n")),
+			Some("/docker")
+		);
+		assert_eq!(get_main_cgroup_path(&[]), None);
+	}
+
+	#[test]
+	fn test_parse_boot_time() {
+		assert_eq!(
+			parse_boot_time(include_str!(
+				"../../../../tests/process_fixtures/system_stat"
+			)),
+			Some(1_700_000_000)
+		);
+		assert_eq!(parse_boot_time("cpu  1 2 3 4\n"), None);
+	}
+
+	#[test]
+	fn test_parse_cmdline() {
+		assert_eq!(
+			parse_cmdline(include_bytes!("../../../../tests/process_fixtures/cmdline")),
+			"/bin/bash --login -i"
+		);
+		assert_eq!(parse_cmdline(b""), "");
+	}
+
+	#[test]
+	fn test_read_stat() {
+		let task_dir = Path::new(concat!(
+			env!("CARGO_MANIFEST_DIR"),
+			"/tests/process_fixtures/proc/4321/task"
+		));
+		let stat = read_stat(&task_dir.join("4331")).unwrap();
+		assert_eq!((stat.pid, stat.state), (4331, 'S'));
+		assert_eq!(read_stat(&task_dir.join("1234")), None);
+	}
+
+	#[test]
+	fn test_read_process_without_cmdline() {
+		let process = read_process(Path::new(concat!(
+			env!("CARGO_MANIFEST_DIR"),
+			"/tests/process_fixtures/proc/4322"
+		)))
+		.unwrap();
+		assert_eq!(process.command, "[bash]");
+	}
+}
@@ -0,0 +1,104 @@
+//! Finds the descendants of processes by following parent PIDs, for acting on a whole subtree.
+
+use std::collections::{HashMap, HashSet};
+
+use super::ProcessData;
+
+fn add_subtree(
+	pid: u32, children: &HashMap<u32, Vec<u32>>, visited: &mut HashSet<u32>,
+	subtree_pids: &mut Vec<u32>,
+) {
+	if !visited.insert(pid) {
+		return;
+	}
+
+	if let Some(child_pids) = children.get(&pid) {
+		for child_pid in child_pids {
+			add_subtree(*child_pid, children, visited, subtree_pids);
+		}
+	}
+	subtree_pids.push(pid);
+}
+
+/// Gets the PIDs of the given processes and all of their descendants, with every process listed
+/// before its parent, so that signalling them in order reaches children before their parents
+/// can be replaced or reaped.  Each PID is only listed once, even if it is in more than one of
+/// the subtrees, and PIDs that aren't in `processes` are left out.
+pub fn get_subtree_pids(processes: &[ProcessData], root_pids: &[u32]) -> Vec<u32> {
+	let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+	for process in processes {
+		if let Some(ppid) = process.ppid {
+			if ppid != process.pid {
+				children.entry(ppid).or_default().push(process.pid);
+			}
+		}
+	}
+
+	let pids: HashSet<u32> = processes.iter().map(|process| process.pid).collect();
+	let mut visited = HashSet::new();
+	let mut subtree_pids = Vec::new();
+	for root_pid in root_pids.iter().filter(|root_pid| pids.contains(root_pid)) {
+		add_subtree(*root_pid, &children, &mut visited, &mut subtree_pids);
+	}
+	subtree_pids
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_process(pid: u32, ppid: u32) -> ProcessData {
+		ProcessData {
+			pid,
+			ppid: Some(ppid),
+			..ProcessData::default()
+		}
+	}
+
+	/// A shell running a build, which has a compiler and a linker running under it, next to an
+	/// unrelated process.
+	fn get_test_processes() -> Vec<ProcessData> {
+		vec![
+			ProcessData {
+				pid: 1,
+				..ProcessData::default()
+			},
+			get_process(100, 1),
+			get_process(200, 100),
+			get_process(300, 200),
+			get_process(301, 200),
+			get_process(400, 1),
+		]
+	}
+
+	#[test]
+	fn test_children_before_parents() {
+		assert_eq!(
+			get_subtree_pids(&get_test_processes(), &[100]),
+			vec![300, 301, 200, 100]
+		);
+		assert_eq!(get_subtree_pids(&get_test_processes(), &[301]), vec![301]);
+	}
+
+	#[test]
+	fn test_overlapping_subtrees() {
+		// The build is already part of the shell's subtree, so it isn't listed twice.
+		assert_eq!(
+			get_subtree_pids(&get_test_processes(), &[200, 100, 400]),
+			vec![300, 301, 200, 100, 400]
+		);
+	}
+
+	#[test]
+	fn test_missing_and_looping_processes() {
+		assert!(get_subtree_pids(&get_test_processes(), &[999]).is_empty());
+
+		// A process can't be its own parent, but nothing should loop forever if one claims to be.
+		let mut processes = get_test_processes();
+		processes.push(get_process(500, 500));
+		processes.push(get_process(600, 700));
+		processes.push(get_process(700, 600));
+		assert_eq!(get_subtree_pids(&processes, &[500]), vec![500]);
+		assert_eq!(get_subtree_pids(&processes, &[600]), vec![700, 600]);
+	}
+}
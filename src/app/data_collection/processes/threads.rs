@@ -0,0 +1,178 @@
+//! Reads the threads of a single process from `/proc/<pid>/task`, for the thread view.  Each
+//! thread has its own stat file, laid out just like that of a process.
+
+use std::{
+	collections::{HashMap, HashSet},
+	fs, io,
+	path::Path,
+	time::Instant,
+};
+
+use super::{procfs, ProcessData};
+use crate::utils::error::{BottomError, Result};
+
+/// Reads the stats of every thread of a process from a procfs mounted at `proc_dir`, ordered
+/// by thread ID.  Threads that exit while this happens are skipped.
+fn read_thread_stats_from(proc_dir: &Path, pid: u32) -> io::Result<Vec<procfs::ProcStat>> {
+	let mut thread_stats = fs::read_dir(proc_dir.join(pid.to_string()).join("task"))?
+		.filter_map(|entry| {
+			let entry = entry.ok()?;
+			entry.file_name().to_str()?.parse::<u32>().ok()?;
+			procfs::parse_stat(&String::from_utf8_lossy(
+				&fs::read(entry.path().join("stat")).ok()?,
+			))
+		})
+		.collect::<Vec<_>>();
+	thread_stats.sort_by_key(|thread_stat| thread_stat.pid);
+	Ok(thread_stats)
+}
+
+/// Keeps what is needed to work out how much CPU each thread of a process has used since the
+/// last refresh, the same way as is done for processes.
+#[derive(Debug)]
+pub struct ThreadCollector {
+	pid: u32,
+	prev_idle: f64,
+	prev_non_idle: f64,
+	prev_tid_stats: HashMap<String, (f64, Instant)>,
+}
+
+impl ThreadCollector {
+	pub fn new(pid: u32) -> Self {
+		ThreadCollector {
+			pid,
+			prev_idle: 0_f64,
+			prev_non_idle: 0_f64,
+			prev_tid_stats: HashMap::new(),
+		}
+	}
+
+	/// The PID of the process whose threads are collected.
+	pub fn get_pid(&self) -> u32 {
+		self.pid
+	}
+
+	/// Reads the threads of the process, each as its own entry with its thread ID in place of a
+	/// PID.  Threads seen for the first time have no usage to compare against yet, so they are
+	/// shown as not using any CPU until the next refresh.  Only supported on Linux.
+	pub fn get_threads(&mut self, use_current_cpu_total: bool) -> Result<Vec<ProcessData>> {
+		if !cfg!(target_os = "linux") {
+			return Err(BottomError::InvalidIO {
+				message: "Threads can only be shown on Linux.".to_string(),
+			});
+		}
+
+		let thread_stats = read_thread_stats_from(Path::new("/proc"), self.pid)?;
+		let (cpu_usage, cpu_percentage) =
+			super::cpu_usage_calculation(&mut self.prev_idle, &mut self.prev_non_idle)?;
+		let boot_time = fs::read_to_string("/proc/stat")
+			.ok()
+			.and_then(|system_stat| procfs::parse_boot_time(&system_stat));
+		let clock_ticks_per_second = procfs::get_clock_ticks_per_second();
+		let curr_time = Instant::now();
+
+		let thread_vector = thread_stats
+			.into_iter()
+			.map(|thread_stat| {
+				let is_new = !self
+					.prev_tid_stats
+					.contains_key(&thread_stat.pid.to_string());
+				let cpu_usage_percent = super::linux_cpu_usage(
+					thread_stat.pid,
+					(thread_stat.utime + thread_stat.stime) as f64,
+					cpu_usage,
+					cpu_percentage,
+					&mut self.prev_tid_stats,
+					use_current_cpu_total,
+					&curr_time,
+				);
+
+				ProcessData {
+					pid: thread_stat.pid,
+					ppid: Some(self.pid),
+					cpu_usage_percent: if is_new { 0_f64 } else { cpu_usage_percent },
+					state: Some(thread_stat.state),
+					priority: Some(thread_stat.priority),
+					nice: Some(thread_stat.nice),
+					start_time: boot_time.map(|boot_time| {
+						boot_time + thread_stat.start_time / clock_ticks_per_second
+					}),
+					start_ticks: Some(thread_stat.start_time),
+					name: thread_stat.comm.clone(),
+					command: thread_stat.comm,
+					..ProcessData::default()
+				}
+			})
+			.collect::<Vec<_>>();
+
+		// Forget about threads that have exited.
+		let tids = thread_vector
+			.iter()
+			.map(|thread| thread.pid.to_string())
+			.collect::<HashSet<_>>();
+		self.prev_tid_stats.retain(|tid, _| tids.contains(tid));
+
+		Ok(thread_vector)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_fixture_proc_dir() -> &'static Path {
+		Path::new(concat!(
+			env!("CARGO_MANIFEST_DIR"),
+			"/tests/process_fixtures/proc"
+		))
+	}
+
+	#[test]
+	fn test_read_thread_stats() {
+		let thread_stats = read_thread_stats_from(get_fixture_proc_dir(), 4321).unwrap();
+		assert_eq!(
+			thread_stats
+				.iter()
+				.map(|thread_stat| (
+					thread_stat.pid,
+					thread_stat.comm.as_str(),
+					thread_stat.state
+				))
+				.collect::<Vec<_>>(),
+			vec![
+				(4321, "Web (Content) ü", 'R'),
+				(4330, "DOM Worker", 'R'),
+				(4331, "IPC I/O Child", 'S')
+			]
+		);
+		assert_eq!((thread_stats[1].utime, thread_stats[1].stime), (800, 120));
+		assert_eq!((thread_stats[2].priority, thread_stats[2].nice), (20, 0));
+	}
+
+	#[test]
+	fn test_read_thread_stats_of_missing_process() {
+		assert_eq!(
+			read_thread_stats_from(get_fixture_proc_dir(), 1234)
+				.unwrap_err()
+				.kind(),
+			io::ErrorKind::NotFound
+		);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn test_get_own_threads() {
+		let mut thread_collector = ThreadCollector::new(std::process::id());
+		let threads = thread_collector.get_threads(false).unwrap();
+		assert!(threads
+			.iter()
+			.any(|thread| thread.pid == std::process::id()));
+		// Nothing to compare against yet.
+		assert!(threads
+			.iter()
+			.all(|thread| thread.cpu_usage_percent == 0_f64));
+		assert!(threads
+			.iter()
+			.all(|thread| thread.ppid == Some(std::process::id())));
+	}
+}
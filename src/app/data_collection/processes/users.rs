@@ -0,0 +1,78 @@
+//! Resolves UIDs to user names using `/etc/passwd`.
+
+use std::{collections::HashMap, fs, time::SystemTime};
+
+const PASSWD_PATH: &str = "/etc/passwd";
+
+/// Maps UIDs to user names.  `/etc/passwd` is only read again when it has changed, so looking
+/// up users every refresh stays cheap.
+#[derive(Default)]
+pub struct UserTable {
+	users: HashMap<u32, String>,
+	last_modified: Option<SystemTime>,
+}
+
+impl UserTable {
+	/// Re-reads `/etc/passwd` if it has changed since it was last read.
+	pub fn refresh(&mut self) {
+		let last_modified = fs::metadata(PASSWD_PATH)
+			.and_then(|metadata| metadata.modified())
+			.ok();
+		if last_modified.is_some() && last_modified == self.last_modified {
+			return;
+		}
+
+		if let Ok(passwd) = fs::read_to_string(PASSWD_PATH) {
+			self.users = parse_passwd(&passwd);
+			self.last_modified = last_modified;
+		}
+	}
+
+	/// Gets the name of a user, or the UID itself if it has no entry (like in some containers).
+	pub fn get_user_name(&self, uid: u32) -> String {
+		self.users
+			.get(&uid)
+			.cloned()
+			.unwrap_or_else(|| uid.to_string())
+	}
+}
+
+/// Each line is `name:password:uid:gid:gecos:home:shell`; anything malformed is skipped.
+pub fn parse_passwd(passwd: &str) -> HashMap<u32, String> {
+	passwd
+		.lines()
+		.filter(|line| !line.trim_start().starts_with('#'))
+		.filter_map(|line| {
+			let mut fields = line.split(':');
+			let name = fields.next()?;
+			let uid = fields.nth(1)?.parse::<u32>().ok()?;
+			if name.is_empty() {
+				None
+			} else {
+				Some((uid, name.to_string()))
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_passwd() {
+		let users = parse_passwd(include_str!("../../../../tests/process_fixtures/passwd"));
+		assert_eq!(users.get(&0).map(String::as_str), Some("root"));
+		assert_eq!(users.get(&33).map(String::as_str), Some("www-data"));
+		assert_eq!(users.get(&1000).map(String::as_str), Some("alice"));
+		assert_eq!(users.get(&65534).map(String::as_str), Some("nobody"));
+		// The comment, the blank line and the lines with a missing name or a bad UID.
+		assert_eq!(users.len(), 4);
+	}
+
+	#[test]
+	fn test_unknown_user_falls_back_to_uid() {
+		let user_table = UserTable::default();
+		assert_eq!(user_table.get_user_name(4242), "4242");
+	}
+}
@@ -0,0 +1,817 @@
+//! Maps key sequences to the actions they trigger.  Sequences can be made up of several keys
+//! (like `dd`), in which case we wait for the rest of the sequence before acting.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::time::Instant;
+
+use crate::constants;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+	Quit,
+	ResetData,
+	Freeze,
+	ShowHelp,
+	CloseDialog,
+	Confirm,
+	MoveLeft,
+	MoveRight,
+	MoveUp,
+	MoveDown,
+	ScrollUp,
+	ScrollDown,
+	SkipToFirst,
+	SkipToLast,
+	KillProcess,
+	KillProcessTree,
+	ReniceProcess,
+	SetProcessAffinity,
+	ToggleSelected,
+	MarkAllProcesses,
+	UnmarkAllProcesses,
+	SortByCpu,
+	SortByMem,
+	SortByPid,
+	SortByName,
+	SortByUser,
+	SortByState,
+	SortByThreads,
+	SortByRss,
+	SortByVsz,
+	SortByNice,
+	SortByStartTime,
+	SortByReadRate,
+	SortByWriteRate,
+	SortByContainer,
+	ToggleGrouping,
+	ToggleTree,
+	ToggleFullCommand,
+	ToggleHistory,
+	ToggleThreads,
+	ScrollLeft,
+	ScrollRight,
+	CollapseSubtree,
+	ExpandSubtree,
+	OpenSearch,
+	ToggleSearchRegex,
+	ToggleSearchCaseSensitive,
+	ToggleSearchWholeWord,
+}
+
+/// Every action, in the order they are shown in the help screen.
+pub const ALL_ACTIONS: [Action; 48] = [
+	Action::Quit,
+	Action::ResetData,
+	Action::Freeze,
+	Action::MoveLeft,
+	Action::MoveRight,
+	Action::MoveUp,
+	Action::MoveDown,
+	Action::ScrollUp,
+	Action::ScrollDown,
+	Action::SkipToFirst,
+	Action::SkipToLast,
+	Action::CloseDialog,
+	Action::Confirm,
+	Action::ShowHelp,
+	Action::KillProcess,
+	Action::KillProcessTree,
+	Action::ReniceProcess,
+	Action::SetProcessAffinity,
+	Action::ToggleSelected,
+	Action::MarkAllProcesses,
+	Action::UnmarkAllProcesses,
+	Action::SortByCpu,
+	Action::SortByMem,
+	Action::SortByPid,
+	Action::SortByName,
+	Action::SortByUser,
+	Action::SortByState,
+	Action::SortByThreads,
+	Action::SortByRss,
+	Action::SortByVsz,
+	Action::SortByNice,
+	Action::SortByStartTime,
+	Action::SortByReadRate,
+	Action::SortByWriteRate,
+	Action::SortByContainer,
+	Action::ToggleGrouping,
+	Action::ToggleTree,
+	Action::ToggleFullCommand,
+	Action::ToggleHistory,
+	Action::ToggleThreads,
+	Action::ScrollLeft,
+	Action::ScrollRight,
+	Action::CollapseSubtree,
+	Action::ExpandSubtree,
+	Action::OpenSearch,
+	Action::ToggleSearchRegex,
+	Action::ToggleSearchCaseSensitive,
+	Action::ToggleSearchWholeWord,
+];
+
+impl Action {
+	/// The name used to refer to this action in the config file.
+	pub fn name(self) -> &'static str {
+		match self {
+			Action::Quit => "quit",
+			Action::ResetData => "reset_data",
+			Action::Freeze => "freeze",
+			Action::ShowHelp => "show_help",
+			Action::CloseDialog => "close_dialog",
+			Action::Confirm => "confirm",
+			Action::MoveLeft => "move_left",
+			Action::MoveRight => "move_right",
+			Action::MoveUp => "move_up",
+			Action::MoveDown => "move_down",
+			Action::ScrollUp => "scroll_up",
+			Action::ScrollDown => "scroll_down",
+			Action::SkipToFirst => "skip_to_first",
+			Action::SkipToLast => "skip_to_last",
+			Action::KillProcess => "kill_process",
+			Action::KillProcessTree => "kill_process_tree",
+			Action::ReniceProcess => "renice_process",
+			Action::SetProcessAffinity => "set_process_affinity",
+			Action::ToggleSelected => "toggle_selected",
+			Action::MarkAllProcesses => "mark_all",
+			Action::UnmarkAllProcesses => "unmark_all",
+			Action::SortByCpu => "sort_by_cpu",
+			Action::SortByMem => "sort_by_mem",
+			Action::SortByPid => "sort_by_pid",
+			Action::SortByName => "sort_by_name",
+			Action::SortByUser => "sort_by_user",
+			Action::SortByState => "sort_by_state",
+			Action::SortByThreads => "sort_by_threads",
+			Action::SortByRss => "sort_by_rss",
+			Action::SortByVsz => "sort_by_vsz",
+			Action::SortByNice => "sort_by_nice",
+			Action::SortByStartTime => "sort_by_start_time",
+			Action::SortByReadRate => "sort_by_read_rate",
+			Action::SortByWriteRate => "sort_by_write_rate",
+			Action::SortByContainer => "sort_by_container",
+			Action::ToggleGrouping => "toggle_grouping",
+			Action::ToggleTree => "toggle_tree",
+			Action::ToggleFullCommand => "toggle_full_command",
+			Action::ToggleHistory => "toggle_history",
+			Action::ToggleThreads => "toggle_threads",
+			Action::ScrollLeft => "scroll_left",
+			Action::ScrollRight => "scroll_right",
+			Action::CollapseSubtree => "collapse_subtree",
+			Action::ExpandSubtree => "expand_subtree",
+			Action::OpenSearch => "open_search",
+			Action::ToggleSearchRegex => "toggle_search_regex",
+			Action::ToggleSearchCaseSensitive => "toggle_search_case_sensitive",
+			Action::ToggleSearchWholeWord => "toggle_search_whole_word",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Action> {
+		ALL_ACTIONS
+			.iter()
+			.find(|action| action.name() == name)
+			.copied()
+	}
+
+	fn description(self) -> &'static str {
+		match self {
+			Action::Quit => "to quit.",
+			Action::ResetData => "to reset all data.",
+			Action::Freeze => "to toggle freezing and unfreezing the display.",
+			Action::ShowHelp => "to get this help screen.",
+			Action::CloseDialog => "to close a dialog window (help, dd confirmation or process details) or the search bar.",
+			Action::Confirm => {
+				"to confirm killing a process in the dd confirmation, to stop typing in the search bar, or to show the details of the selected process."
+			}
+			Action::MoveLeft => "to move to the panel on the left.",
+			Action::MoveRight => "to move to the panel on the right.",
+			Action::MoveUp => "to move to the panel above.",
+			Action::MoveDown => "to move to the panel below.",
+			Action::ScrollUp => "to scroll up through a list.",
+			Action::ScrollDown => "to scroll down through a list.",
+			Action::SkipToFirst => "to jump to the first entry of a list.",
+			Action::SkipToLast => "to jump to the last entry of a list.",
+			Action::KillProcess => "to pick a signal to send to the selected process, such as to kill it.",
+			Action::KillProcessTree => {
+				"to pick a signal to send to the selected process and all of its descendants."
+			}
+			Action::ReniceProcess => "to change the niceness of the selected process.",
+			Action::SetProcessAffinity => "to change which CPUs the selected process may run on.",
+			Action::ToggleSelected => {
+				"to mark or unmark the selected process, or to pick or unpick the selected CPU in the CPU affinity dialog."
+			}
+			Action::MarkAllProcesses => {
+				"to mark every process listed, or unmark them if they all are."
+			}
+			Action::UnmarkAllProcesses => "to unmark every process.",
+			Action::SortByCpu => "to sort by CPU usage.",
+			Action::SortByMem => "to sort by memory usage.",
+			Action::SortByPid => "to sort by PID.",
+			Action::SortByName => "to sort by process name.",
+			Action::SortByUser => "to sort by user.",
+			Action::SortByState => "to sort by process state.",
+			Action::SortByThreads => "to sort by thread count.",
+			Action::SortByRss => "to sort by resident memory (RSS).",
+			Action::SortByVsz => "to sort by virtual memory (VSZ).",
+			Action::SortByNice => "to sort by niceness.",
+			Action::SortByStartTime => "to sort by start time.",
+			Action::SortByReadRate => "to sort by how fast processes are reading from disk.",
+			Action::SortByWriteRate => "to sort by how fast processes are writing to disk.",
+			Action::SortByContainer => "to sort by container or systemd unit.",
+			Action::ToggleGrouping => {
+				"to cycle through grouping processes by name, user, parent, executable, cgroup and container."
+			}
+			Action::ToggleTree => "to toggle showing processes as a tree under their parents.",
+			Action::ToggleFullCommand => {
+				"to toggle showing the full command line of processes instead of their name."
+			}
+			Action::ToggleHistory => {
+				"to toggle showing the recent CPU and memory usage of the selected process."
+			}
+			Action::ToggleThreads => {
+				"to toggle showing the threads of the selected process instead of all processes."
+			}
+			Action::ScrollLeft => "to scroll the full command lines of processes to the left.",
+			Action::ScrollRight => "to scroll the full command lines of processes to the right.",
+			Action::CollapseSubtree => {
+				"to collapse the selected process in the tree, showing its subtree as one entry."
+			}
+			Action::ExpandSubtree => "to expand the selected process in the tree.",
+			Action::OpenSearch => "to search for processes by name, PID or command line.",
+			Action::ToggleSearchRegex => "to toggle searching with a regex.",
+			Action::ToggleSearchCaseSensitive => "to toggle case-sensitive searching.",
+			Action::ToggleSearchWholeWord => "to toggle matching whole words when searching.",
+		}
+	}
+
+	/// Whether the action changes processes, which isn't allowed in read-only mode.
+	pub fn is_mutating(self) -> bool {
+		matches!(
+			self,
+			Action::KillProcess
+				| Action::KillProcessTree
+				| Action::ReniceProcess
+				| Action::SetProcessAffinity
+		)
+	}
+
+	fn is_process_action(self) -> bool {
+		matches!(
+			self,
+			Action::KillProcess
+				| Action::KillProcessTree
+				| Action::ReniceProcess
+				| Action::SetProcessAffinity
+				| Action::ToggleSelected
+				| Action::MarkAllProcesses
+				| Action::UnmarkAllProcesses
+				| Action::SortByCpu
+				| Action::SortByMem
+				| Action::SortByPid
+				| Action::SortByName
+				| Action::SortByUser
+				| Action::SortByState
+				| Action::SortByThreads
+				| Action::SortByRss
+				| Action::SortByVsz
+				| Action::SortByNice
+				| Action::SortByStartTime
+				| Action::SortByReadRate
+				| Action::SortByWriteRate
+				| Action::SortByContainer
+				| Action::ToggleGrouping
+				| Action::ToggleTree
+				| Action::ToggleFullCommand
+				| Action::ToggleHistory
+				| Action::ToggleThreads
+				| Action::ScrollLeft
+				| Action::ScrollRight
+				| Action::CollapseSubtree
+				| Action::ExpandSubtree
+				| Action::OpenSearch
+				| Action::ToggleSearchRegex
+				| Action::ToggleSearchCaseSensitive
+				| Action::ToggleSearchWholeWord
+		)
+	}
+}
+
+pub type KeySequence = Vec<KeyEvent>;
+
+pub struct KeyMap {
+	bindings: Vec<(KeySequence, Action)>,
+	pending_keys: KeySequence,
+	last_key_press: Instant,
+}
+
+impl Default for KeyMap {
+	fn default() -> Self {
+		fn key(code: KeyCode) -> KeyEvent {
+			KeyEvent::new(code, KeyModifiers::empty())
+		}
+		fn ctrl(code: KeyCode) -> KeyEvent {
+			KeyEvent::new(code, KeyModifiers::CONTROL)
+		}
+		fn alt(code: KeyCode) -> KeyEvent {
+			KeyEvent::new(code, KeyModifiers::ALT)
+		}
+		fn chars(sequence: &str) -> KeySequence {
+			sequence.chars().map(|c| key(KeyCode::Char(c))).collect()
+		}
+
+		KeyMap::new(vec![
+			(chars("q"), Action::Quit),
+			(vec![ctrl(KeyCode::Char('c'))], Action::Quit),
+			(vec![ctrl(KeyCode::Char('r'))], Action::ResetData),
+			(chars("f"), Action::Freeze),
+			(chars("?"), Action::ShowHelp),
+			(vec![key(KeyCode::Esc)], Action::CloseDialog),
+			(vec![key(KeyCode::Enter)], Action::Confirm),
+			(chars("h"), Action::MoveLeft),
+			(vec![ctrl(KeyCode::Left)], Action::MoveLeft),
+			(chars("l"), Action::MoveRight),
+			(vec![ctrl(KeyCode::Right)], Action::MoveRight),
+			(chars("k"), Action::MoveUp),
+			(vec![ctrl(KeyCode::Up)], Action::MoveUp),
+			(chars("j"), Action::MoveDown),
+			(vec![ctrl(KeyCode::Down)], Action::MoveDown),
+			(vec![key(KeyCode::Up)], Action::ScrollUp),
+			(vec![key(KeyCode::Down)], Action::ScrollDown),
+			(chars("gg"), Action::SkipToFirst),
+			(vec![key(KeyCode::Home)], Action::SkipToFirst),
+			(chars("G"), Action::SkipToLast),
+			(vec![key(KeyCode::End)], Action::SkipToLast),
+			(chars("dd"), Action::KillProcess),
+			(chars("D"), Action::KillProcessTree),
+			(vec![ctrl(KeyCode::Char('n'))], Action::ReniceProcess),
+			(chars("a"), Action::SetProcessAffinity),
+			(chars(" "), Action::ToggleSelected),
+			(vec![ctrl(KeyCode::Char('a'))], Action::MarkAllProcesses),
+			(chars("U"), Action::UnmarkAllProcesses),
+			(chars("c"), Action::SortByCpu),
+			(chars("m"), Action::SortByMem),
+			(chars("p"), Action::SortByPid),
+			(chars("n"), Action::SortByName),
+			(chars("u"), Action::SortByUser),
+			(chars("s"), Action::SortByState),
+			(chars("T"), Action::SortByThreads),
+			(chars("r"), Action::SortByRss),
+			(chars("v"), Action::SortByVsz),
+			(chars("N"), Action::SortByNice),
+			(chars("S"), Action::SortByStartTime),
+			(chars("R"), Action::SortByReadRate),
+			(chars("W"), Action::SortByWriteRate),
+			(chars("C"), Action::SortByContainer),
+			(vec![key(KeyCode::Tab)], Action::ToggleGrouping),
+			(chars("t"), Action::ToggleTree),
+			(chars("P"), Action::ToggleFullCommand),
+			(chars("H"), Action::ToggleHistory),
+			(chars("e"), Action::ToggleThreads),
+			(vec![key(KeyCode::Left)], Action::ScrollLeft),
+			(vec![key(KeyCode::Right)], Action::ScrollRight),
+			(chars("-"), Action::CollapseSubtree),
+			(chars("+"), Action::ExpandSubtree),
+			(chars("="), Action::ExpandSubtree),
+			(chars("/"), Action::OpenSearch),
+			(vec![alt(KeyCode::Char('r'))], Action::ToggleSearchRegex),
+			(
+				vec![alt(KeyCode::Char('c'))],
+				Action::ToggleSearchCaseSensitive,
+			),
+			(vec![alt(KeyCode::Char('w'))], Action::ToggleSearchWholeWord),
+		])
+	}
+}
+
+impl KeyMap {
+	pub fn new(bindings: Vec<(KeySequence, Action)>) -> Self {
+		KeyMap {
+			bindings,
+			pending_keys: Vec::new(),
+			last_key_press: Instant::now(),
+		}
+	}
+
+	/// Replaces all the key sequences bound to an action.
+	pub fn set_bindings(&mut self, action: Action, sequences: Vec<KeySequence>) {
+		self.bindings
+			.retain(|(_, bound_action)| *bound_action != action);
+		self.bindings
+			.extend(sequences.into_iter().map(|sequence| (sequence, action)));
+	}
+
+	pub fn get_bindings(&self, action: Action) -> Vec<&KeySequence> {
+		self.bindings
+			.iter()
+			.filter(|(_, bound_action)| *bound_action == action)
+			.map(|(sequence, _)| sequence)
+			.collect()
+	}
+
+	/// Returns a pair of bindings where one would stop the other from ever being triggered,
+	/// if there is one; that is, where both are the same, or one starts with the other.
+	pub fn find_conflict(&self) -> Option<(&KeySequence, Action, &KeySequence, Action)> {
+		for (index, (sequence, action)) in self.bindings.iter().enumerate() {
+			for (other_sequence, other_action) in &self.bindings[index + 1..] {
+				if sequence.starts_with(other_sequence) || other_sequence.starts_with(sequence) {
+					return Some((sequence, *action, other_sequence, *other_action));
+				}
+			}
+		}
+		None
+	}
+
+	pub fn reset_pending_keys(&mut self) {
+		self.pending_keys.clear();
+	}
+
+	/// Feeds a key press in, returning the action to perform if it completed a sequence.
+	pub fn on_key_event(&mut self, key_event: KeyEvent) -> Option<Action> {
+		let key_event = normalise_key_event(key_event);
+
+		let current_key_press_inst = Instant::now();
+		if current_key_press_inst
+			.duration_since(self.last_key_press)
+			.as_millis()
+			> constants::MAX_KEY_TIMEOUT_IN_MILLISECONDS
+		{
+			self.reset_pending_keys();
+		}
+		self.last_key_press = current_key_press_inst;
+
+		self.pending_keys.push(key_event);
+		if let Some(action) = self.match_pending_keys() {
+			return action;
+		}
+
+		// The sequence went nowhere; see if the key just pressed starts a new one instead.
+		self.pending_keys = vec![key_event];
+		self.match_pending_keys().unwrap_or_else(|| {
+			self.reset_pending_keys();
+			None
+		})
+	}
+
+	/// Returns `Some(Some(action))` if the pending keys match a sequence, `Some(None)` if they
+	/// are the start of one, and `None` if they match nothing.
+	fn match_pending_keys(&mut self) -> Option<Option<Action>> {
+		if let Some((_, action)) = self
+			.bindings
+			.iter()
+			.find(|(sequence, _)| *sequence == self.pending_keys)
+		{
+			let action = *action;
+			self.reset_pending_keys();
+			Some(Some(action))
+		} else if self
+			.bindings
+			.iter()
+			.any(|(sequence, _)| sequence.starts_with(&self.pending_keys))
+		{
+			Some(None)
+		} else {
+			None
+		}
+	}
+
+	/// Generates the help screen text from the current bindings.  In read-only mode, the actions
+	/// that would change processes are left out, as they do nothing.
+	pub fn get_help_text(&self, read_only: bool) -> Vec<String> {
+		let get_section = |is_process_section: bool| {
+			ALL_ACTIONS
+				.iter()
+				.filter(|action| action.is_process_action() == is_process_section)
+				.filter(|action| !(read_only && action.is_mutating()))
+				.filter_map(|action| {
+					let bindings = self.get_bindings(*action);
+					if bindings.is_empty() {
+						None
+					} else {
+						Some(format!(
+							"{} {}\n",
+							bindings
+								.into_iter()
+								.map(|sequence| key_sequence_to_string(sequence))
+								.collect::<Vec<_>>()
+								.join(", "),
+							action.description()
+						))
+					}
+				})
+				.collect::<Vec<_>>()
+		};
+
+		let mut help_text = vec!["\nGeneral Keybindings\n".to_string()];
+		help_text.extend(get_section(false));
+		help_text.push(if read_only {
+			"\n Process Panel Keybindings (read-only)\n".to_string()
+		} else {
+			"\n Process Panel Keybindings\n".to_string()
+		});
+		help_text.extend(get_section(true));
+		help_text
+	}
+}
+
+/// Shift is folded into the character itself for character keys, and some platforms report it
+/// while others do not, so drop it after applying it to the character.
+fn normalise_key_event(key_event: KeyEvent) -> KeyEvent {
+	match key_event {
+		KeyEvent {
+			code: KeyCode::Char(c),
+			modifiers,
+		} if modifiers.contains(KeyModifiers::SHIFT) => KeyEvent::new(
+			KeyCode::Char(c.to_ascii_uppercase()),
+			modifiers - KeyModifiers::SHIFT,
+		),
+		_ => key_event,
+	}
+}
+
+fn parse_key_code(name: &str) -> Option<KeyCode> {
+	let mut chars = name.chars();
+	if let (Some(c), None) = (chars.next(), chars.next()) {
+		return Some(KeyCode::Char(c));
+	}
+
+	match name.to_lowercase().as_str() {
+		"esc" | "escape" => Some(KeyCode::Esc),
+		"enter" | "return" => Some(KeyCode::Enter),
+		"tab" => Some(KeyCode::Tab),
+		"backtab" => Some(KeyCode::BackTab),
+		"backspace" => Some(KeyCode::Backspace),
+		"delete" | "del" => Some(KeyCode::Delete),
+		"insert" | "ins" => Some(KeyCode::Insert),
+		"space" => Some(KeyCode::Char(' ')),
+		"up" => Some(KeyCode::Up),
+		"down" => Some(KeyCode::Down),
+		"left" => Some(KeyCode::Left),
+		"right" => Some(KeyCode::Right),
+		"home" => Some(KeyCode::Home),
+		"end" => Some(KeyCode::End),
+		"pageup" => Some(KeyCode::PageUp),
+		"pagedown" => Some(KeyCode::PageDown),
+		function_key if function_key.starts_with('f') => {
+			function_key[1..].parse::<u8>().ok().map(KeyCode::F)
+		}
+		_ => None,
+	}
+}
+
+/// Parses one whitespace-separated part of a key sequence, like `Ctrl-r`, `Up` or `dd`.  Parts
+/// that are not a key name are read as a sequence of characters.
+fn parse_key_sequence_part(part: &str) -> Option<KeySequence> {
+	let mut modifiers = KeyModifiers::empty();
+	let mut remaining = part;
+	loop {
+		let lowercase = remaining.to_lowercase();
+		let (modifier, prefix_len) =
+			if lowercase.starts_with("ctrl-") || lowercase.starts_with("ctrl+") {
+				(KeyModifiers::CONTROL, 5)
+			} else if lowercase.starts_with("alt-") || lowercase.starts_with("alt+") {
+				(KeyModifiers::ALT, 4)
+			} else if lowercase.starts_with("shift-") || lowercase.starts_with("shift+") {
+				(KeyModifiers::SHIFT, 6)
+			} else {
+				break;
+			};
+
+		// A dangling "Ctrl-" is a mistake, rather than the characters of "Ctrl-"
+		if remaining.len() == prefix_len {
+			return None;
+		}
+		modifiers |= modifier;
+		remaining = &remaining[prefix_len..];
+	}
+
+	if let Some(code) = parse_key_code(remaining) {
+		Some(vec![normalise_key_event(KeyEvent::new(code, modifiers))])
+	} else if modifiers.is_empty() {
+		Some(
+			remaining
+				.chars()
+				.map(|c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty()))
+				.collect(),
+		)
+	} else {
+		None
+	}
+}
+
+/// Parses a key sequence as written in the config file, like `q`, `Ctrl-c`, `dd` or `g Home`.
+pub fn parse_key_sequence(sequence: &str) -> Option<KeySequence> {
+	let mut key_sequence = Vec::new();
+	for part in sequence.split_whitespace() {
+		key_sequence.extend(parse_key_sequence_part(part)?);
+	}
+
+	if key_sequence.is_empty() {
+		None
+	} else {
+		Some(key_sequence)
+	}
+}
+
+fn key_event_to_string(key_event: &KeyEvent) -> String {
+	let mut key_string = String::new();
+	if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+		key_string += "Ctrl-";
+	}
+	if key_event.modifiers.contains(KeyModifiers::ALT) {
+		key_string += "Alt-";
+	}
+	if key_event.modifiers.contains(KeyModifiers::SHIFT) {
+		key_string += "Shift-";
+	}
+
+	key_string += &match key_event.code {
+		KeyCode::Char(' ') => "Space".to_string(),
+		KeyCode::Char(c) => c.to_string(),
+		KeyCode::F(num) => format!("F{}", num),
+		KeyCode::PageUp => "PageUp".to_string(),
+		KeyCode::PageDown => "PageDown".to_string(),
+		KeyCode::BackTab => "BackTab".to_string(),
+		other => format!("{:?}", other),
+	};
+	key_string
+}
+
+/// Writes a sequence out the same way it would be written in the config file.  Runs of plain
+/// characters are written together, like `dd`.
+pub fn key_sequence_to_string(sequence: &[KeyEvent]) -> String {
+	let mut sequence_string = String::new();
+	let mut previous_was_char = false;
+	for key_event in sequence {
+		let is_char = match key_event.code {
+			KeyCode::Char(c) => key_event.modifiers.is_empty() && !c.is_whitespace(),
+			_ => false,
+		};
+
+		let is_continuing_chars = is_char && previous_was_char;
+		if !sequence_string.is_empty() && !is_continuing_chars {
+			sequence_string.push(' ');
+		}
+		sequence_string += &key_event_to_string(key_event);
+		previous_was_char = is_char;
+	}
+	sequence_string
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	fn key(c: char) -> KeyEvent {
+		KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty())
+	}
+
+	fn chars(sequence: &str) -> KeySequence {
+		sequence.chars().map(key).collect()
+	}
+
+	#[test]
+	fn test_parse_key_sequence() {
+		assert_eq!(parse_key_sequence("q"), Some(vec![key('q')]));
+		assert_eq!(parse_key_sequence("dd"), Some(chars("dd")));
+		assert_eq!(
+			parse_key_sequence("Ctrl-c"),
+			Some(vec![KeyEvent::new(
+				KeyCode::Char('c'),
+				KeyModifiers::CONTROL
+			)])
+		);
+		assert_eq!(parse_key_sequence("ctrl+c"), parse_key_sequence("Ctrl-c"));
+		assert_eq!(
+			parse_key_sequence("Ctrl-Alt-Del"),
+			Some(vec![KeyEvent::new(
+				KeyCode::Delete,
+				KeyModifiers::CONTROL | KeyModifiers::ALT
+			)])
+		);
+		// Shift is applied to the character, rather than kept as a modifier.
+		assert_eq!(parse_key_sequence("Shift-g"), Some(vec![key('G')]));
+		assert_eq!(
+			parse_key_sequence("g Home"),
+			Some(vec![
+				key('g'),
+				KeyEvent::new(KeyCode::Home, KeyModifiers::empty())
+			])
+		);
+		assert_eq!(
+			parse_key_sequence("F5 Space"),
+			Some(vec![
+				KeyEvent::new(KeyCode::F(5), KeyModifiers::empty()),
+				key(' ')
+			])
+		);
+
+		assert_eq!(parse_key_sequence(""), None);
+		assert_eq!(parse_key_sequence("   "), None);
+		assert_eq!(parse_key_sequence("Ctrl-"), None);
+		assert_eq!(parse_key_sequence("Ctrl-foo"), None);
+		assert_eq!(parse_key_sequence("dd Alt-"), None);
+	}
+
+	#[test]
+	fn test_key_sequence_to_string() {
+		for sequence in &["q", "dd", "Ctrl-c", "Ctrl-Alt-Delete", "g Home", "F5 Space"] {
+			assert_eq!(
+				key_sequence_to_string(&parse_key_sequence(sequence).unwrap()),
+				*sequence
+			);
+		}
+	}
+
+	#[test]
+	fn test_on_key_event() {
+		let mut keymap = KeyMap::default();
+		assert_eq!(keymap.on_key_event(key('d')), None);
+		assert_eq!(keymap.on_key_event(key('d')), Some(Action::KillProcess));
+
+		// A key that doesn't continue the sequence starts a new one instead.
+		assert_eq!(keymap.on_key_event(key('d')), None);
+		assert_eq!(keymap.on_key_event(key('q')), Some(Action::Quit));
+		assert_eq!(keymap.on_key_event(key('g')), None);
+		assert_eq!(keymap.on_key_event(key('d')), None);
+		assert_eq!(keymap.on_key_event(key('d')), Some(Action::KillProcess));
+
+		// Keys bound to nothing are dropped.
+		assert_eq!(keymap.on_key_event(key('z')), None);
+		assert!(keymap.pending_keys.is_empty());
+
+		// Waiting too long between keys starts the sequence again.
+		assert_eq!(keymap.on_key_event(key('d')), None);
+		keymap.last_key_press -=
+			Duration::from_millis(constants::MAX_KEY_TIMEOUT_IN_MILLISECONDS as u64 + 1);
+		assert_eq!(keymap.on_key_event(key('d')), None);
+		assert_eq!(keymap.on_key_event(key('d')), Some(Action::KillProcess));
+
+		// Whether Shift is reported along with the character makes no difference.
+		assert_eq!(
+			keymap.on_key_event(KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+			Some(Action::SkipToLast)
+		);
+		assert_eq!(
+			keymap.on_key_event(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::SHIFT)),
+			Some(Action::SkipToLast)
+		);
+	}
+
+	#[test]
+	fn test_find_conflict() {
+		let mut keymap = KeyMap::default();
+		assert!(keymap.find_conflict().is_none());
+
+		// "d" would always be matched before "dd" could be finished.
+		keymap.set_bindings(Action::Freeze, vec![chars("d")]);
+		let (sequence, action, other_sequence, other_action) = keymap.find_conflict().unwrap();
+		assert_eq!(
+			(
+				sequence.clone(),
+				action,
+				other_sequence.clone(),
+				other_action
+			),
+			(chars("dd"), Action::KillProcess, chars("d"), Action::Freeze)
+		);
+
+		let keymap = KeyMap::new(vec![
+			(chars("x"), Action::Quit),
+			(chars("x"), Action::Freeze),
+		]);
+		assert!(keymap.find_conflict().is_some());
+
+		let keymap = KeyMap::new(vec![
+			(chars("gg"), Action::SkipToFirst),
+			(chars("gx"), Action::SkipToLast),
+		]);
+		assert!(keymap.find_conflict().is_none());
+	}
+
+	#[test]
+	fn test_get_help_text() {
+		let keymap = KeyMap::default();
+		let has_line = |help_text: &[String], action: Action| {
+			help_text
+				.iter()
+				.any(|line| line.ends_with(&format!(" {}\n", action.description())))
+		};
+
+		let help_text = keymap.get_help_text(false);
+		for action in ALL_ACTIONS.iter() {
+			assert!(has_line(&help_text, *action), "{:?}", action);
+		}
+		assert!(help_text.contains(&format!("dd {}\n", Action::KillProcess.description())));
+
+		let help_text = keymap.get_help_text(true);
+		for action in ALL_ACTIONS.iter() {
+			assert_eq!(
+				has_line(&help_text, *action),
+				!action.is_mutating(),
+				"{:?}",
+				action
+			);
+		}
+		assert!(help_text.contains(&"\n Process Panel Keybindings (read-only)\n".to_string()));
+	}
+}
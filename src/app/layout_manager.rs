@@ -0,0 +1,413 @@
+//! Houses the layout of the widgets drawn by the canvas, as well as the navigation between them.
+//!
+//! A layout is made of rows, which are split into columns, which are split into widgets.  Each
+//! level is sized by ratios relative to its siblings.
+
+use crate::app::ApplicationPosition;
+use std::cmp::{max, min};
+
+/// The virtual width and height used to figure out where widgets are relative to each other.
+const LAYOUT_MAX: u64 = 10_000;
+
+#[derive(Clone, Copy, Debug)]
+pub enum WidgetDirection {
+	Left,
+	Right,
+	Up,
+	Down,
+}
+
+#[derive(Clone, Debug)]
+pub struct BottomLayout {
+	pub rows: Vec<BottomRow>,
+	pub total_row_ratio: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct BottomRow {
+	pub children: Vec<BottomCol>,
+	pub total_col_ratio: u32,
+	pub ratio: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct BottomCol {
+	pub children: Vec<BottomWidget>,
+	pub total_widget_ratio: u32,
+	pub ratio: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct BottomWidget {
+	pub widget_type: ApplicationPosition,
+	pub widget_id: u64,
+	pub ratio: u32,
+	pub left_neighbour: Option<u64>,
+	pub right_neighbour: Option<u64>,
+	pub up_neighbour: Option<u64>,
+	pub down_neighbour: Option<u64>,
+}
+
+/// The bounds of a widget in the virtual layout space, as (x1, x2, y1, y2).
+type WidgetBounds = (u64, u64, u64, u64);
+
+impl BottomWidget {
+	pub fn new(widget_type: ApplicationPosition, ratio: u32) -> Self {
+		BottomWidget {
+			widget_type,
+			widget_id: 0,
+			ratio,
+			left_neighbour: None,
+			right_neighbour: None,
+			up_neighbour: None,
+			down_neighbour: None,
+		}
+	}
+}
+
+impl BottomCol {
+	pub fn new(children: Vec<BottomWidget>, ratio: u32) -> Self {
+		BottomCol {
+			total_widget_ratio: children.iter().map(|widget| widget.ratio).sum(),
+			children,
+			ratio,
+		}
+	}
+}
+
+impl BottomRow {
+	pub fn new(children: Vec<BottomCol>, ratio: u32) -> Self {
+		BottomRow {
+			total_col_ratio: children.iter().map(|col| col.ratio).sum(),
+			children,
+			ratio,
+		}
+	}
+}
+
+impl Default for BottomLayout {
+	fn default() -> Self {
+		BottomLayout::new(vec![
+			BottomRow::new(
+				vec![BottomCol::new(
+					vec![BottomWidget::new(ApplicationPosition::Cpu, 1)],
+					1,
+				)],
+				33,
+			),
+			BottomRow::new(
+				vec![
+					BottomCol::new(vec![BottomWidget::new(ApplicationPosition::Mem, 1)], 60),
+					BottomCol::new(
+						vec![
+							BottomWidget::new(ApplicationPosition::Temp, 1),
+							BottomWidget::new(ApplicationPosition::Disk, 1),
+						],
+						40,
+					),
+				],
+				34,
+			),
+			BottomRow::new(
+				vec![
+					BottomCol::new(vec![BottomWidget::new(ApplicationPosition::Network, 1)], 50),
+					BottomCol::new(vec![BottomWidget::new(ApplicationPosition::Process, 1)], 50),
+				],
+				34,
+			),
+		])
+	}
+}
+
+impl BottomLayout {
+	/// Creates a layout from the given rows, assigning widget IDs and computing the neighbours
+	/// of each widget.
+	pub fn new(rows: Vec<BottomRow>) -> Self {
+		let mut layout = BottomLayout {
+			total_row_ratio: rows.iter().map(|row| row.ratio).sum(),
+			rows,
+		};
+
+		for (widget_id, widget) in layout.widgets_mut().enumerate() {
+			widget.widget_id = widget_id as u64;
+		}
+
+		layout.compute_neighbours();
+		layout
+	}
+
+	/// Returns one of the built-in layouts, if the name matches one.
+	pub fn from_preset(preset: &str) -> Option<Self> {
+		match preset {
+			"default" => Some(BottomLayout::default()),
+			"processes" => Some(BottomLayout::new(vec![
+				BottomRow::new(
+					vec![BottomCol::new(
+						vec![BottomWidget::new(ApplicationPosition::Cpu, 1)],
+						1,
+					)],
+					25,
+				),
+				BottomRow::new(
+					vec![
+						BottomCol::new(vec![BottomWidget::new(ApplicationPosition::Mem, 1)], 1),
+						BottomCol::new(vec![BottomWidget::new(ApplicationPosition::Network, 1)], 1),
+					],
+					20,
+				),
+				BottomRow::new(
+					vec![BottomCol::new(
+						vec![BottomWidget::new(ApplicationPosition::Process, 1)],
+						1,
+					)],
+					55,
+				),
+			])),
+			"minimal" => Some(BottomLayout::new(vec![
+				BottomRow::new(
+					vec![
+						BottomCol::new(vec![BottomWidget::new(ApplicationPosition::Cpu, 1)], 1),
+						BottomCol::new(vec![BottomWidget::new(ApplicationPosition::Mem, 1)], 1),
+					],
+					1,
+				),
+				BottomRow::new(
+					vec![BottomCol::new(
+						vec![BottomWidget::new(ApplicationPosition::Process, 1)],
+						1,
+					)],
+					2,
+				),
+			])),
+			_ => None,
+		}
+	}
+
+	pub fn widgets(&self) -> impl Iterator<Item = &BottomWidget> {
+		self.rows
+			.iter()
+			.flat_map(|row| row.children.iter())
+			.flat_map(|col| col.children.iter())
+	}
+
+	fn widgets_mut(&mut self) -> impl Iterator<Item = &mut BottomWidget> {
+		self.rows
+			.iter_mut()
+			.flat_map(|row| row.children.iter_mut())
+			.flat_map(|col| col.children.iter_mut())
+	}
+
+	pub fn get_widget(&self, widget_id: u64) -> Option<&BottomWidget> {
+		self.widgets().find(|widget| widget.widget_id == widget_id)
+	}
+
+	/// The widget to select on startup; the processes widget if there is one, as that was the
+	/// old default, otherwise the first widget.
+	pub fn get_initial_widget(&self) -> Option<&BottomWidget> {
+		self.widgets()
+			.find(|widget| matches!(widget.widget_type, ApplicationPosition::Process))
+			.or_else(|| self.widgets().next())
+	}
+
+	pub fn get_neighbour(
+		&self, widget_id: u64, direction: WidgetDirection,
+	) -> Option<&BottomWidget> {
+		let widget = self.get_widget(widget_id)?;
+		let neighbour_id = match direction {
+			WidgetDirection::Left => widget.left_neighbour,
+			WidgetDirection::Right => widget.right_neighbour,
+			WidgetDirection::Up => widget.up_neighbour,
+			WidgetDirection::Down => widget.down_neighbour,
+		}?;
+
+		self.get_widget(neighbour_id)
+	}
+
+	/// Works out where each widget sits in a virtual LAYOUT_MAX by LAYOUT_MAX space.
+	fn get_widget_bounds(&self) -> Vec<(u64, WidgetBounds)> {
+		let mut widget_bounds = Vec::new();
+
+		let mut row_ratio_so_far = 0;
+		for row in &self.rows {
+			let row_y1 = scale(row_ratio_so_far, self.total_row_ratio, LAYOUT_MAX);
+			row_ratio_so_far += row.ratio;
+			let row_y2 = scale(row_ratio_so_far, self.total_row_ratio, LAYOUT_MAX);
+
+			let mut col_ratio_so_far = 0;
+			for col in &row.children {
+				let col_x1 = scale(col_ratio_so_far, row.total_col_ratio, LAYOUT_MAX);
+				col_ratio_so_far += col.ratio;
+				let col_x2 = scale(col_ratio_so_far, row.total_col_ratio, LAYOUT_MAX);
+
+				let mut widget_ratio_so_far = 0;
+				for widget in &col.children {
+					let widget_y1 = row_y1
+						+ scale(widget_ratio_so_far, col.total_widget_ratio, row_y2 - row_y1);
+					widget_ratio_so_far += widget.ratio;
+					let widget_y2 = row_y1
+						+ scale(widget_ratio_so_far, col.total_widget_ratio, row_y2 - row_y1);
+
+					widget_bounds.push((widget.widget_id, (col_x1, col_x2, widget_y1, widget_y2)));
+				}
+			}
+		}
+
+		widget_bounds
+	}
+
+	/// For each widget and direction, picks the closest widget lying entirely in that
+	/// direction that also overlaps with it on the other axis.  Ties are broken by whichever
+	/// shares the most of its edge with the widget, and then by whichever is further
+	/// up or to the left.
+	fn compute_neighbours(&mut self) {
+		let widget_bounds = self.get_widget_bounds();
+
+		let find_neighbour = |bounds: &WidgetBounds, direction: WidgetDirection| -> Option<u64> {
+			widget_bounds
+				.iter()
+				.filter_map(|(other_id, other_bounds)| {
+					let (distance, overlap, tiebreak) = match direction {
+						WidgetDirection::Left if other_bounds.1 <= bounds.0 => (
+							bounds.0 - other_bounds.1,
+							get_overlap((bounds.2, bounds.3), (other_bounds.2, other_bounds.3)),
+							other_bounds.2,
+						),
+						WidgetDirection::Right if other_bounds.0 >= bounds.1 => (
+							other_bounds.0 - bounds.1,
+							get_overlap((bounds.2, bounds.3), (other_bounds.2, other_bounds.3)),
+							other_bounds.2,
+						),
+						WidgetDirection::Up if other_bounds.3 <= bounds.2 => (
+							bounds.2 - other_bounds.3,
+							get_overlap((bounds.0, bounds.1), (other_bounds.0, other_bounds.1)),
+							other_bounds.0,
+						),
+						WidgetDirection::Down if other_bounds.2 >= bounds.3 => (
+							other_bounds.2 - bounds.3,
+							get_overlap((bounds.0, bounds.1), (other_bounds.0, other_bounds.1)),
+							other_bounds.0,
+						),
+						_ => return None,
+					};
+
+					if overlap > 0 {
+						Some((distance, overlap, tiebreak, *other_id))
+					} else {
+						None
+					}
+				})
+				.min_by(|a, b| {
+					a.0.cmp(&b.0)
+						.then_with(|| b.1.cmp(&a.1))
+						.then_with(|| a.2.cmp(&b.2))
+				})
+				.map(|(_, _, _, other_id)| other_id)
+		};
+
+		let neighbours = widget_bounds
+			.iter()
+			.map(|(widget_id, bounds)| {
+				(
+					*widget_id,
+					find_neighbour(bounds, WidgetDirection::Left),
+					find_neighbour(bounds, WidgetDirection::Right),
+					find_neighbour(bounds, WidgetDirection::Up),
+					find_neighbour(bounds, WidgetDirection::Down),
+				)
+			})
+			.collect::<Vec<_>>();
+
+		for (widget, (_, left, right, up, down)) in self.widgets_mut().zip(neighbours) {
+			widget.left_neighbour = left;
+			widget.right_neighbour = right;
+			widget.up_neighbour = up;
+			widget.down_neighbour = down;
+		}
+	}
+}
+
+fn scale(ratio_so_far: u32, total_ratio: u32, length: u64) -> u64 {
+	if total_ratio == 0 {
+		0
+	} else {
+		u64::from(ratio_so_far) * length / u64::from(total_ratio)
+	}
+}
+
+fn get_overlap(a: (u64, u64), b: (u64, u64)) -> u64 {
+	min(a.1, b.1).saturating_sub(max(a.0, b.0))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The left, right, up and down neighbours of each widget, in order of widget ID.
+	fn get_neighbours(layout: &BottomLayout) -> Vec<[Option<u64>; 4]> {
+		layout
+			.widgets()
+			.enumerate()
+			.map(|(index, widget)| {
+				assert_eq!(widget.widget_id, index as u64);
+				[
+					widget.left_neighbour,
+					widget.right_neighbour,
+					widget.up_neighbour,
+					widget.down_neighbour,
+				]
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_default_neighbours() {
+		// CPU (0) on top; memory (1), temperatures (2) above disks (3) in the middle; network (4)
+		// and processes (5) at the bottom.
+		let layout = BottomLayout::from_preset("default").unwrap();
+		assert_eq!(
+			get_neighbours(&layout),
+			vec![
+				[None, None, None, Some(1)],
+				[None, Some(2), Some(0), Some(4)],
+				[Some(1), None, Some(0), Some(3)],
+				[Some(1), None, Some(2), Some(5)],
+				[None, Some(5), Some(1), None],
+				[Some(4), None, Some(3), None],
+			]
+		);
+	}
+
+	#[test]
+	fn test_processes_neighbours() {
+		// CPU (0) on top, memory (1) beside network (2), then processes (3).
+		let layout = BottomLayout::from_preset("processes").unwrap();
+		assert_eq!(
+			get_neighbours(&layout),
+			vec![
+				[None, None, None, Some(1)],
+				[None, Some(2), Some(0), Some(3)],
+				[Some(1), None, Some(0), Some(3)],
+				[None, None, Some(1), None],
+			]
+		);
+	}
+
+	#[test]
+	fn test_minimal_neighbours() {
+		// CPU (0) beside memory (1), above processes (2).
+		let layout = BottomLayout::from_preset("minimal").unwrap();
+		assert_eq!(
+			get_neighbours(&layout),
+			vec![
+				[None, Some(1), None, Some(2)],
+				[Some(0), None, None, Some(2)],
+				[None, None, Some(0), None],
+			]
+		);
+	}
+
+	#[test]
+	fn test_unknown_preset() {
+		assert!(BottomLayout::from_preset("everything").is_none());
+	}
+}
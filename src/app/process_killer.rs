@@ -1,6 +1,6 @@
 /// This file is meant to house (OS specific) implementations on how to kill processes.
-use crate::utils::error::BottomError;
-use std::process::Command;
+use crate::{app::data_collection::processes, utils::error::BottomError};
+use std::time::{Duration, Instant};
 
 // Copied from SO: https://stackoverflow.com/a/55231715
 #[cfg(target_os = "windows")]
@@ -31,21 +31,125 @@ impl Process {
 	}
 }
 
-/// Kills a process, given a PID.
-pub fn kill_process_given_pid(pid: u32) -> crate::utils::error::Result<()> {
-	if cfg!(target_os = "linux") {
-		Command::new("kill").arg(pid.to_string()).output()?;
+/// The signals that can be picked in the dd dialog.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Signal {
+	Term,
+	Kill,
+	Int,
+	Hup,
+	Stop,
+	Cont,
+	Usr1,
+	Usr2,
+}
+
+impl Default for Signal {
+	fn default() -> Self {
+		Signal::Term
+	}
+}
+
+/// Every signal, in the order they are listed in the dd dialog.
+pub const ALL_SIGNALS: [Signal; 8] = [
+	Signal::Term,
+	Signal::Kill,
+	Signal::Int,
+	Signal::Hup,
+	Signal::Stop,
+	Signal::Cont,
+	Signal::Usr1,
+	Signal::Usr2,
+];
+
+impl Signal {
+	pub fn name(self) -> &'static str {
+		match self {
+			Signal::Term => "SIGTERM",
+			Signal::Kill => "SIGKILL",
+			Signal::Int => "SIGINT",
+			Signal::Hup => "SIGHUP",
+			Signal::Stop => "SIGSTOP",
+			Signal::Cont => "SIGCONT",
+			Signal::Usr1 => "SIGUSR1",
+			Signal::Usr2 => "SIGUSR2",
+		}
+	}
+
+	#[cfg(target_family = "unix")]
+	fn as_libc_signal(self) -> std::os::raw::c_int {
+		match self {
+			Signal::Term => libc::SIGTERM,
+			Signal::Kill => libc::SIGKILL,
+			Signal::Int => libc::SIGINT,
+			Signal::Hup => libc::SIGHUP,
+			Signal::Stop => libc::SIGSTOP,
+			Signal::Cont => libc::SIGCONT,
+			Signal::Usr1 => libc::SIGUSR1,
+			Signal::Usr2 => libc::SIGUSR2,
+		}
+	}
+}
+
+#[cfg(target_family = "unix")]
+fn send_unix_signal(pid: u32, signal: Signal) -> crate::utils::error::Result<()> {
+	// A PID of 0 (or anything that overflows into a negative PID) would signal a whole group of
+	// processes instead, which is never what we want.
+	if pid == 0 || pid > libc::pid_t::MAX as u32 {
+		return Err(BottomError::SignalError {
+			message: format!("{} is not a valid PID to send {} to.", pid, signal.name()),
+		});
+	}
+
+	// This is safe, as kill only takes plain integers and reports failure through errno.
+	if unsafe { libc::kill(pid as libc::pid_t, signal.as_libc_signal()) } == 0 {
+		return Ok(());
+	}
+
+	let err = std::io::Error::last_os_error();
+	let message = match err.raw_os_error() {
+		Some(libc::EPERM) => format!(
+			"Permission denied sending {} to PID {} (EPERM); it may belong to another user.",
+			signal.name(),
+			pid
+		),
+		Some(libc::ESRCH) => format!(
+			"PID {} no longer exists, so {} was not sent (ESRCH).",
+			pid,
+			signal.name()
+		),
+		_ => format!("Failed to send {} to PID {}: {}", signal.name(), pid, err),
+	};
+	Err(BottomError::SignalError { message })
+}
+
+/// Sends a signal to a process, given a PID.
+pub fn kill_process_given_pid(pid: u32, signal: Signal) -> crate::utils::error::Result<()> {
+	if cfg!(target_family = "unix") {
+		#[cfg(target_family = "unix")]
+		{
+			send_unix_signal(pid, signal)?;
+		}
 	} else if cfg!(target_os = "windows") {
 		#[cfg(target_os = "windows")]
 		{
-			let process = Process::open(pid as DWORD)?;
-			process.kill()?;
+			// Windows has no signals, so the best we can do is terminate the process.
+			match signal {
+				Signal::Term | Signal::Kill => {
+					let process = Process::open(pid as DWORD)?;
+					process.kill()?;
+				}
+				_ => {
+					return Err(BottomError::SignalError {
+						message: format!(
+							"Only SIGTERM and SIGKILL are supported on Windows, so {} was not sent to PID {}.",
+							signal.name(),
+							pid
+						),
+					});
+				}
+			}
 		}
-	} else if cfg!(target_os = "macos") {
-		// TODO: macOS
-		return Err(BottomError::GenericError {
-			message: "Sorry, macOS support is not implemented yet!".to_string(),
-		});
 	} else {
 		return Err(BottomError::GenericError {
 			message:
@@ -56,3 +160,367 @@ pub fn kill_process_given_pid(pid: u32) -> crate::utils::error::Result<()> {
 
 	Ok(())
 }
+
+/// A process picked to be signalled, told apart by when it started from any process that is
+/// given the same PID after it exits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProcessIdentity {
+	pub pid: u32,
+	/// When the process started, in clock ticks since boot, if known; see
+	/// `ProcessData::start_ticks`.
+	pub start_ticks: Option<u64>,
+}
+
+/// Checks that a PID still belongs to the process that was picked, given when the process now
+/// using it started.  If either start time isn't known, there is nothing to go on, so this passes.
+fn check_process_identity(
+	identity: ProcessIdentity, current_start_ticks: Option<u64>, signal: Signal,
+) -> crate::utils::error::Result<()> {
+	match (identity.start_ticks, current_start_ticks) {
+		(Some(start_ticks), Some(current_start_ticks)) if start_ticks != current_start_ticks => {
+			Err(BottomError::SignalError {
+				message: format!(
+					"PID {} now belongs to a different process, as the one picked has exited, so {} was not sent.",
+					identity.pid,
+					signal.name()
+				),
+			})
+		}
+		_ => Ok(()),
+	}
+}
+
+/// Sends a signal to a process, given its identity.  Its PID is checked to still belong to it
+/// right before the signal is sent, so a process that happens to have been given the same PID
+/// since it was picked is never signalled instead.
+pub fn kill_process_given_identity(
+	identity: ProcessIdentity, signal: Signal,
+) -> crate::utils::error::Result<()> {
+	check_process_identity(identity, processes::get_start_ticks(identity.pid), signal)?;
+	kill_process_given_pid(identity.pid, signal)
+}
+
+/// How far along a process is in being terminated with escalation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EscalationState {
+	/// SIGTERM was sent, and SIGKILL will be if the process is still running once the grace
+	/// period is over.
+	Terminating,
+	/// SIGKILL was sent, as the process outlasted the grace period.
+	Killing,
+	/// The process exited after SIGTERM.
+	Terminated,
+	/// The process exited after SIGKILL.
+	Killed,
+	/// The process had already exited before SIGTERM could be sent.
+	AlreadyExited,
+	/// The escalation was cancelled before SIGKILL was sent.
+	Cancelled,
+	/// A signal could not be sent, for the given reason.
+	Failed(String),
+}
+
+/// A process being terminated with escalation.
+#[derive(Clone, Debug)]
+pub struct EscalatingProcess {
+	pub identity: ProcessIdentity,
+	pub name: String,
+	pub state: EscalationState,
+}
+
+/// Terminates processes politely, but firmly: each is sent SIGTERM, and then SIGKILL if it is
+/// still running once the grace period is over.  Whether they are still running is checked on
+/// every refresh, through `update`.
+#[derive(Clone, Debug)]
+pub struct KillEscalation {
+	pub processes: Vec<EscalatingProcess>,
+	pub grace_period: Duration,
+	started: Instant,
+	is_cancelled: bool,
+}
+
+impl KillEscalation {
+	/// Sends SIGTERM (through `send_term`) to each of the given processes, in order.
+	pub fn start(
+		processes: &[(ProcessIdentity, String)], grace_period: Duration, started: Instant,
+		is_running: impl Fn(ProcessIdentity) -> bool,
+		send_term: impl Fn(ProcessIdentity) -> crate::utils::error::Result<()>,
+	) -> Self {
+		KillEscalation {
+			processes: processes
+				.iter()
+				.map(|(identity, name)| EscalatingProcess {
+					identity: *identity,
+					name: name.clone(),
+					state: match send_term(*identity) {
+						Ok(()) => EscalationState::Terminating,
+						Err(_) if !is_running(*identity) => EscalationState::AlreadyExited,
+						Err(err) => EscalationState::Failed(get_signal_error_message(err)),
+					},
+				})
+				.collect(),
+			grace_period,
+			started,
+			is_cancelled: false,
+		}
+	}
+
+	/// Checks which processes have exited, and sends SIGKILL (through `send_kill`) to those
+	/// still running once the grace period is over.
+	pub fn update(
+		&mut self, now: Instant, is_running: impl Fn(ProcessIdentity) -> bool,
+		send_kill: impl Fn(ProcessIdentity) -> crate::utils::error::Result<()>,
+	) {
+		let is_grace_period_over = now.duration_since(self.started) >= self.grace_period;
+		for process in &mut self.processes {
+			process.state = match &process.state {
+				EscalationState::Terminating if !is_running(process.identity) => {
+					EscalationState::Terminated
+				}
+				EscalationState::Terminating if is_grace_period_over => {
+					match send_kill(process.identity) {
+						Ok(()) => EscalationState::Killing,
+						// It exited between being checked and SIGKILL being sent.
+						Err(_) if !is_running(process.identity) => EscalationState::Terminated,
+						Err(err) => EscalationState::Failed(get_signal_error_message(err)),
+					}
+				}
+				EscalationState::Killing if !is_running(process.identity) => {
+					EscalationState::Killed
+				}
+				state => state.clone(),
+			};
+		}
+	}
+
+	/// How long is left until SIGKILL is sent, rounded up to the second.
+	pub fn get_seconds_left(&self, now: Instant) -> u64 {
+		let left = self
+			.grace_period
+			.checked_sub(now.duration_since(self.started))
+			.unwrap_or_default();
+		left.as_secs() + u64::from(left.subsec_nanos() > 0)
+	}
+
+	/// Stops SIGKILL from being sent to the processes still waiting for it, returning them.
+	pub fn cancel(&mut self) -> Vec<ProcessIdentity> {
+		self.is_cancelled = true;
+		self.processes
+			.iter_mut()
+			.filter(|process| process.state == EscalationState::Terminating)
+			.map(|process| {
+				process.state = EscalationState::Cancelled;
+				process.identity
+			})
+			.collect()
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.is_cancelled
+	}
+
+	/// Whether every process has either exited or failed to be signalled, or the escalation was
+	/// cancelled.
+	pub fn is_done(&self) -> bool {
+		self.is_cancelled
+			|| self.processes.iter().all(|process| {
+				!matches!(
+					process.state,
+					EscalationState::Terminating | EscalationState::Killing
+				)
+			})
+	}
+}
+
+fn get_signal_error_message(err: BottomError) -> String {
+	match err {
+		BottomError::SignalError { message } => message,
+		err => err.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::cell::RefCell;
+
+	fn get_identity(pid: u32) -> ProcessIdentity {
+		ProcessIdentity {
+			pid,
+			start_ticks: Some(40213),
+		}
+	}
+
+	fn get_states(escalation: &KillEscalation) -> Vec<EscalationState> {
+		escalation
+			.processes
+			.iter()
+			.map(|process| process.state.clone())
+			.collect()
+	}
+
+	#[test]
+	fn test_kill_escalation() {
+		let start = Instant::now();
+		let processes = vec![
+			(get_identity(100), "polite".to_string()),
+			(get_identity(200), "stubborn".to_string()),
+			(get_identity(300), "unkillable".to_string()),
+		];
+		let mut escalation = KillEscalation::start(
+			&processes,
+			Duration::from_secs(5),
+			start,
+			|_| true,
+			|_| Ok(()),
+		);
+		assert_eq!(escalation.get_seconds_left(start), 5);
+
+		let running = RefCell::new(vec![200, 300]);
+		let killed = RefCell::new(Vec::new());
+		let is_running = |identity: ProcessIdentity| running.borrow().contains(&identity.pid);
+		let send_kill = |identity: ProcessIdentity| {
+			killed.borrow_mut().push(identity.pid);
+			Ok(())
+		};
+
+		// Before the grace period is over, only what has exited changes.
+		let later = start + Duration::from_millis(2500);
+		escalation.update(later, is_running, send_kill);
+		assert_eq!(
+			get_states(&escalation),
+			vec![
+				EscalationState::Terminated,
+				EscalationState::Terminating,
+				EscalationState::Terminating
+			]
+		);
+		assert_eq!(escalation.get_seconds_left(later), 3);
+		assert!(killed.borrow().is_empty());
+
+		let later = start + Duration::from_secs(5);
+		escalation.update(later, is_running, send_kill);
+		assert_eq!(*killed.borrow(), vec![200, 300]);
+		assert_eq!(escalation.get_seconds_left(later), 0);
+		assert!(!escalation.is_done());
+
+		// SIGKILL isn't sent twice, and a process that survives it is still waited on.
+		running.borrow_mut().retain(|pid| *pid != 200);
+		escalation.update(later + Duration::from_secs(1), is_running, send_kill);
+		assert_eq!(*killed.borrow(), vec![200, 300]);
+		assert_eq!(
+			get_states(&escalation),
+			vec![
+				EscalationState::Terminated,
+				EscalationState::Killed,
+				EscalationState::Killing
+			]
+		);
+		assert!(!escalation.is_done());
+	}
+
+	#[test]
+	fn test_cancel_kill_escalation() {
+		let start = Instant::now();
+		let processes = vec![
+			(get_identity(100), "polite".to_string()),
+			(get_identity(200), "stubborn".to_string()),
+		];
+		let mut escalation = KillEscalation::start(
+			&processes,
+			Duration::from_secs(5),
+			start,
+			|_| true,
+			|_| Ok(()),
+		);
+		escalation.update(start, |identity| identity.pid == 200, |_| Ok(()));
+		assert!(!escalation.is_done());
+
+		// Only the processes still waiting for SIGKILL are cancelled.
+		assert_eq!(escalation.cancel(), vec![get_identity(200)]);
+		assert_eq!(
+			get_states(&escalation),
+			vec![EscalationState::Terminated, EscalationState::Cancelled]
+		);
+		assert!(escalation.is_cancelled());
+		assert!(escalation.is_done());
+
+		let killed = RefCell::new(Vec::new());
+		escalation.update(
+			start + Duration::from_secs(5),
+			|_| true,
+			|identity| {
+				killed.borrow_mut().push(identity.pid);
+				Ok(())
+			},
+		);
+		assert!(killed.borrow().is_empty());
+	}
+
+	#[test]
+	fn test_kill_escalation_failures() {
+		let start = Instant::now();
+		let processes = vec![
+			(get_identity(100), "other user's".to_string()),
+			(get_identity(200), "mine".to_string()),
+			(get_identity(300), "gone".to_string()),
+			(get_identity(400), "stubborn".to_string()),
+		];
+		let running = RefCell::new(vec![100, 200, 400]);
+		let is_running = |identity: ProcessIdentity| running.borrow().contains(&identity.pid);
+		let no_such_process = |identity: ProcessIdentity| {
+			Err(BottomError::SignalError {
+				message: format!("PID {} no longer exists", identity.pid),
+			})
+		};
+		let mut escalation = KillEscalation::start(
+			&processes,
+			Duration::from_secs(1),
+			start,
+			is_running,
+			|identity| match identity.pid {
+				100 => Err(BottomError::SignalError {
+					message: "Permission denied".to_string(),
+				}),
+				300 => no_such_process(identity),
+				_ => Ok(()),
+			},
+		);
+
+		// 400 exits just before SIGKILL can be sent to it, so it isn't a failure either.
+		running.borrow_mut().retain(|pid| *pid != 400);
+		escalation.update(
+			start + Duration::from_secs(1),
+			|identity| identity.pid == 200 || is_running(identity),
+			no_such_process,
+		);
+		assert_eq!(
+			get_states(&escalation),
+			vec![
+				EscalationState::Failed("Permission denied".to_string()),
+				EscalationState::Failed("PID 200 no longer exists".to_string()),
+				EscalationState::AlreadyExited,
+				EscalationState::Terminated,
+			]
+		);
+		assert!(escalation.is_done());
+	}
+
+	#[test]
+	fn test_check_process_identity() {
+		assert!(check_process_identity(get_identity(100), Some(40213), Signal::Term).is_ok());
+		// Whatever has the PID now started later, so it isn't the process that was picked.
+		let err = check_process_identity(get_identity(100), Some(51000), Signal::Kill).unwrap_err();
+		assert_eq!(
+			get_signal_error_message(err),
+			"PID 100 now belongs to a different process, as the one picked has exited, so SIGKILL was not sent."
+		);
+		// With nothing to compare, the signal is sent, and fails if the process is gone.
+		assert!(check_process_identity(get_identity(100), None, Signal::Term).is_ok());
+		let unknown = ProcessIdentity {
+			pid: 100,
+			start_ticks: None,
+		};
+		assert!(check_process_identity(unknown, Some(51000), Signal::Term).is_ok());
+	}
+}
@@ -0,0 +1,272 @@
+//! Changes how processes are scheduled: their niceness, and which CPUs they are allowed to run on.
+use crate::utils::error::{BottomError, Result};
+
+/// The niceness of the process most favoured by the scheduler.
+pub const MIN_NICE: i64 = -20;
+/// The niceness of the process least favoured by the scheduler.
+pub const MAX_NICE: i64 = 19;
+
+/// Brings a niceness within the range the scheduler accepts.
+pub fn clamp_nice(nice: i64) -> i64 {
+	nice.clamp(MIN_NICE, MAX_NICE)
+}
+
+/// On Linux, niceness and affinity belong to each thread rather than to the whole process, so
+/// every thread has to be changed, as `renice` and `taskset -a` do.
+#[cfg(target_family = "unix")]
+fn get_thread_ids(pid: u32) -> Vec<u32> {
+	let mut thread_ids = std::fs::read_dir(format!("/proc/{}/task", pid))
+		.map(|entries| {
+			entries
+				.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+				.filter(|thread_id| *thread_id != pid)
+				.collect::<Vec<_>>()
+		})
+		.unwrap_or_default();
+	// The main thread goes first, so that if it fails, the error is about the process itself.
+	thread_ids.insert(0, pid);
+	thread_ids
+}
+
+#[cfg(target_family = "unix")]
+fn check_pid(pid: u32) -> Result<()> {
+	// A PID of 0 means the calling process, which is never what we want.
+	if pid == 0 || pid > libc::pid_t::MAX as u32 {
+		Err(BottomError::SchedulingError {
+			message: format!("{} is not a valid PID.", pid),
+		})
+	} else {
+		Ok(())
+	}
+}
+
+/// Applies a change to every thread of a process.  Threads other than the main one may exit
+/// while this happens, which is not a failure.
+#[cfg(target_family = "unix")]
+fn apply_to_threads(
+	pid: u32, apply: impl Fn(u32) -> std::os::raw::c_int,
+	describe_error: impl Fn(&std::io::Error) -> String,
+) -> Result<()> {
+	check_pid(pid)?;
+
+	for thread_id in get_thread_ids(pid) {
+		if apply(thread_id) != 0 {
+			let err = std::io::Error::last_os_error();
+			if thread_id != pid && err.raw_os_error() == Some(libc::ESRCH) {
+				continue;
+			}
+
+			return Err(BottomError::SchedulingError {
+				message: match err.raw_os_error() {
+					Some(libc::ESRCH) => format!("PID {} no longer exists (ESRCH).", pid),
+					_ => describe_error(&err),
+				},
+			});
+		}
+	}
+
+	Ok(())
+}
+
+/// Sets the niceness of a process.  Only root can make a process less nice than it is.
+pub fn set_process_nice(pid: u32, nice: i64) -> Result<()> {
+	#[cfg(target_family = "unix")]
+	{
+		let nice = clamp_nice(nice);
+		apply_to_threads(
+			pid,
+			// This is safe, as setpriority only takes plain integers and reports failure through
+			// errno.
+			|thread_id| unsafe {
+				libc::setpriority(
+					libc::PRIO_PROCESS,
+					thread_id as libc::id_t,
+					nice as std::os::raw::c_int,
+				)
+			},
+			|err| match err.raw_os_error() {
+				Some(libc::EACCES) => format!(
+					"Permission denied lowering the niceness of PID {} to {} (EACCES); only root can do this.",
+					pid, nice
+				),
+				Some(libc::EPERM) => format!(
+					"Permission denied changing the niceness of PID {} (EPERM); it may belong to another user.",
+					pid
+				),
+				_ => format!("Failed to change the niceness of PID {}: {}", pid, err),
+			},
+		)
+	}
+
+	#[cfg(not(target_family = "unix"))]
+	{
+		let _ = nice;
+		Err(BottomError::SchedulingError {
+			message: format!(
+				"Changing the niceness of PID {} is not supported on this platform.",
+				pid
+			),
+		})
+	}
+}
+
+/// Gets which of the first `num_cpus` CPUs a process is allowed to run on.
+pub fn get_process_affinity(pid: u32, num_cpus: usize) -> Result<Vec<bool>> {
+	#[cfg(target_os = "linux")]
+	{
+		check_pid(pid)?;
+
+		// This is safe, as the set is plain data that is zeroed before use, and
+		// sched_getaffinity is told its size so it can't write past it.
+		unsafe {
+			let mut cpu_set: libc::cpu_set_t = std::mem::zeroed();
+			if libc::sched_getaffinity(
+				pid as libc::pid_t,
+				std::mem::size_of::<libc::cpu_set_t>(),
+				&mut cpu_set,
+			) != 0
+			{
+				return Err(BottomError::SchedulingError {
+					message: format!(
+						"Failed to get the CPU affinity of PID {}: {}",
+						pid,
+						std::io::Error::last_os_error()
+					),
+				});
+			}
+
+			// CPUs past the end of the set can't be in it.
+			Ok((0..num_cpus)
+				.map(|cpu| cpu < libc::CPU_SETSIZE as usize && libc::CPU_ISSET(cpu, &cpu_set))
+				.collect())
+		}
+	}
+
+	#[cfg(not(target_os = "linux"))]
+	{
+		let _ = num_cpus;
+		Err(BottomError::SchedulingError {
+			message: format!(
+				"Getting the CPU affinity of PID {} is only supported on Linux.",
+				pid
+			),
+		})
+	}
+}
+
+/// Builds the set of CPUs to pass to sched_setaffinity, where `cpus[n]` is whether CPU n is in
+/// it.  The set only has room for the first CPU_SETSIZE CPUs.
+#[cfg(target_os = "linux")]
+fn get_cpu_set(cpus: &[bool]) -> Result<libc::cpu_set_t> {
+	// This is safe, as the set is plain data that is zeroed before use.
+	let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+	for (cpu, is_allowed) in cpus.iter().enumerate() {
+		if *is_allowed {
+			if cpu >= libc::CPU_SETSIZE as usize {
+				return Err(BottomError::SchedulingError {
+					message: format!(
+						"CPU {} can't be picked, as only the first {} CPUs can be.",
+						cpu,
+						libc::CPU_SETSIZE
+					),
+				});
+			}
+			// This is safe, as CPU_SET only sets a bit in the set, which was just checked to
+			// have room for it.
+			unsafe { libc::CPU_SET(cpu, &mut cpu_set) };
+		}
+	}
+	Ok(cpu_set)
+}
+
+/// Sets which CPUs a process is allowed to run on, where `cpus[n]` is whether it may use CPU n.
+pub fn set_process_affinity(pid: u32, cpus: &[bool]) -> Result<()> {
+	if !cpus.iter().any(|is_allowed| *is_allowed) {
+		return Err(BottomError::SchedulingError {
+			message: "At least one CPU has to be picked.".to_string(),
+		});
+	}
+
+	#[cfg(target_os = "linux")]
+	{
+		let cpu_set = get_cpu_set(cpus)?;
+		apply_to_threads(
+			pid,
+			// This is safe, as sched_setaffinity is told the size of the set it reads.
+			|thread_id| unsafe {
+				libc::sched_setaffinity(
+					thread_id as libc::pid_t,
+					std::mem::size_of::<libc::cpu_set_t>(),
+					&cpu_set,
+				)
+			},
+			|err| {
+				match err.raw_os_error() {
+				Some(libc::EPERM) => format!(
+					"Permission denied changing the CPU affinity of PID {} (EPERM); it may belong to another user.",
+					pid
+				),
+				Some(libc::EINVAL) => format!(
+					"PID {} is not allowed to run on any of the picked CPUs (EINVAL).",
+					pid
+				),
+				_ => format!("Failed to change the CPU affinity of PID {}: {}", pid, err),
+			}
+			},
+		)
+	}
+
+	#[cfg(not(target_os = "linux"))]
+	{
+		Err(BottomError::SchedulingError {
+			message: format!(
+				"Changing the CPU affinity of PID {} is only supported on Linux.",
+				pid
+			),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_clamp_nice() {
+		assert_eq!(clamp_nice(0), 0);
+		assert_eq!(clamp_nice(-20), -20);
+		assert_eq!(clamp_nice(19), 19);
+		assert_eq!(clamp_nice(-100), MIN_NICE);
+		assert_eq!(clamp_nice(100), MAX_NICE);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn test_get_cpu_set() {
+		let cpu_set = get_cpu_set(&[true, false, true, false]).unwrap();
+		// This is safe, as every CPU checked is within the set.
+		let is_set = |cpu| unsafe { libc::CPU_ISSET(cpu, &cpu_set) };
+		assert_eq!(
+			(0..8).map(is_set).collect::<Vec<_>>(),
+			vec![true, false, true, false, false, false, false, false]
+		);
+
+		let setsize = libc::CPU_SETSIZE as usize;
+		let mut cpus = vec![false; setsize];
+		cpus[setsize - 1] = true;
+		let cpu_set = get_cpu_set(&cpus).unwrap();
+		assert!(unsafe { libc::CPU_ISSET(setsize - 1, &cpu_set) });
+
+		// CPUs past the end of the set can be left out, but not picked.
+		cpus.push(false);
+		assert!(get_cpu_set(&cpus).is_ok());
+		cpus[setsize] = true;
+		assert!(get_cpu_set(&cpus).is_err());
+	}
+
+	#[test]
+	fn test_set_process_affinity_without_cpus() {
+		assert!(set_process_affinity(1, &[]).is_err());
+		assert!(set_process_affinity(1, &[false, false]).is_err());
+	}
+}
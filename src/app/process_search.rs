@@ -0,0 +1,226 @@
+//! The state of the search bar of the process widget, which filters the processes shown.
+
+use regex::{Regex, RegexBuilder};
+
+#[derive(Default)]
+pub struct ProcessSearchState {
+	/// Whether the search bar is shown, and so whether the filter is applied.
+	pub is_enabled: bool,
+	/// Whether keys are going into the search bar rather than being treated as actions.
+	pub is_typing: bool,
+	pub current_query: String,
+	pub is_regex: bool,
+	pub is_case_sensitive: bool,
+	pub is_whole_word: bool,
+	compiled_query: Option<Result<Regex, regex::Error>>,
+}
+
+impl ProcessSearchState {
+	pub fn open(&mut self) {
+		self.is_enabled = true;
+		self.is_typing = true;
+	}
+
+	pub fn close(&mut self) {
+		self.is_enabled = false;
+		self.is_typing = false;
+		self.current_query.clear();
+		self.update_query();
+	}
+
+	pub fn push_char(&mut self, c: char) {
+		self.current_query.push(c);
+		self.update_query();
+	}
+
+	pub fn pop_char(&mut self) {
+		self.current_query.pop();
+		self.update_query();
+	}
+
+	pub fn clear_query(&mut self) {
+		self.current_query.clear();
+		self.update_query();
+	}
+
+	pub fn toggle_regex(&mut self) {
+		self.is_regex = !self.is_regex;
+		self.update_query();
+	}
+
+	pub fn toggle_case_sensitive(&mut self) {
+		self.is_case_sensitive = !self.is_case_sensitive;
+		self.update_query();
+	}
+
+	pub fn toggle_whole_word(&mut self) {
+		self.is_whole_word = !self.is_whole_word;
+		self.update_query();
+	}
+
+	/// Recompiles the query, so this is only done when it changes rather than on every refresh.
+	fn update_query(&mut self) {
+		self.compiled_query = if self.current_query.is_empty() {
+			None
+		} else {
+			let pattern = if self.is_regex {
+				self.current_query.clone()
+			} else {
+				regex::escape(&self.current_query)
+			};
+			let pattern = if self.is_whole_word {
+				format!(r"\b(?:{})\b", pattern)
+			} else {
+				pattern
+			};
+
+			Some(
+				RegexBuilder::new(&pattern)
+					.case_insensitive(!self.is_case_sensitive)
+					.build(),
+			)
+		};
+	}
+
+	/// The error to show if the query is not a valid pattern.
+	pub fn get_query_error(&self) -> Option<String> {
+		if let Some(Err(err)) = &self.compiled_query {
+			// The full error points at where in the pattern things went wrong over several
+			// lines; the last line says what went wrong, which is what fits in the search bar.
+			err.to_string()
+				.lines()
+				.last()
+				.map(|line| line.trim().to_string())
+		} else {
+			None
+		}
+	}
+
+	/// Whether a process should be shown, going by its PID, name, command line and container (or
+	/// systemd unit).  Everything matches if there is no query, and nothing matches if the query
+	/// is invalid.
+	pub fn is_match(&self, pid: u32, name: &str, command: &str, container: &str) -> bool {
+		if !self.is_enabled {
+			return true;
+		}
+
+		match &self.compiled_query {
+			None => true,
+			Some(Ok(regex)) => {
+				regex.is_match(name)
+					|| regex.is_match(&pid.to_string())
+					|| regex.is_match(command)
+					|| regex.is_match(container)
+			}
+			Some(Err(_)) => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_search(query: &str) -> ProcessSearchState {
+		let mut search = ProcessSearchState::default();
+		search.open();
+		for c in query.chars() {
+			search.push_char(c);
+		}
+		search
+	}
+
+	fn is_name_match(search: &ProcessSearchState, name: &str) -> bool {
+		search.is_match(1, name, "", "")
+	}
+
+	#[test]
+	fn test_substring_search() {
+		let search = get_search("bas");
+		assert!(is_name_match(&search, "bash"));
+		assert!(is_name_match(&search, "zbase"));
+		assert!(!is_name_match(&search, "zsh"));
+
+		// The PID, command and container are searched too.
+		let search = get_search("123");
+		assert!(search.is_match(41234, "bash", "", ""));
+		assert!(!search.is_match(4321, "bash", "", ""));
+		let search = get_search("login");
+		assert!(search.is_match(1, "bash", "/bin/bash --login", ""));
+		let search = get_search("sshd.service");
+		assert!(search.is_match(1, "sshd", "", "sshd.service"));
+
+		// Characters special to regexes are just characters.
+		let search = get_search("a.b");
+		assert!(is_name_match(&search, "a.b"));
+		assert!(!is_name_match(&search, "axb"));
+		assert!(search.get_query_error().is_none());
+
+		// An empty or closed search shows everything.
+		assert!(is_name_match(&get_search(""), "bash"));
+		let mut search = get_search("zsh");
+		search.close();
+		assert!(is_name_match(&search, "bash"));
+	}
+
+	#[test]
+	fn test_case_sensitive_search() {
+		let mut search = get_search("Bash");
+		assert!(is_name_match(&search, "bash"));
+		assert!(is_name_match(&search, "BASH"));
+
+		search.toggle_case_sensitive();
+		assert!(is_name_match(&search, "Bash"));
+		assert!(!is_name_match(&search, "bash"));
+	}
+
+	#[test]
+	fn test_whole_word_search() {
+		let mut search = get_search("bash");
+		search.toggle_whole_word();
+		assert!(is_name_match(&search, "bash"));
+		assert!(search.is_match(1, "sudo", "sudo bash -c ls", ""));
+		assert!(!is_name_match(&search, "bashtop"));
+		assert!(!is_name_match(&search, "rbash"));
+
+		// Applies to each alternative of a regex.
+		search.clear_query();
+		search.toggle_regex();
+		for c in "zsh|bash".chars() {
+			search.push_char(c);
+		}
+		assert!(is_name_match(&search, "zsh"));
+		assert!(!is_name_match(&search, "zshell"));
+	}
+
+	#[test]
+	fn test_regex_search() {
+		let mut search = get_search("^b.*h$");
+		assert!(!is_name_match(&search, "bash"));
+
+		search.toggle_regex();
+		assert!(is_name_match(&search, "bash"));
+		assert!(is_name_match(&search, "BATCH"));
+		assert!(!is_name_match(&search, "bashrc"));
+	}
+
+	#[test]
+	fn test_invalid_regex_search() {
+		let mut search = get_search("(bash");
+		assert!(search.get_query_error().is_none());
+		assert!(is_name_match(&search, "(bash)"));
+
+		// An invalid pattern matches nothing, rather than everything, and says what is wrong.
+		search.toggle_regex();
+		let error = search.get_query_error().unwrap();
+		assert!(!error.is_empty());
+		assert!(!error.contains('\n'));
+		assert!(!is_name_match(&search, "(bash)"));
+		assert!(!is_name_match(&search, "bash"));
+
+		// Fixing it clears the error.
+		search.push_char(')');
+		assert!(search.get_query_error().is_none());
+		assert!(is_name_match(&search, "bash"));
+	}
+}
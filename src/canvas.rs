@@ -1,56 +1,40 @@
 use crate::{
 	app, constants,
-	data_conversion::{ConvertedCpuData, ConvertedProcessData},
-	utils::{error, gen_util::*},
+	data_conversion::{
+		convert_process_details, ConvertedCpuData, ConvertedProcessData, ProcessDetailLine,
+	},
+	utils::error,
 };
 use std::cmp::max;
 use tui::{
 	backend,
 	layout::{Alignment, Constraint, Direction, Layout, Rect},
-	style::{Color, Modifier, Style},
+	style::{Modifier, Style},
 	terminal::Frame,
-	widgets::{Axis, Block, Borders, Chart, Dataset, Marker, Paragraph, Row, Table, Text, Widget},
+	widgets::{
+		Axis, Block, Borders, Chart, Dataset, Marker, Paragraph, Row, Sparkline, Table, Text,
+		Widget,
+	},
 	Terminal,
 };
 
-const TEXT_COLOUR: Color = Color::Gray;
-const GRAPH_COLOUR: Color = Color::Gray;
-const BORDER_STYLE_COLOUR: Color = Color::Gray;
-const HIGHLIGHTED_BORDER_STYLE_COLOUR: Color = Color::LightBlue;
-const GOLDEN_RATIO: f32 = 0.618_034; // Approx, good enough for use (also Clippy gets mad if it's too long)
+mod canvas_colours;
+pub use canvas_colours::*;
 
 // Headers
 const CPU_LEGEND_HEADER: [&str; 2] = ["CPU", "Use%"];
 const DISK_HEADERS: [&str; 7] = ["Disk", "Mount", "Used", "Free", "Total", "R/s", "W/s"];
 const TEMP_HEADERS: [&str; 2] = ["Sensor", "Temp"];
+const MEM_HEADERS: [&str; 4] = ["Mem", "Used", "Total", "Use%"];
 const NON_WINDOWS_NETWORK_HEADERS: [&str; 4] = ["RX", "TX", "Total RX", "Total TX"];
 const WINDOWS_NETWORK_HEADERS: [&str; 2] = ["RX", "TX"];
 const FORCE_MIN_THRESHOLD: usize = 5;
+/// The process history is only shown if the process widget is at least this tall.
+const MIN_HEIGHT_FOR_PROCESS_HISTORY: u16 = 20;
+const PROCESS_HISTORY_HEIGHT: u16 = 7;
+const BASIC_CPU_CELL_WIDTH: u16 = 13; // Enough for "CPU10  100%" plus some spacing
 
 lazy_static! {
-	static ref HELP_TEXT: [Text<'static>; 15] = [
-		Text::raw("\nGeneral Keybindings\n"),
-		Text::raw("q, Ctrl-c to quit.\n"),
-		Text::raw("Ctrl-r to reset all data.\n"),
-		Text::raw("f to toggle freezing and unfreezing the display.\n"),
-		Text::raw(
-			"Ctrl+Up/k, Ctrl+Down/j, Ctrl+Left/h, Ctrl+Right/l to navigate between panels.\n"
-		),
-		Text::raw("Up and Down scrolls through a list.\n"),
-		Text::raw("Esc to close a dialog window (help or dd confirmation).\n"),
-		Text::raw("? to get this help screen.\n"),
-		Text::raw("\n Process Panel Keybindings\n"),
-		Text::raw("dd to kill the selected process.\n"),
-		Text::raw("c to sort by CPU usage.\n"),
-		Text::raw("m to sort by memory usage.\n"),
-		Text::raw("p to sort by PID.\n"),
-		Text::raw("n to sort by process name.\n"),
-		Text::raw("`Tab` to group together processes with the same name.\n")
-	];
-	static ref COLOUR_LIST: Vec<Color> = gen_n_colours(constants::NUM_COLOURS);
-	static ref CANVAS_BORDER_STYLE: Style = Style::default().fg(BORDER_STYLE_COLOUR);
-	static ref CANVAS_HIGHLIGHTED_BORDER_STYLE: Style =
-		Style::default().fg(HIGHLIGHTED_BORDER_STYLE_COLOUR);
 	static ref DISK_HEADERS_LENS: Vec<usize> = DISK_HEADERS
 		.iter()
 		.map(|entry| max(FORCE_MIN_THRESHOLD, entry.len()))
@@ -63,6 +47,10 @@ lazy_static! {
 		.iter()
 		.map(|entry| max(FORCE_MIN_THRESHOLD, entry.len()))
 		.collect::<Vec<_>>();
+	static ref MEM_HEADERS_LENS: Vec<usize> = MEM_HEADERS
+		.iter()
+		.map(|entry| max(FORCE_MIN_THRESHOLD, entry.len()))
+		.collect::<Vec<_>>();
 	static ref NON_WINDOWS_NETWORK_HEADERS_LENS: Vec<usize> = NON_WINDOWS_NETWORK_HEADERS
 		.iter()
 		.map(|entry| max(FORCE_MIN_THRESHOLD, entry.len()))
@@ -85,58 +73,14 @@ pub struct CanvasData {
 	pub temp_sensor_data: Vec<Vec<String>>,
 	pub process_data: Vec<ConvertedProcessData>,
 	pub grouped_process_data: Vec<ConvertedProcessData>,
+	pub tree_process_data: Vec<ConvertedProcessData>,
+	pub thread_process_data: Vec<ConvertedProcessData>,
 	pub memory_labels: Vec<(u64, u64)>,
 	pub mem_data: Vec<(f64, f64)>,
 	pub swap_data: Vec<(f64, f64)>,
 	pub cpu_data: Vec<ConvertedCpuData>,
 }
 
-/// Generates random colours.
-/// Strategy found from https://martin.ankerl.com/2009/12/09/how-to-create-random-colors-programmatically/
-fn gen_n_colours(num_to_gen: i32) -> Vec<Color> {
-	fn gen_hsv(h: f32) -> f32 {
-		let new_val = h + GOLDEN_RATIO;
-		if new_val > 1.0 {
-			new_val.fract()
-		} else {
-			new_val
-		}
-	}
-	/// This takes in an h, s, and v value of range [0, 1]
-	/// For explanation of what this does, see
-	/// https://en.wikipedia.org/wiki/HSL_and_HSV#HSV_to_RGB_alternative
-	fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
-		fn hsv_helper(num: u32, hu: f32, sat: f32, val: f32) -> f32 {
-			let k = (num as f32 + hu * 6.0) % 6.0;
-			val - val * sat * float_max(float_min(k, float_min(4.1 - k, 1.1)), 0.0)
-		}
-
-		(
-			(hsv_helper(5, hue, saturation, value) * 255.0) as u8,
-			(hsv_helper(3, hue, saturation, value) * 255.0) as u8,
-			(hsv_helper(1, hue, saturation, value) * 255.0) as u8,
-		)
-	}
-
-	// Generate colours
-	let mut colour_vec: Vec<Color> = vec![
-		Color::LightCyan,
-		Color::LightYellow,
-		Color::Red,
-		Color::Green,
-		Color::LightMagenta,
-	];
-
-	let mut h: f32 = 0.4; // We don't need random colours... right?
-	for _i in 0..num_to_gen {
-		h = gen_hsv(h);
-		let result = hsv_to_rgb(h, 0.5, 0.95);
-		colour_vec.push(Color::Rgb(result.0, result.1, result.2));
-	}
-
-	colour_vec
-}
-
 pub fn draw_data<B: backend::Backend>(
 	terminal: &mut Terminal<B>, app_state: &mut app::App,
 ) -> error::Result<()> {
@@ -149,9 +93,9 @@ pub fn draw_data<B: backend::Backend>(
 				.margin(1)
 				.constraints(
 					[
-						Constraint::Percentage(32),
-						Constraint::Percentage(40),
-						Constraint::Percentage(28),
+						Constraint::Percentage(10),
+						Constraint::Percentage(80),
+						Constraint::Percentage(10),
 					]
 					.as_ref(),
 				)
@@ -162,33 +106,43 @@ pub fn draw_data<B: backend::Backend>(
 				.margin(0)
 				.constraints(
 					[
-						Constraint::Percentage(30),
-						Constraint::Percentage(40),
-						Constraint::Percentage(30),
+						Constraint::Percentage(20),
+						Constraint::Percentage(60),
+						Constraint::Percentage(20),
 					]
 					.as_ref(),
 				)
 				.split(vertical_dialog_chunk[1]);
 
-			Paragraph::new(HELP_TEXT.iter())
+			// Generated from the keymap each time, so it always matches the current bindings.
+			let help_text = app_state
+				.keymap
+				.get_help_text(app_state.read_only)
+				.into_iter()
+				.map(Text::raw)
+				.collect::<Vec<_>>();
+
+			Paragraph::new(help_text.iter())
 				.block(
 					Block::default()
 						.title("Help (Press Esc to close)")
 						.borders(Borders::ALL),
 				)
-				.style(Style::default().fg(Color::Gray))
+				.style(app_state.canvas_colours.text_style)
 				.alignment(Alignment::Left)
 				.wrap(true)
 				.render(&mut f, middle_dialog_chunk[1]);
-		} else if app_state.show_dd {
+		} else if app_state.show_dd || app_state.show_renice || app_state.show_affinity {
+			draw_process_dialog(&mut f, app_state);
+		} else if let Some(process_details) = &app_state.process_details {
 			let vertical_dialog_chunk = Layout::default()
 				.direction(Direction::Vertical)
 				.margin(1)
 				.constraints(
 					[
-						Constraint::Percentage(40),
-						Constraint::Percentage(20),
-						Constraint::Percentage(40),
+						Constraint::Percentage(10),
+						Constraint::Percentage(80),
+						Constraint::Percentage(10),
 					]
 					.as_ref(),
 				)
@@ -199,166 +153,454 @@ pub fn draw_data<B: backend::Backend>(
 				.margin(0)
 				.constraints(
 					[
-						Constraint::Percentage(30),
-						Constraint::Percentage(40),
-						Constraint::Percentage(30),
+						Constraint::Percentage(10),
+						Constraint::Percentage(80),
+						Constraint::Percentage(10),
 					]
 					.as_ref(),
 				)
 				.split(vertical_dialog_chunk[1]);
+			let draw_loc = middle_dialog_chunk[1];
+
+			let title = match &process_details.stat {
+				Ok(stat) => format!("Process {} ({}) (Press Esc to close)", stat.pid, stat.comm),
+				Err(_) => format!(
+					"Process {} (no longer running) (Press Esc to close)",
+					process_details.pid
+				),
+			};
+
+			let detail_lines = convert_process_details(process_details);
+
+			// Long lines wrap, so count how many rows each takes up to know how far down
+			// scrolling can go.
+			let inner_width = std::cmp::max(draw_loc.width.saturating_sub(2), 1) as usize;
+			let num_rows: usize = detail_lines
+				.iter()
+				.map(|line| {
+					let line_width = match line {
+						ProcessDetailLine::Heading(text)
+						| ProcessDetailLine::Value(text)
+						| ProcessDetailLine::Unavailable(text) => text.chars().count(),
+					};
+					std::cmp::max((line_width + inner_width - 1) / inner_width, 1)
+				})
+				.sum();
+			let max_scroll = num_rows.saturating_sub(draw_loc.height.saturating_sub(2) as usize);
+			app_state.process_details_scroll =
+				std::cmp::min(app_state.process_details_scroll as usize, max_scroll) as u16;
 
-			if let Some(dd_err) = app_state.dd_err.clone() {
-				let dd_text = [Text::raw(format!(
-					"\nFailure to properly kill the process - {}",
-					dd_err
-				))];
-
-				Paragraph::new(dd_text.iter())
-					.block(
-						Block::default()
-							.title("Kill Process Error (Press Esc to close)")
-							.borders(Borders::ALL),
-					)
-					.style(Style::default().fg(Color::Gray))
-					.alignment(Alignment::Center)
-					.wrap(true)
-					.render(&mut f, middle_dialog_chunk[1]);
-			} else if let Some(process_list) = app_state.get_current_highlighted_process_list() {
-				if let Some(process) = process_list.first() {
-					let dd_text = [
-					if app_state.is_grouped() {
-						Text::raw(format!(
-							"\nAre you sure you want to kill {} process(es) with name {}?",
-							process_list.len(), process.name
+			let details_text = detail_lines
+				.into_iter()
+				.map(|line| match line {
+					ProcessDetailLine::Heading(text) => Text::styled(
+						format!("{}\n", text),
+						app_state.canvas_colours.table_header_style,
+					),
+					ProcessDetailLine::Value(text) => {
+						Text::styled(format!("{}\n", text), app_state.canvas_colours.text_style)
+					}
+					ProcessDetailLine::Unavailable(text) => Text::styled(
+						format!("{}\n", text),
+						app_state.canvas_colours.invalid_query_style,
+					),
+				})
+				.collect::<Vec<_>>();
+
+			Paragraph::new(details_text.iter())
+				.block(Block::default().title(&title).borders(Borders::ALL))
+				.style(app_state.canvas_colours.text_style)
+				.alignment(Alignment::Left)
+				.wrap(true)
+				.scroll(app_state.process_details_scroll)
+				.render(&mut f, draw_loc);
+		} else {
+			// Work out where every widget in the layout goes first, then draw them all.
+			let mut widget_draw_locs: Vec<(app::ApplicationPosition, u64, Rect)> = Vec::new();
+			let total_row_ratio = app_state.layout.total_row_ratio;
+			let row_constraints = app_state
+				.layout
+				.rows
+				.iter()
+				.map(|row| {
+					// In basic mode, rows of only CPU widgets are given as much room as they need.
+					if app_state.use_basic_mode
+						&& row.children.iter().all(|col| {
+							col.children.iter().all(|widget| {
+								matches!(widget.widget_type, app::ApplicationPosition::Cpu)
+							})
+						}) {
+						Constraint::Length(get_basic_cpu_height(
+							app_state.canvas_data.cpu_data.len(),
+							f.size().width.saturating_sub(2) / max(1, row.children.len() as u16),
+							f.size().height.saturating_sub(2),
 						))
 					} else {
-						Text::raw(format!(
-							"\nAre you sure you want to kill process {} with PID {}?",
-							process.name, process.pid
-						))
-					},
-					Text::raw("\n\nPress ENTER to proceed, ESC to exit."),
-					Text::raw("\nNote that if bottom is frozen, it must be unfrozen for changes to be shown."),
-				];
-
-					Paragraph::new(dd_text.iter())
-						.block(
-							Block::default()
-								.title("Kill Process Confirmation (Press Esc to close)")
-								.borders(Borders::ALL),
-						)
-						.style(Style::default().fg(Color::Gray))
-						.alignment(Alignment::Center)
-						.wrap(true)
-						.render(&mut f, middle_dialog_chunk[1]);
-				} else {
-					app_state.show_dd = false;
-				}
-			} else {
-				// This is a bit nasty, but it works well... I guess.
-				app_state.show_dd = false;
-			}
-		} else {
-			let vertical_chunks = Layout::default()
+						Constraint::Ratio(row.ratio, total_row_ratio)
+					}
+				})
+				.collect::<Vec<_>>();
+			let row_draw_locs = Layout::default()
 				.direction(Direction::Vertical)
 				.margin(1)
-				.constraints(
-					[
-						Constraint::Percentage(33),
-						Constraint::Percentage(34),
-						Constraint::Percentage(34),
-					]
-					.as_ref(),
-				)
+				.constraints(row_constraints)
 				.split(f.size());
 
-			let middle_chunks = Layout::default()
-				.direction(Direction::Horizontal)
-				.margin(0)
-				.constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
-				.split(vertical_chunks[1]);
+			for (row, row_draw_loc) in app_state.layout.rows.iter().zip(row_draw_locs) {
+				let col_draw_locs = Layout::default()
+					.direction(Direction::Horizontal)
+					.margin(0)
+					.constraints(
+						row.children
+							.iter()
+							.map(|col| Constraint::Ratio(col.ratio, row.total_col_ratio))
+							.collect::<Vec<_>>(),
+					)
+					.split(row_draw_loc);
+
+				for (col, col_draw_loc) in row.children.iter().zip(col_draw_locs) {
+					let widget_draw_loc_list = Layout::default()
+						.direction(Direction::Vertical)
+						.margin(0)
+						.constraints(
+							col.children
+								.iter()
+								.map(|widget| {
+									Constraint::Ratio(widget.ratio, col.total_widget_ratio)
+								})
+								.collect::<Vec<_>>(),
+						)
+						.split(col_draw_loc);
+
+					for (widget, widget_draw_loc) in col.children.iter().zip(widget_draw_loc_list) {
+						widget_draw_locs.push((
+							widget.widget_type,
+							widget.widget_id,
+							widget_draw_loc,
+						));
+					}
+				}
+			}
 
-			let middle_divided_chunk_2 = Layout::default()
-				.direction(Direction::Vertical)
-				.margin(0)
-				.constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-				.split(middle_chunks[1]);
+			for (widget_type, widget_id, widget_draw_loc) in widget_draw_locs {
+				match widget_type {
+					app::ApplicationPosition::Cpu => {
+						draw_cpu(&mut f, app_state, widget_draw_loc, widget_id)
+					}
+					app::ApplicationPosition::Mem => {
+						if app_state.use_basic_mode {
+							draw_memory_table(&mut f, app_state, widget_draw_loc, widget_id)
+						} else {
+							draw_memory_graph(&mut f, app_state, widget_draw_loc, widget_id)
+						}
+					}
+					app::ApplicationPosition::Network => {
+						draw_network(&mut f, app_state, widget_draw_loc, widget_id)
+					}
+					app::ApplicationPosition::Temp => {
+						draw_temp_table(&mut f, app_state, widget_draw_loc, widget_id)
+					}
+					app::ApplicationPosition::Disk => {
+						draw_disk_table(&mut f, app_state, widget_draw_loc, widget_id)
+					}
+					app::ApplicationPosition::Process => {
+						draw_processes_table(&mut f, app_state, widget_draw_loc, widget_id)
+					}
+				}
+			}
+		}
+	})?;
 
-			let bottom_chunks = Layout::default()
-				.direction(Direction::Horizontal)
-				.margin(0)
-				.constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-				.split(vertical_chunks[2]);
+	Ok(())
+}
 
-			// Component specific chunks
-			let cpu_chunk = Layout::default()
-				.direction(Direction::Horizontal)
-				.margin(0)
-				.constraints(
-					if app_state.left_legend {
-						[Constraint::Percentage(15), Constraint::Percentage(85)]
-					} else {
-						[Constraint::Percentage(85), Constraint::Percentage(15)]
-					}
-					.as_ref(),
-				)
-				.split(vertical_chunks[0]);
+/// Draws the dialogs that act on the selected processes: dd, renice and CPU affinity.
+fn draw_process_dialog<B: backend::Backend>(f: &mut Frame<B>, app_state: &mut app::App) {
+	// The dialog is made bigger when it has to list several processes.
+	let (margin_percentage, dialog_percentage) =
+		match app_state.get_current_highlighted_process_list() {
+			Some(process_list) if process_list.len() > 1 => (10, 80),
+			_ => (25, 50),
+		};
 
-			let network_chunk = Layout::default()
-				.direction(Direction::Vertical)
-				.margin(0)
-				.constraints(
-					if app_state.left_legend {
-						[Constraint::Percentage(10), Constraint::Percentage(90)]
+	let vertical_dialog_chunk = Layout::default()
+		.direction(Direction::Vertical)
+		.margin(1)
+		.constraints(
+			[
+				Constraint::Percentage(margin_percentage),
+				Constraint::Percentage(dialog_percentage),
+				Constraint::Percentage(margin_percentage),
+			]
+			.as_ref(),
+		)
+		.split(f.size());
+
+	let middle_dialog_chunk = Layout::default()
+		.direction(Direction::Horizontal)
+		.margin(0)
+		.constraints(
+			[
+				Constraint::Percentage(margin_percentage),
+				Constraint::Percentage(dialog_percentage),
+				Constraint::Percentage(margin_percentage),
+			]
+			.as_ref(),
+		)
+		.split(vertical_dialog_chunk[1]);
+
+	let dialog_name = if app_state.show_dd && app_state.dd_kill_tree {
+		"Kill Process Tree"
+	} else if app_state.show_dd {
+		"Kill Process"
+	} else if app_state.show_renice {
+		"Renice Process"
+	} else {
+		"CPU Affinity"
+	};
+
+	if let Some(dd_err) = app_state.dd_err.clone() {
+		let dd_text = [Text::raw(format!("\n{}", dd_err))];
+
+		Paragraph::new(dd_text.iter())
+			.block(
+				Block::default()
+					.title(&if app_state.read_only {
+						"Read-Only Mode (Press Esc to close)".to_string()
 					} else {
-						[Constraint::Percentage(75), Constraint::Percentage(10)]
-					}
-					.as_ref(),
-				)
-				.split(bottom_chunks[0]);
+						format!("{} Error (Press Esc to close)", dialog_name)
+					})
+					.borders(Borders::ALL),
+			)
+			.style(app_state.canvas_colours.text_style)
+			.alignment(Alignment::Center)
+			.wrap(true)
+			.render(f, middle_dialog_chunk[1]);
+	} else if let Some(kill_escalation) = &app_state.kill_escalation {
+		draw_kill_escalation(
+			f,
+			app_state,
+			kill_escalation,
+			dialog_name,
+			middle_dialog_chunk[1],
+		);
+	} else if let Some(process_list) = app_state
+		.get_current_highlighted_process_list()
+		.filter(|process_list| !process_list.is_empty())
+	{
+		// Every target is listed, so it's clear what is about to be acted on.
+		let target = if let [process] = process_list.as_slice() {
+			format!("process {} with PID {}?", process.name, process.pid)
+		} else {
+			format!(
+				"these {} processes{}?\n\n{}",
+				process_list.len(),
+				if app_state.dd_kill_tree {
+					", children first"
+				} else {
+					""
+				},
+				process_list
+					.iter()
+					.map(|process| format!("{} ({})", process.name, process.pid))
+					.collect::<Vec<_>>()
+					.join(", ")
+			)
+		};
 
-			// Default chunk index based on left or right legend setting
-			let legend_index = if app_state.left_legend { 0 } else { 1 };
-			let graph_index = if app_state.left_legend { 1 } else { 0 };
+		let mut dd_text = Vec::new();
+		if app_state.show_dd {
+			dd_text.push(Text::raw(format!("\nSend a signal to {}", target)));
+			dd_text.push(Text::raw("\n\n"));
+
+			let dd_signal = app_state.get_dd_signal();
+			for signal in &app::process_killer::ALL_SIGNALS {
+				dd_text.push(Text::styled(
+					format!(" {} ", signal.name()),
+					if *signal == dd_signal {
+						app_state.canvas_colours.selected_text_style
+					} else {
+						app_state.canvas_colours.text_style
+					},
+				));
+				dd_text.push(Text::raw("\n"));
+			}
 
-			// Set up blocks and their components
-			// CPU graph
-			draw_cpu_graph(&mut f, &app_state, cpu_chunk[graph_index]);
+			if let Some(kill_grace_period) = app_state.kill_grace_period {
+				dd_text.push(Text::raw(format!(
+					"\nSIGTERM is followed by SIGKILL for anything still running after {}s.",
+					kill_grace_period.as_secs()
+				)));
+			}
+			dd_text.push(Text::raw(
+				"\nUse Up and Down to pick a signal. Press ENTER to send it, ESC to exit.",
+			));
+		} else if app_state.show_renice {
+			dd_text.push(Text::raw(format!("\nChange the niceness of {}", target)));
+			dd_text.push(Text::raw("\n\n"));
+			dd_text.push(Text::styled(
+				format!(" {} ", app_state.renice_value),
+				app_state.canvas_colours.selected_text_style,
+			));
+			dd_text.push(Text::raw("\n\n"));
+			dd_text.push(Text::raw(format!(
+				"Use Up and Down to change the niceness, from {} (most favoured by the scheduler) to {} (least favoured); only root can lower it. Press ENTER to apply it, ESC to exit.",
+				app::process_scheduler::MIN_NICE,
+				app::process_scheduler::MAX_NICE
+			)));
+		} else {
+			dd_text.push(Text::raw(format!(
+				"\nChange which CPUs may be run on by {}",
+				target
+			)));
+			dd_text.push(Text::raw("\n\n"));
+
+			for (cpu, is_allowed) in app_state.affinity_cpus.iter().enumerate() {
+				dd_text.push(Text::styled(
+					format!(" [{}] CPU{} ", if *is_allowed { "x" } else { " " }, cpu),
+					if cpu == app_state.affinity_cursor {
+						app_state.canvas_colours.selected_text_style
+					} else {
+						app_state.canvas_colours.text_style
+					},
+				));
+				dd_text.push(Text::raw(" "));
+			}
 
-			// CPU legend
-			draw_cpu_legend(&mut f, app_state, cpu_chunk[legend_index]);
+			dd_text.push(Text::raw("\n\nUse Up and Down to move between CPUs, and Space to pick or unpick one. Press ENTER to apply, ESC to exit."));
+		}
+		dd_text.push(Text::raw(
+			"\nNote that if bottom is frozen, it must be unfrozen for changes to be shown.",
+		));
 
-			//Memory usage graph
-			draw_memory_graph(&mut f, &app_state, middle_chunks[0]);
+		Paragraph::new(dd_text.iter())
+			.block(
+				Block::default()
+					.title(&format!(
+						"{} Confirmation (Press Esc to close)",
+						dialog_name
+					))
+					.borders(Borders::ALL),
+			)
+			.style(app_state.canvas_colours.text_style)
+			.alignment(Alignment::Center)
+			.wrap(true)
+			.render(f, middle_dialog_chunk[1]);
+	} else {
+		// This is a bit nasty, but it works well... I guess.
+		app_state.show_dd = false;
+		app_state.show_renice = false;
+		app_state.show_affinity = false;
+	}
+}
 
-			// Network graph
-			draw_network_graph(&mut f, &app_state, network_chunk[0]);
+/// Shows how terminating each process from the dd dialog is going, once SIGTERM has been sent.
+fn draw_kill_escalation<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &app::App, kill_escalation: &app::process_killer::KillEscalation,
+	dialog_name: &str, draw_loc: Rect,
+) {
+	use app::process_killer::EscalationState;
+
+	let seconds_left = kill_escalation.get_seconds_left(std::time::Instant::now());
+	let mut dd_text = vec![Text::raw(format!(
+		"\nSent SIGTERM; anything still running after {}s is sent SIGKILL.\n\n",
+		kill_escalation.grace_period.as_secs()
+	))];
+	for process in &kill_escalation.processes {
+		let status = match &process.state {
+			EscalationState::Terminating => format!("waiting, SIGKILL in {}s", seconds_left),
+			EscalationState::Killing => "SIGKILL sent, still running".to_string(),
+			EscalationState::Terminated => "exited after SIGTERM".to_string(),
+			EscalationState::Killed => "killed by SIGKILL".to_string(),
+			EscalationState::AlreadyExited => "had already exited".to_string(),
+			EscalationState::Cancelled => "SIGKILL cancelled".to_string(),
+			EscalationState::Failed(message) => format!("failed: {}", message),
+		};
+		dd_text.push(Text::raw(format!(
+			"{} ({}): {}\n",
+			process.name, process.identity.pid, status
+		)));
+	}
 
-			draw_network_labels(&mut f, app_state, network_chunk[1]);
+	let title = if kill_escalation.is_cancelled() {
+		let num_cancelled = kill_escalation
+			.processes
+			.iter()
+			.filter(|process| process.state == EscalationState::Cancelled)
+			.count();
+		dd_text.push(Text::raw(format!(
+			"\nCancelled; SIGKILL was not sent to {} of {} processes. Press ENTER or ESC to close.",
+			num_cancelled,
+			kill_escalation.processes.len()
+		)));
+		format!("{} Cancelled (Press Esc to close)", dialog_name)
+	} else if kill_escalation.is_done() {
+		let num_failed = kill_escalation
+			.processes
+			.iter()
+			.filter(|process| matches!(process.state, EscalationState::Failed(_)))
+			.count();
+		dd_text.push(Text::raw(format!(
+			"\nDone; {} of {} processes exited. Press ENTER or ESC to close.",
+			kill_escalation.processes.len() - num_failed,
+			kill_escalation.processes.len()
+		)));
+		format!("{} Finished (Press Esc to close)", dialog_name)
+	} else {
+		dd_text.push(Text::raw(
+			"\nPressing ESC cancels SIGKILL for anything still running.",
+		));
+		format!("{} In Progress (Press Esc to cancel)", dialog_name)
+	};
 
-			// Temperature table
-			draw_temp_table(&mut f, app_state, middle_divided_chunk_2[0]);
+	Paragraph::new(dd_text.iter())
+		.block(Block::default().title(&title).borders(Borders::ALL))
+		.style(app_state.canvas_colours.text_style)
+		.alignment(Alignment::Center)
+		.wrap(true)
+		.render(f, draw_loc);
+}
 
-			// Disk usage table
-			draw_disk_table(&mut f, app_state, middle_divided_chunk_2[1]);
+/// Draws the CPU widget; in basic mode this is just a table, otherwise a graph with a legend.
+fn draw_cpu<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect, widget_id: u64,
+) {
+	if app_state.use_basic_mode {
+		draw_basic_cpu(f, app_state, draw_loc, widget_id);
+	} else {
+		let cpu_chunk = Layout::default()
+			.direction(Direction::Horizontal)
+			.margin(0)
+			.constraints(
+				if app_state.left_legend {
+					[Constraint::Percentage(15), Constraint::Percentage(85)]
+				} else {
+					[Constraint::Percentage(85), Constraint::Percentage(15)]
+				}
+				.as_ref(),
+			)
+			.split(draw_loc);
 
-			// Processes table
-			draw_processes_table(&mut f, app_state, bottom_chunks[1]);
-		}
-	})?;
+		// Default chunk index based on left or right legend setting
+		let legend_index = if app_state.left_legend { 0 } else { 1 };
+		let graph_index = if app_state.left_legend { 1 } else { 0 };
 
-	Ok(())
+		draw_cpu_graph(f, app_state, cpu_chunk[graph_index], widget_id);
+		draw_cpu_legend(f, app_state, cpu_chunk[legend_index], widget_id);
+	}
 }
 
-fn draw_cpu_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect) {
+fn draw_cpu_graph<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect, widget_id: u64,
+) {
 	let cpu_data: &[ConvertedCpuData] = &app_state.canvas_data.cpu_data;
 
 	// CPU usage graph
 	let x_axis: Axis<String> = Axis::default()
-		.style(Style::default().fg(GRAPH_COLOUR))
+		.style(app_state.canvas_colours.graph_style)
 		.bounds([0.0, constants::TIME_STARTS_FROM as f64 * 10.0]);
 	let y_axis = Axis::default()
-		.style(Style::default().fg(GRAPH_COLOUR))
+		.style(app_state.canvas_colours.graph_style)
 		.bounds([-0.5, 100.5])
 		.labels(&["0%", "100%"]);
 
@@ -377,7 +619,9 @@ fn draw_cpu_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App, d
 		}
 
 		cpu_entries_vec.push((
-			Style::default().fg(COLOUR_LIST[(i - avg_cpu_exist_offset) % COLOUR_LIST.len()]),
+			app_state
+				.canvas_colours
+				.get_cpu_colour_style(i - avg_cpu_exist_offset),
 			cpu.cpu_data
 				.iter()
 				.map(<(f64, f64)>::from)
@@ -388,7 +632,9 @@ fn draw_cpu_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App, d
 	if app_state.show_average_cpu {
 		if let Some(avg_cpu_entry) = cpu_data.first() {
 			cpu_entries_vec.push((
-				Style::default().fg(COLOUR_LIST[(cpu_data.len() - 1) % COLOUR_LIST.len()]),
+				app_state
+					.canvas_colours
+					.get_cpu_colour_style(cpu_data.len() - 1),
 				avg_cpu_entry
 					.cpu_data
 					.iter()
@@ -416,9 +662,10 @@ fn draw_cpu_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App, d
 			Block::default()
 				.title("CPU")
 				.borders(Borders::ALL)
-				.border_style(match app_state.current_application_position {
-					app::ApplicationPosition::Cpu => *CANVAS_HIGHLIGHTED_BORDER_STYLE,
-					_ => *CANVAS_BORDER_STYLE,
+				.border_style(if app_state.current_widget_id == widget_id {
+					app_state.canvas_colours.highlighted_border_style
+				} else {
+					app_state.canvas_colours.border_style
 				}),
 		)
 		.x_axis(x_axis)
@@ -428,7 +675,7 @@ fn draw_cpu_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App, d
 }
 
 fn draw_cpu_legend<B: backend::Backend>(
-	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect,
+	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect, widget_id: u64,
 ) {
 	let cpu_data: &[ConvertedCpuData] = &(app_state.canvas_data.cpu_data);
 
@@ -436,7 +683,7 @@ fn draw_cpu_legend<B: backend::Backend>(
 	let start_position = get_start_position(
 		num_rows,
 		&(app_state.scroll_direction),
-		&mut app_state.previous_cpu_table_position,
+		app_state.previous_positions.entry(widget_id).or_default(),
 		app_state.currently_selected_cpu_table_position,
 	);
 
@@ -460,21 +707,20 @@ fn draw_cpu_legend<B: backend::Backend>(
 		.map(|(itx, cpu_string_row)| {
 			Row::StyledData(
 				cpu_string_row.iter(),
-				match app_state.current_application_position {
-					app::ApplicationPosition::Cpu => {
-						if cpu_row_counter
-							== app_state.currently_selected_cpu_table_position - start_position
-						{
-							cpu_row_counter = -1;
-							Style::default().fg(Color::Black).bg(Color::Cyan)
-						} else {
-							if cpu_row_counter >= 0 {
-								cpu_row_counter += 1;
-							}
-							Style::default().fg(COLOUR_LIST[itx % COLOUR_LIST.len()])
+				if app_state.current_widget_id == widget_id {
+					if cpu_row_counter
+						== app_state.currently_selected_cpu_table_position - start_position
+					{
+						cpu_row_counter = -1;
+						app_state.canvas_colours.selected_text_style
+					} else {
+						if cpu_row_counter >= 0 {
+							cpu_row_counter += 1;
 						}
+						app_state.canvas_colours.get_cpu_colour_style(itx)
 					}
-					_ => Style::default().fg(COLOUR_LIST[itx % COLOUR_LIST.len()]),
+				} else {
+					app_state.canvas_colours.get_cpu_colour_style(itx)
 				},
 			)
 		});
@@ -490,12 +736,13 @@ fn draw_cpu_legend<B: backend::Backend>(
 	// Draw
 	Table::new(CPU_LEGEND_HEADER.iter(), cpu_rows)
 		.block(Block::default().borders(Borders::ALL).border_style(
-			match app_state.current_application_position {
-				app::ApplicationPosition::Cpu => *CANVAS_HIGHLIGHTED_BORDER_STYLE,
-				_ => *CANVAS_BORDER_STYLE,
+			if app_state.current_widget_id == widget_id {
+				app_state.canvas_colours.highlighted_border_style
+			} else {
+				app_state.canvas_colours.border_style
 			},
 		))
-		.header_style(Style::default().fg(Color::LightBlue))
+		.header_style(app_state.canvas_colours.table_header_style)
 		.widths(
 			&(intrinsic_widths
 				.into_iter()
@@ -505,22 +752,136 @@ fn draw_cpu_legend<B: backend::Backend>(
 		.render(f, draw_loc);
 }
 
-fn _draw_memory_table<B: backend::Backend>(
-	_f: &mut Frame<B>, _app_state: &app::App, _draw_loc: Rect,
+/// Returns how tall the basic mode CPU table should be to show every core, capped to a third of
+/// the available height.
+fn get_basic_cpu_height(num_cpus: usize, available_width: u16, available_height: u16) -> u16 {
+	let num_columns = max(1, available_width.saturating_sub(2) / BASIC_CPU_CELL_WIDTH) as usize;
+	let num_rows = ((num_cpus + num_columns - 1) / num_columns) as u16;
+
+	max(3, std::cmp::min(num_rows + 2, available_height / 3))
+}
+
+fn draw_basic_cpu<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect, widget_id: u64,
 ) {
-        // TODO: Memory table to be made for basic mode
+	let cpu_data: &[ConvertedCpuData] = &(app_state.canvas_data.cpu_data);
+	let num_columns = max(1, draw_loc.width.saturating_sub(2) / BASIC_CPU_CELL_WIDTH) as usize;
+
+	// Scrolling is done by rows, so translate the currently selected CPU into a row.
+	let num_rows = i64::from(draw_loc.height) - 3;
+	let start_row = get_start_position(
+		num_rows,
+		&(app_state.scroll_direction),
+		app_state.previous_positions.entry(widget_id).or_default(),
+		app_state.currently_selected_cpu_table_position / num_columns as i64,
+	);
+
+	let mut cpu_text: Vec<Text> = Vec::new();
+	for (itx, cpu) in cpu_data
+		.iter()
+		.enumerate()
+		.skip(start_row as usize * num_columns)
+	{
+		let cpu_usage = if let Some(cpu_point) = cpu.cpu_data.last() {
+			cpu_point.usage
+		} else {
+			0_f64
+		};
+
+		cpu_text.push(Text::styled(
+			format!("{:<6}{:>4}%", cpu.cpu_name.trim(), cpu_usage.round() as u64),
+			if app_state.current_widget_id == widget_id
+				&& itx as i64 == app_state.currently_selected_cpu_table_position
+			{
+				app_state.canvas_colours.selected_text_style
+			} else {
+				app_state.canvas_colours.get_cpu_colour_style(itx)
+			},
+		));
+		cpu_text.push(Text::raw(if (itx + 1) % num_columns == 0 {
+			"\n"
+		} else {
+			"  "
+		}));
+	}
+
+	Paragraph::new(cpu_text.iter())
+		.block(
+			Block::default()
+				.title("CPU")
+				.borders(Borders::ALL)
+				.border_style(if app_state.current_widget_id == widget_id {
+					app_state.canvas_colours.highlighted_border_style
+				} else {
+					app_state.canvas_colours.border_style
+				}),
+		)
+		.render(f, draw_loc);
 }
 
-fn draw_memory_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect) {
+fn draw_memory_table<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect, widget_id: u64,
+) {
+	let memory_labels: &[(u64, u64)] = &(app_state.canvas_data.memory_labels);
+
+	let mut memory_rows: Vec<Vec<String>> = Vec::new();
+	for (label, (used_in_mb, total_in_mb)) in ["RAM", "SWP"].iter().zip(memory_labels) {
+		// Skip entries that do not exist, like swap on some systems
+		if *total_in_mb > 0 {
+			memory_rows.push(vec![
+				label.to_string(),
+				format!("{:.1}GB", *used_in_mb as f64 / 1024.0),
+				format!("{:.1}GB", *total_in_mb as f64 / 1024.0),
+				format!("{:.0}%", *used_in_mb as f64 / *total_in_mb as f64 * 100_f64),
+			]);
+		}
+	}
+	let mapped_memory = memory_rows
+		.iter()
+		.map(|row| Row::StyledData(row.iter(), app_state.canvas_colours.text_style));
+
+	// Calculate widths
+	let width = f64::from(draw_loc.width);
+	let width_ratios = [0.25, 0.25, 0.25, 0.25];
+	let variable_intrinsic_results =
+		get_variable_intrinsic_widths(width as u16, &width_ratios, &MEM_HEADERS_LENS);
+	let intrinsic_widths: Vec<u16> =
+		((variable_intrinsic_results.0)[0..variable_intrinsic_results.1]).to_vec();
+
+	// Draw
+	Table::new(MEM_HEADERS.iter(), mapped_memory)
+		.block(
+			Block::default()
+				.title("Memory")
+				.borders(Borders::ALL)
+				.border_style(if app_state.current_widget_id == widget_id {
+					app_state.canvas_colours.highlighted_border_style
+				} else {
+					app_state.canvas_colours.border_style
+				}),
+		)
+		.header_style(app_state.canvas_colours.table_header_style)
+		.widths(
+			&(intrinsic_widths
+				.into_iter()
+				.map(Constraint::Length)
+				.collect::<Vec<_>>()),
+		)
+		.render(f, draw_loc);
+}
+
+fn draw_memory_graph<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect, widget_id: u64,
+) {
 	let mem_data: &[(f64, f64)] = &(app_state.canvas_data.mem_data);
 	let swap_data: &[(f64, f64)] = &(app_state.canvas_data.swap_data);
 	let memory_labels: &[(u64, u64)] = &(app_state.canvas_data.memory_labels);
 
 	let x_axis: Axis<String> = Axis::default()
-		.style(Style::default().fg(GRAPH_COLOUR))
+		.style(app_state.canvas_colours.graph_style)
 		.bounds([0.0, constants::TIME_STARTS_FROM as f64 * 10.0]);
 	let y_axis = Axis::default()
-		.style(Style::default().fg(GRAPH_COLOUR))
+		.style(app_state.canvas_colours.graph_style)
 		.bounds([-0.5, 100.5]) // Offset as the zero value isn't drawn otherwise...
 		.labels(&["0%", "100%"]);
 
@@ -542,7 +903,7 @@ fn draw_memory_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App
 		} else {
 			Marker::Braille
 		})
-		.style(Style::default().fg(COLOUR_LIST[0]))
+		.style(app_state.canvas_colours.ram_style)
 		.data(&mem_data)];
 
 	if !(&swap_data).is_empty() {
@@ -565,7 +926,7 @@ fn draw_memory_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App
 						} else {
 							Marker::Braille
 						})
-						.style(Style::default().fg(COLOUR_LIST[1]))
+						.style(app_state.canvas_colours.swap_style)
 						.data(&swap_data),
 				);
 			}
@@ -577,9 +938,10 @@ fn draw_memory_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App
 			Block::default()
 				.title("Memory")
 				.borders(Borders::ALL)
-				.border_style(match app_state.current_application_position {
-					app::ApplicationPosition::Mem => *CANVAS_HIGHLIGHTED_BORDER_STYLE,
-					_ => *CANVAS_BORDER_STYLE,
+				.border_style(if app_state.current_widget_id == widget_id {
+					app_state.canvas_colours.highlighted_border_style
+				} else {
+					app_state.canvas_colours.border_style
 				}),
 		)
 		.x_axis(x_axis)
@@ -588,15 +950,42 @@ fn draw_memory_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App
 		.render(f, draw_loc);
 }
 
-fn draw_network_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect) {
+/// Draws the network widget; in basic mode this is just a table, otherwise a graph with labels.
+fn draw_network<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect, widget_id: u64,
+) {
+	if app_state.use_basic_mode {
+		draw_network_labels(f, app_state, draw_loc, widget_id);
+	} else {
+		let network_chunk = Layout::default()
+			.direction(Direction::Vertical)
+			.margin(0)
+			.constraints(
+				if app_state.left_legend {
+					[Constraint::Percentage(10), Constraint::Percentage(90)]
+				} else {
+					[Constraint::Percentage(75), Constraint::Percentage(10)]
+				}
+				.as_ref(),
+			)
+			.split(draw_loc);
+
+		draw_network_graph(f, app_state, network_chunk[0], widget_id);
+		draw_network_labels(f, app_state, network_chunk[1], widget_id);
+	}
+}
+
+fn draw_network_graph<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect, widget_id: u64,
+) {
 	let network_data_rx: &[(f64, f64)] = &(app_state.canvas_data.network_data_rx);
 	let network_data_tx: &[(f64, f64)] = &(app_state.canvas_data.network_data_tx);
 
 	let x_axis: Axis<String> = Axis::default()
-		.style(Style::default().fg(GRAPH_COLOUR))
+		.style(app_state.canvas_colours.graph_style)
 		.bounds([0.0, 600_000.0]);
 	let y_axis = Axis::default()
-		.style(Style::default().fg(GRAPH_COLOUR))
+		.style(app_state.canvas_colours.graph_style)
 		.bounds([-0.5, 30_f64])
 		.labels(&["0B", "1KiB", "1MiB", "1GiB"]);
 	Chart::default()
@@ -604,9 +993,10 @@ fn draw_network_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::Ap
 			Block::default()
 				.title("Network")
 				.borders(Borders::ALL)
-				.border_style(match app_state.current_application_position {
-					app::ApplicationPosition::Network => *CANVAS_HIGHLIGHTED_BORDER_STYLE,
-					_ => *CANVAS_BORDER_STYLE,
+				.border_style(if app_state.current_widget_id == widget_id {
+					app_state.canvas_colours.highlighted_border_style
+				} else {
+					app_state.canvas_colours.border_style
 				}),
 		)
 		.x_axis(x_axis)
@@ -618,7 +1008,7 @@ fn draw_network_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::Ap
 				} else {
 					Marker::Braille
 				})
-				.style(Style::default().fg(COLOUR_LIST[0]))
+				.style(app_state.canvas_colours.rx_style)
 				.data(&network_data_rx),
 			Dataset::default()
 				.marker(if app_state.use_dot {
@@ -626,14 +1016,14 @@ fn draw_network_graph<B: backend::Backend>(f: &mut Frame<B>, app_state: &app::Ap
 				} else {
 					Marker::Braille
 				})
-				.style(Style::default().fg(COLOUR_LIST[1]))
+				.style(app_state.canvas_colours.tx_style)
 				.data(&network_data_tx),
 		])
 		.render(f, draw_loc);
 }
 
 fn draw_network_labels<B: backend::Backend>(
-	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect,
+	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect, widget_id: u64,
 ) {
 	let rx_display: String = app_state.canvas_data.rx_display.clone();
 	let tx_display: String = app_state.canvas_data.tx_display.clone();
@@ -651,7 +1041,9 @@ fn draw_network_labels<B: backend::Backend>(
 	} else {
 		vec![vec![rx_display, tx_display]]
 	};
-	let mapped_network = total_network.iter().map(|val| Row::Data(val.iter()));
+	let mapped_network = total_network
+		.iter()
+		.map(|val| Row::StyledData(val.iter(), app_state.canvas_colours.text_style));
 
 	// Calculate widths
 	let width_ratios: Vec<f64>;
@@ -681,13 +1073,21 @@ fn draw_network_labels<B: backend::Backend>(
 		.iter(),
 		mapped_network,
 	)
-	.block(Block::default().borders(Borders::ALL).border_style(
-		match app_state.current_application_position {
-			app::ApplicationPosition::Network => *CANVAS_HIGHLIGHTED_BORDER_STYLE,
-			_ => *CANVAS_BORDER_STYLE,
-		},
-	))
-	.header_style(Style::default().fg(Color::LightBlue))
+	.block(
+		Block::default()
+			.title(if app_state.use_basic_mode {
+				"Network"
+			} else {
+				""
+			})
+			.borders(Borders::ALL)
+			.border_style(if app_state.current_widget_id == widget_id {
+				app_state.canvas_colours.highlighted_border_style
+			} else {
+				app_state.canvas_colours.border_style
+			}),
+	)
+	.header_style(app_state.canvas_colours.table_header_style)
 	.widths(
 		&(intrinsic_widths
 			.into_iter()
@@ -698,7 +1098,7 @@ fn draw_network_labels<B: backend::Backend>(
 }
 
 fn draw_temp_table<B: backend::Backend>(
-	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect,
+	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect, widget_id: u64,
 ) {
 	let temp_sensor_data: &[Vec<String>] = &(app_state.canvas_data.temp_sensor_data);
 
@@ -706,7 +1106,7 @@ fn draw_temp_table<B: backend::Backend>(
 	let start_position = get_start_position(
 		num_rows,
 		&(app_state.scroll_direction),
-		&mut app_state.previous_temp_position,
+		app_state.previous_positions.entry(widget_id).or_default(),
 		app_state.currently_selected_temperature_position,
 	);
 
@@ -716,21 +1116,20 @@ fn draw_temp_table<B: backend::Backend>(
 	let temperature_rows = sliced_vec.iter().map(|temp_row| {
 		Row::StyledData(
 			temp_row.iter(),
-			match app_state.current_application_position {
-				app::ApplicationPosition::Temp => {
-					if temp_row_counter
-						== app_state.currently_selected_temperature_position - start_position
-					{
-						temp_row_counter = -1;
-						Style::default().fg(Color::Black).bg(Color::Cyan)
-					} else {
-						if temp_row_counter >= 0 {
-							temp_row_counter += 1;
-						}
-						Style::default().fg(TEXT_COLOUR)
+			if app_state.current_widget_id == widget_id {
+				if temp_row_counter
+					== app_state.currently_selected_temperature_position - start_position
+				{
+					temp_row_counter = -1;
+					app_state.canvas_colours.selected_text_style
+				} else {
+					if temp_row_counter >= 0 {
+						temp_row_counter += 1;
 					}
+					app_state.canvas_colours.text_style
 				}
-				_ => Style::default().fg(TEXT_COLOUR),
+			} else {
+				app_state.canvas_colours.text_style
 			},
 		)
 	});
@@ -749,12 +1148,13 @@ fn draw_temp_table<B: backend::Backend>(
 			Block::default()
 				.title("Temperatures")
 				.borders(Borders::ALL)
-				.border_style(match app_state.current_application_position {
-					app::ApplicationPosition::Temp => *CANVAS_HIGHLIGHTED_BORDER_STYLE,
-					_ => *CANVAS_BORDER_STYLE,
+				.border_style(if app_state.current_widget_id == widget_id {
+					app_state.canvas_colours.highlighted_border_style
+				} else {
+					app_state.canvas_colours.border_style
 				}),
 		)
-		.header_style(Style::default().fg(Color::LightBlue))
+		.header_style(app_state.canvas_colours.table_header_style)
 		.widths(
 			&(intrinsic_widths
 				.into_iter()
@@ -765,14 +1165,14 @@ fn draw_temp_table<B: backend::Backend>(
 }
 
 fn draw_disk_table<B: backend::Backend>(
-	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect,
+	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect, widget_id: u64,
 ) {
 	let disk_data: &[Vec<String>] = &(app_state.canvas_data.disk_data);
 	let num_rows = i64::from(draw_loc.height) - 5;
 	let start_position = get_start_position(
 		num_rows,
 		&(app_state.scroll_direction),
-		&mut app_state.previous_disk_position,
+		app_state.previous_positions.entry(widget_id).or_default(),
 		app_state.currently_selected_disk_position,
 	);
 
@@ -782,19 +1182,18 @@ fn draw_disk_table<B: backend::Backend>(
 	let disk_rows = sliced_vec.iter().map(|disk| {
 		Row::StyledData(
 			disk.iter(),
-			match app_state.current_application_position {
-				app::ApplicationPosition::Disk => {
-					if disk_counter == app_state.currently_selected_disk_position - start_position {
-						disk_counter = -1;
-						Style::default().fg(Color::Black).bg(Color::Cyan)
-					} else {
-						if disk_counter >= 0 {
-							disk_counter += 1;
-						}
-						Style::default().fg(TEXT_COLOUR)
+			if app_state.current_widget_id == widget_id {
+				if disk_counter == app_state.currently_selected_disk_position - start_position {
+					disk_counter = -1;
+					app_state.canvas_colours.selected_text_style
+				} else {
+					if disk_counter >= 0 {
+						disk_counter += 1;
 					}
+					app_state.canvas_colours.text_style
 				}
-				_ => Style::default().fg(TEXT_COLOUR),
+			} else {
+				app_state.canvas_colours.text_style
 			},
 		)
 	});
@@ -814,14 +1213,16 @@ fn draw_disk_table<B: backend::Backend>(
 			Block::default()
 				.title("Disk")
 				.borders(Borders::ALL)
-				.border_style(match app_state.current_application_position {
-					app::ApplicationPosition::Disk => *CANVAS_HIGHLIGHTED_BORDER_STYLE,
-					_ => *CANVAS_BORDER_STYLE,
+				.border_style(if app_state.current_widget_id == widget_id {
+					app_state.canvas_colours.highlighted_border_style
+				} else {
+					app_state.canvas_colours.border_style
 				}),
 		)
 		.header_style(
-			Style::default()
-				.fg(Color::LightBlue)
+			app_state
+				.canvas_colours
+				.table_header_style
 				.modifier(Modifier::BOLD),
 		)
 		.widths(
@@ -834,12 +1235,37 @@ fn draw_disk_table<B: backend::Backend>(
 }
 
 fn draw_processes_table<B: backend::Backend>(
-	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect,
+	f: &mut Frame<B>, app_state: &mut app::App, draw_loc: Rect, widget_id: u64,
 ) {
-	let process_data: &[ConvertedProcessData] = if app_state.is_grouped() {
-		&app_state.canvas_data.grouped_process_data
+	let draw_loc = if app_state.process_search_state.is_enabled {
+		let process_chunks = Layout::default()
+			.direction(Direction::Vertical)
+			.constraints([Constraint::Min(0), Constraint::Length(4)].as_ref())
+			.split(draw_loc);
+		draw_search_bar(f, app_state, process_chunks[1], widget_id);
+		process_chunks[0]
+	} else {
+		draw_loc
+	};
+
+	// Only take room from the table for the history if there is plenty to spare.
+	let draw_loc = if app_state.is_showing_process_history()
+		&& draw_loc.height >= MIN_HEIGHT_FOR_PROCESS_HISTORY
+	{
+		let process_chunks = Layout::default()
+			.direction(Direction::Vertical)
+			.constraints(
+				[
+					Constraint::Min(0),
+					Constraint::Length(PROCESS_HISTORY_HEIGHT),
+				]
+				.as_ref(),
+			)
+			.split(draw_loc);
+		draw_process_history(f, app_state, process_chunks[1]);
+		process_chunks[0]
 	} else {
-		&app_state.canvas_data.process_data
+		draw_loc
 	};
 
 	// Admittedly this is kinda a hack... but we need to:
@@ -852,101 +1278,378 @@ fn draw_processes_table<B: backend::Backend>(
 	let start_position = get_start_position(
 		num_rows,
 		&(app_state.scroll_direction),
-		&mut app_state.previous_process_position,
+		app_state.previous_positions.entry(widget_id).or_default(),
 		app_state.currently_selected_process_position,
 	);
 
+	let process_data = app_state.get_displayed_rows();
 	let sliced_vec: Vec<ConvertedProcessData> = (&process_data[start_position as usize..]).to_vec();
 	let mut process_counter = 0;
 
+	use app::data_collection::processes::{grouping::ProcessGrouping, ProcessSorting};
+	let direction_val = if app_state.process_sorting_reverse {
+		"⯆"
+	} else {
+		"⯅"
+	};
+
+	// Groups are shown in the name column, which is named after what they are grouped by.
+	let name_header = match app_state.get_grouping() {
+		Some(grouping) if grouping != ProcessGrouping::Name && app_state.is_showing_groups() => {
+			format!("{}(n)", grouping.column_name())
+		}
+		_ if app_state.is_showing_full_command() => "Command(n)".to_string(),
+		_ => "Name(n)".to_string(),
+	};
+
+	// TODO: [OPT] Reuse calculation to save time?
+	let process_headers = [
+		(
+			if app_state.is_showing_groups() {
+				"Count"
+			} else if app_state.is_showing_threads() {
+				"TID(p)"
+			} else {
+				"PID(p)"
+			},
+			ProcessSorting::PID,
+		),
+		(
+			name_header.as_str(),
+			if app_state.is_showing_full_command() {
+				ProcessSorting::COMMAND
+			} else {
+				ProcessSorting::NAME
+			},
+		),
+		("CPU%(c)", ProcessSorting::CPU),
+		("Mem%(m)", ProcessSorting::MEM),
+		("R/s(R)", ProcessSorting::READ),
+		("W/s(W)", ProcessSorting::WRITE),
+		("User(u)", ProcessSorting::USER),
+		("State(s)", ProcessSorting::STATE),
+		("Threads(T)", ProcessSorting::THREADS),
+		("RSS(r)", ProcessSorting::RSS),
+		("VSZ(v)", ProcessSorting::VSZ),
+		("PRI/NI(N)", ProcessSorting::NICE),
+		("Start(S)", ProcessSorting::START),
+		("Container(C)", ProcessSorting::CONTAINER),
+	]
+	.iter()
+	.map(|(header, sorting_type)| {
+		if *sorting_type == app_state.process_sorting_type {
+			format!("{}{}", header, direction_val)
+		} else {
+			header.to_string()
+		}
+	})
+	.collect::<Vec<_>>();
+	let process_headers_lens: Vec<usize> = process_headers
+		.iter()
+		.map(|entry| entry.chars().count())
+		.collect::<Vec<_>>();
+
+	// Calculate widths; columns on the right are dropped first if there isn't room for them all.
+	let width = f64::from(draw_loc.width);
+	let width_ratios = [
+		0.07, 0.14, 0.06, 0.06, 0.07, 0.07, 0.07, 0.06, 0.07, 0.06, 0.06, 0.06, 0.06, 0.1,
+	];
+	let variable_intrinsic_results =
+		get_variable_intrinsic_widths(width as u16, &width_ratios, &process_headers_lens);
+	let intrinsic_widths: Vec<u16> =
+		((variable_intrinsic_results.0)[0..variable_intrinsic_results.1]).to_vec();
+
+	// Long commands are scrolled sideways, but only until the end of the longest one is shown.
+	// The tree lines stay where they are, and an ellipsis marks that the start is cut off.
+	let is_showing_full_command = app_state.is_showing_full_command();
+	if is_showing_full_command {
+		let name_width = intrinsic_widths.get(1).cloned().unwrap_or(0) as usize;
+		let max_scroll = process_data
+			.iter()
+			.map(|process| {
+				(process.tree_prefix.chars().count() + process.command.chars().count() + 1)
+					.saturating_sub(name_width)
+			})
+			.max()
+			.unwrap_or(0);
+		app_state.process_command_scroll =
+			std::cmp::min(app_state.process_command_scroll, max_scroll);
+	}
+	let command_scroll = app_state.process_command_scroll;
+
 	// Draw!
 	let process_rows = sliced_vec.iter().map(|process| {
 		let stringified_process_vec: Vec<String> = vec![
-			if app_state.is_grouped() {
-				process.group.len().to_string()
+			// Marked processes (and groups whose processes are all marked) are starred.
+			format!(
+				"{}{}",
+				if app_state.is_marked(if process.group.is_empty() {
+					std::slice::from_ref(&process.pid)
+				} else {
+					&process.group
+				}) {
+					"*"
+				} else {
+					""
+				},
+				if app_state.is_showing_groups() {
+					process.group.len().to_string()
+				} else {
+					process.pid.to_string()
+				}
+			),
+			if !is_showing_full_command {
+				format!("{}{}", process.tree_prefix, process.name)
+			} else if command_scroll > 0 {
+				format!(
+					"{}…{}",
+					process.tree_prefix,
+					process
+						.command
+						.chars()
+						.skip(command_scroll)
+						.collect::<String>()
+				)
 			} else {
-				process.pid.to_string()
+				format!("{}{}", process.tree_prefix, process.command)
 			},
-			process.name.clone(),
 			process.cpu_usage.clone(),
 			process.mem_usage.clone(),
+			process.read_rate.clone(),
+			process.write_rate.clone(),
+			process.user.clone(),
+			process.state.clone(),
+			process.threads.clone(),
+			process.rss.clone(),
+			process.vsz.clone(),
+			process.priority.clone(),
+			process.start_time.clone(),
+			process.container.clone(),
 		];
 		Row::StyledData(
 			stringified_process_vec.into_iter(),
-			match app_state.current_application_position {
-				app::ApplicationPosition::Process => {
-					if process_counter
-						== app_state.currently_selected_process_position - start_position
-					{
-						process_counter = -1;
-						Style::default().fg(Color::Black).bg(Color::Cyan)
-					} else {
-						if process_counter >= 0 {
-							process_counter += 1;
-						}
-						Style::default().fg(TEXT_COLOUR)
+			if app_state.current_widget_id == widget_id {
+				if process_counter == app_state.currently_selected_process_position - start_position
+				{
+					process_counter = -1;
+					app_state.canvas_colours.selected_text_style
+				} else {
+					if process_counter >= 0 {
+						process_counter += 1;
 					}
+					app_state.canvas_colours.text_style
 				}
-				_ => Style::default().fg(TEXT_COLOUR),
+			} else {
+				app_state.canvas_colours.text_style
 			},
 		)
 	});
 
-	use app::data_collection::processes::ProcessSorting;
-	let mut pid_or_name = if app_state.is_grouped() {
-		"Count"
-	} else {
-		"PID(p)"
+	Table::new(process_headers.iter(), process_rows)
+		.block(
+			Block::default()
+				.title(&if let Some(pid) = app_state.get_thread_view_pid() {
+					get_thread_view_title(app_state, pid)
+				} else {
+					match (
+						app_state.is_tree_mode(),
+						app_state.marked_process_pids.len(),
+					) {
+						(false, 0) => "Processes".to_string(),
+						(true, 0) => "Processes (tree)".to_string(),
+						(false, num_marked) => format!("Processes ({} marked)", num_marked),
+						(true, num_marked) => format!("Processes (tree, {} marked)", num_marked),
+					}
+				})
+				.borders(Borders::ALL)
+				.border_style(if app_state.current_widget_id == widget_id {
+					app_state.canvas_colours.highlighted_border_style
+				} else {
+					app_state.canvas_colours.border_style
+				}),
+		)
+		.header_style(app_state.canvas_colours.table_header_style)
+		.widths(
+			&(intrinsic_widths
+				.into_iter()
+				.map(|calculated_width| Constraint::Length(calculated_width as u16))
+				.collect::<Vec<_>>()),
+		)
+		.render(f, draw_loc);
+}
+
+/// Names the process whose threads are shown, or why they can't be.
+fn get_thread_view_title(app_state: &app::App, pid: u32) -> String {
+	match app_state
+		.data
+		.list_of_processes
+		.iter()
+		.find(|process| process.pid == pid)
+	{
+		None => format!("Threads of PID {} (no longer running)", pid),
+		Some(process) => match &app_state.thread_err {
+			Some(thread_err) => format!("Threads of {} ({}): {}", process.name, pid, thread_err),
+			None => format!("Threads of {} ({}, Esc to go back)", process.name, pid),
+		},
 	}
-	.to_string();
-	let mut name = "Name(n)".to_string();
-	let mut cpu = "CPU%(c)".to_string();
-	let mut mem = "Mem%(m)".to_string();
+}
 
-	let direction_val = if app_state.process_sorting_reverse {
-		"⯆".to_string()
-	} else {
-		"⯅".to_string()
+/// Draws the recent CPU and memory usage of the selected process (or group, or collapsed
+/// subtree) as two sparklines, with the newest usage on the right.
+fn draw_process_history<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect,
+) {
+	use app::data_collection::processes::history::combine_process_history;
+
+	// Threads have no history of their own, so that of their process is shown instead.
+	let selected_process = match app_state.get_thread_view_pid() {
+		Some(pid) => app_state
+			.canvas_data
+			.process_data
+			.iter()
+			.find(|process| process.pid == pid),
+		None => app_state.get_selected_row(),
 	};
-
-	match app_state.process_sorting_type {
-		ProcessSorting::CPU => cpu += &direction_val,
-		ProcessSorting::MEM => mem += &direction_val,
-		ProcessSorting::PID => pid_or_name += &direction_val,
-		ProcessSorting::NAME => name += &direction_val,
+	let (title, points) = match selected_process {
+		Some(process) => {
+			let pids = if process.group.is_empty() {
+				vec![process.pid]
+			} else {
+				process.group.clone()
+			};
+			let title = if pids.len() > 1 {
+				format!("{} ({} processes)", process.name, pids.len())
+			} else {
+				format!("{} ({})", process.name, pids[0])
+			};
+			(
+				title,
+				combine_process_history(&app_state.data.process_history, &pids),
+			)
+		}
+		None => (String::new(), Vec::new()),
 	};
 
-	// TODO: [OPT] Reuse calculation to save time?
-	let process_headers = [pid_or_name, name, cpu, mem];
-	let process_headers_lens: Vec<usize> = process_headers
+	let history_chunks = Layout::default()
+		.direction(Direction::Horizontal)
+		.constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+		.split(draw_loc);
+
+	let cpu_usage = points
+		.iter()
+		.map(|point| point.cpu_usage_percent)
+		.collect::<Vec<_>>();
+	let mem_usage = points
 		.iter()
-		.map(|entry| entry.len())
+		.map(|point| point.mem_usage_percent)
 		.collect::<Vec<_>>();
+	draw_history_sparkline(
+		f,
+		app_state,
+		history_chunks[0],
+		&format!("CPU of {}", title),
+		&cpu_usage,
+		app_state
+			.canvas_colours
+			.cpu_colour_styles
+			.first()
+			.cloned()
+			.unwrap_or(app_state.canvas_colours.graph_style),
+	);
+	draw_history_sparkline(
+		f,
+		app_state,
+		history_chunks[1],
+		&format!("Mem of {}", title),
+		&mem_usage,
+		app_state.canvas_colours.ram_style,
+	);
+}
 
-	// Calculate widths
-	let width = f64::from(draw_loc.width);
-	let width_ratios = [0.2, 0.4, 0.2, 0.2];
-	let variable_intrinsic_results =
-		get_variable_intrinsic_widths(width as u16, &width_ratios, &process_headers_lens);
-	let intrinsic_widths: Vec<u16> =
-		((variable_intrinsic_results.0)[0..variable_intrinsic_results.1]).to_vec();
+fn draw_history_sparkline<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect, title: &str, usage: &[f64],
+	style: Style,
+) {
+	// Only as many points as there are columns are shown, in tenths of a percent so that small
+	// usage still shows up.
+	let num_columns = draw_loc.width.saturating_sub(2) as usize;
+	let usage = &usage[usage.len().saturating_sub(num_columns)..];
+	let data = usage
+		.iter()
+		.map(|usage| (usage * 10_f64).round() as u64)
+		.collect::<Vec<_>>();
+	let peak = usage.iter().cloned().fold(0_f64, f64::max);
 
-	Table::new(process_headers.iter(), process_rows)
+	let block_title = match usage.last() {
+		Some(current) => format!("{}: {:.1}% (peak {:.1}%)", title, current, peak),
+		None => format!("{} (no history yet)", title),
+	};
+
+	Sparkline::default()
 		.block(
 			Block::default()
-				.title("Processes")
+				.title(&block_title)
 				.borders(Borders::ALL)
-				.border_style(match app_state.current_application_position {
-					app::ApplicationPosition::Process => *CANVAS_HIGHLIGHTED_BORDER_STYLE,
-					_ => *CANVAS_BORDER_STYLE,
-				}),
+				.border_style(app_state.canvas_colours.border_style),
 		)
-		.header_style(Style::default().fg(Color::LightBlue))
-		.widths(
-			&(intrinsic_widths
-				.into_iter()
-				.map(|calculated_width| Constraint::Length(calculated_width as u16))
-				.collect::<Vec<_>>()),
+		.data(&data)
+		// Scaled to at least 100%, so that low usage looks low.
+		.max(max(1000, data.iter().cloned().max().unwrap_or(0)))
+		.style(style)
+		.render(f, draw_loc);
+}
+
+fn draw_search_bar<B: backend::Backend>(
+	f: &mut Frame<B>, app_state: &app::App, draw_loc: Rect, widget_id: u64,
+) {
+	use app::keymap::{key_sequence_to_string, Action};
+
+	let search_state = &app_state.process_search_state;
+	let get_key = |action: Action| {
+		app_state
+			.keymap
+			.get_bindings(action)
+			.first()
+			.map(|key_sequence| format!(" ({})", key_sequence_to_string(key_sequence)))
+			.unwrap_or_default()
+	};
+	let get_check_box = |is_checked: bool| if is_checked { "[x]" } else { "[ ]" };
+
+	let search_text = [
+		Text::styled(
+			format!("> {}\n", search_state.current_query),
+			app_state.canvas_colours.text_style,
+		),
+		if let Some(query_error) = search_state.get_query_error() {
+			Text::styled(query_error, app_state.canvas_colours.invalid_query_style)
+		} else {
+			Text::styled(
+				format!(
+					"{} Regex{}  {} Case-sensitive{}  {} Whole word{}",
+					get_check_box(search_state.is_regex),
+					get_key(Action::ToggleSearchRegex),
+					get_check_box(search_state.is_case_sensitive),
+					get_key(Action::ToggleSearchCaseSensitive),
+					get_check_box(search_state.is_whole_word),
+					get_key(Action::ToggleSearchWholeWord),
+				),
+				app_state.canvas_colours.text_style,
+			)
+		},
+	];
+
+	Paragraph::new(search_text.iter())
+		.block(
+			Block::default()
+				.title("Search")
+				.borders(Borders::ALL)
+				.border_style(
+					if search_state.is_typing && app_state.current_widget_id == widget_id {
+						app_state.canvas_colours.highlighted_border_style
+					} else {
+						app_state.canvas_colours.border_style
+					},
+				),
 		)
 		.render(f, draw_loc);
 }
@@ -965,7 +1668,7 @@ fn get_variable_intrinsic_widths(
 	let mut resulting_widths: Vec<u16> = vec![0; num_widths];
 	let mut last_index = 0;
 
-	let mut remaining_width = (total_width - (num_widths as u16 - 1)) as i32; // Required for spaces...
+	let mut remaining_width = total_width.saturating_sub(num_widths as u16 - 1) as i32; // Required for spaces...
 	let desired_widths = desired_widths_ratio
 		.iter()
 		.map(|&desired_width_ratio| (desired_width_ratio * total_width as f64) as i32)
@@ -1005,7 +1708,7 @@ fn get_variable_intrinsic_widths(
 	}
 
 	// Simple redistribution tactic - if there's any space left, split it evenly amongst all members
-	if last_index < num_widths {
+	if last_index < num_widths && last_index > 0 {
 		let for_all_widths = (remaining_width / last_index as i32) as u16;
 		let mut remainder = remaining_width % last_index as i32;
 
@@ -0,0 +1,204 @@
+//! The colours and styles used by the canvas.  These start off as one of the built-in themes,
+//! and can then have individual elements overridden by the config file.
+
+use crate::{constants, utils::gen_util::*};
+use tui::style::{Color, Modifier, Style};
+
+const GOLDEN_RATIO: f32 = 0.618_034; // Approx, good enough for use (also Clippy gets mad if it's too long)
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CanvasTheme {
+	Default,
+	Light,
+	HighContrast,
+	Monochrome,
+}
+
+impl CanvasTheme {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name.to_lowercase().as_str() {
+			"default" => Some(CanvasTheme::Default),
+			"light" => Some(CanvasTheme::Light),
+			"high-contrast" | "high_contrast" => Some(CanvasTheme::HighContrast),
+			"monochrome" => Some(CanvasTheme::Monochrome),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct CanvasColours {
+	pub text_style: Style,
+	pub graph_style: Style,
+	pub border_style: Style,
+	pub highlighted_border_style: Style,
+	pub table_header_style: Style,
+	pub selected_text_style: Style,
+	pub ram_style: Style,
+	pub swap_style: Style,
+	pub rx_style: Style,
+	pub tx_style: Style,
+	/// Used for the error shown in the process search bar when the query is not a valid pattern.
+	pub invalid_query_style: Style,
+	/// Used for each CPU entry, cycling around if there are more CPUs than colours.
+	pub cpu_colour_styles: Vec<Style>,
+}
+
+impl Default for CanvasColours {
+	fn default() -> Self {
+		CanvasColours::from_theme(CanvasTheme::Default)
+	}
+}
+
+impl CanvasColours {
+	pub fn from_theme(theme: CanvasTheme) -> Self {
+		match theme {
+			CanvasTheme::Default => CanvasColours {
+				text_style: Style::default().fg(Color::Gray),
+				graph_style: Style::default().fg(Color::Gray),
+				border_style: Style::default().fg(Color::Gray),
+				highlighted_border_style: Style::default().fg(Color::LightBlue),
+				table_header_style: Style::default().fg(Color::LightBlue),
+				selected_text_style: Style::default().fg(Color::Black).bg(Color::Cyan),
+				ram_style: Style::default().fg(Color::LightCyan),
+				swap_style: Style::default().fg(Color::LightYellow),
+				rx_style: Style::default().fg(Color::LightCyan),
+				tx_style: Style::default().fg(Color::LightYellow),
+				invalid_query_style: Style::default().fg(Color::LightRed),
+				cpu_colour_styles: gen_n_styles(
+					&[
+						Color::LightCyan,
+						Color::LightYellow,
+						Color::Red,
+						Color::Green,
+						Color::LightMagenta,
+					],
+					constants::NUM_COLOURS,
+					0.5,
+					0.95,
+				),
+			},
+			CanvasTheme::Light => CanvasColours {
+				text_style: Style::default().fg(Color::Black),
+				graph_style: Style::default().fg(Color::Black),
+				border_style: Style::default().fg(Color::DarkGray),
+				highlighted_border_style: Style::default().fg(Color::Blue),
+				table_header_style: Style::default().fg(Color::Blue),
+				selected_text_style: Style::default().fg(Color::White).bg(Color::Blue),
+				ram_style: Style::default().fg(Color::Blue),
+				swap_style: Style::default().fg(Color::Red),
+				rx_style: Style::default().fg(Color::Blue),
+				tx_style: Style::default().fg(Color::Red),
+				invalid_query_style: Style::default().fg(Color::Red),
+				cpu_colour_styles: gen_n_styles(
+					&[
+						Color::Blue,
+						Color::Red,
+						Color::Green,
+						Color::Magenta,
+						Color::Cyan,
+					],
+					constants::NUM_COLOURS,
+					0.8,
+					0.6,
+				),
+			},
+			CanvasTheme::HighContrast => CanvasColours {
+				text_style: Style::default().fg(Color::White),
+				graph_style: Style::default().fg(Color::White),
+				border_style: Style::default().fg(Color::White),
+				highlighted_border_style: Style::default()
+					.fg(Color::LightYellow)
+					.modifier(Modifier::BOLD),
+				table_header_style: Style::default()
+					.fg(Color::LightYellow)
+					.modifier(Modifier::BOLD),
+				selected_text_style: Style::default().fg(Color::Black).bg(Color::LightYellow),
+				ram_style: Style::default().fg(Color::LightCyan),
+				swap_style: Style::default().fg(Color::LightMagenta),
+				rx_style: Style::default().fg(Color::LightCyan),
+				tx_style: Style::default().fg(Color::LightMagenta),
+				invalid_query_style: Style::default()
+					.fg(Color::LightRed)
+					.modifier(Modifier::BOLD),
+				cpu_colour_styles: gen_n_styles(
+					&[
+						Color::LightCyan,
+						Color::LightYellow,
+						Color::LightRed,
+						Color::LightGreen,
+						Color::LightMagenta,
+						Color::LightBlue,
+						Color::White,
+					],
+					constants::NUM_COLOURS,
+					1.0,
+					1.0,
+				),
+			},
+			CanvasTheme::Monochrome => CanvasColours {
+				text_style: Style::default(),
+				graph_style: Style::default(),
+				border_style: Style::default(),
+				highlighted_border_style: Style::default().modifier(Modifier::BOLD),
+				table_header_style: Style::default().modifier(Modifier::BOLD),
+				selected_text_style: Style::default().modifier(Modifier::REVERSED),
+				ram_style: Style::default(),
+				swap_style: Style::default(),
+				rx_style: Style::default(),
+				tx_style: Style::default(),
+				invalid_query_style: Style::default().modifier(Modifier::BOLD),
+				cpu_colour_styles: vec![Style::default()],
+			},
+		}
+	}
+
+	pub fn get_cpu_colour_style(&self, index: usize) -> Style {
+		self.cpu_colour_styles[index % self.cpu_colour_styles.len()]
+	}
+}
+
+/// Generates random colours, after the given base colours.
+/// Strategy found from https://martin.ankerl.com/2009/12/09/how-to-create-random-colors-programmatically/
+fn gen_n_styles(
+	base_colours: &[Color], num_to_gen: i32, saturation: f32, value: f32,
+) -> Vec<Style> {
+	fn gen_hsv(h: f32) -> f32 {
+		let new_val = h + GOLDEN_RATIO;
+		if new_val > 1.0 {
+			new_val.fract()
+		} else {
+			new_val
+		}
+	}
+	/// This takes in an h, s, and v value of range [0, 1]
+	/// For explanation of what this does, see
+	/// https://en.wikipedia.org/wiki/HSL_and_HSV#HSV_to_RGB_alternative
+	fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
+		fn hsv_helper(num: u32, hu: f32, sat: f32, val: f32) -> f32 {
+			let k = (num as f32 + hu * 6.0) % 6.0;
+			val - val * sat * float_max(float_min(k, float_min(4.1 - k, 1.1)), 0.0)
+		}
+
+		(
+			(hsv_helper(5, hue, saturation, value) * 255.0) as u8,
+			(hsv_helper(3, hue, saturation, value) * 255.0) as u8,
+			(hsv_helper(1, hue, saturation, value) * 255.0) as u8,
+		)
+	}
+
+	// Generate colours
+	let mut colour_vec: Vec<Color> = base_colours.to_vec();
+
+	let mut h: f32 = 0.4; // We don't need random colours... right?
+	for _i in 0..num_to_gen {
+		h = gen_hsv(h);
+		let result = hsv_to_rgb(h, saturation, value);
+		colour_vec.push(Color::Rgb(result.0, result.1, result.2));
+	}
+
+	colour_vec
+		.into_iter()
+		.map(|colour| Style::default().fg(colour))
+		.collect()
+}
@@ -5,3 +5,7 @@ pub const TICK_RATE_IN_MILLISECONDS: u64 = 200; // We use this as it's a good va
 pub const DEFAULT_REFRESH_RATE_IN_MILLISECONDS: u128 = 1000;
 pub const MAX_KEY_TIMEOUT_IN_MILLISECONDS: u128 = 1000;
 pub const NUM_COLOURS: i32 = 256;
+pub const DEFAULT_CONFIG_FILE_PATH: &str = "bottom/bottom.toml";
+
+/// How many characters the full commands of processes are scrolled sideways by at a time.
+pub const COMMAND_SCROLL_STEP: i64 = 8;
@@ -1,9 +1,13 @@
 use crate::{
-	app::data_collection,
+	app::data_collection::{
+		self,
+		processes::{details::ProcessDetails, ProcessData},
+	},
 	constants,
 	utils::gen_util::{get_exact_byte_values, get_simple_byte_values},
 };
 use constants::*;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Default, Debug)]
 pub struct ConvertedNetworkData {
@@ -19,9 +23,26 @@ pub struct ConvertedNetworkData {
 pub struct ConvertedProcessData {
 	pub pid: u32,
 	pub name: String,
+	pub command: String,
 	pub cpu_usage: String,
 	pub mem_usage: String,
 	pub group: Vec<u32>,
+	/// The branches drawn before the name in the process tree; empty otherwise.
+	pub tree_prefix: String,
+	pub user: String,
+	pub state: String,
+	pub threads: String,
+	pub rss: String,
+	pub vsz: String,
+	/// The priority and niceness, like `20/0`.
+	pub priority: String,
+	pub start_time: String,
+	/// When the process started, in clock ticks since boot; see `ProcessData::start_ticks`.
+	pub start_ticks: Option<u64>,
+	pub read_rate: String,
+	pub write_rate: String,
+	/// The container or systemd unit the process is in, if any.
+	pub container: String,
 }
 
 #[derive(Clone, Default, Debug)]
@@ -136,66 +157,450 @@ pub fn update_disk_row(app_data: &data_collection::Data) -> Vec<Vec<String>> {
 	disk_vector
 }
 
+/// Gets the memory usage of a process as a percentage, working it out from the amount used if
+/// that is all we have.
+fn get_mem_usage_percent(process: &ProcessData, app_data: &data_collection::Data) -> f64 {
+	if let Some(mem_usage) = process.mem_usage_percent {
+		mem_usage
+	} else if let Some(mem_usage_kb) = process.mem_usage_kb {
+		if let Some(mem_data) = app_data.memory.last() {
+			(mem_usage_kb / 1000) as f64 / mem_data.mem_total_in_mb as f64 * 100_f64
+		} else {
+			0_f64
+		}
+	} else {
+		0_f64
+	}
+}
+
+fn convert_process_bytes(bytes: Option<u64>) -> String {
+	if let Some(bytes) = bytes {
+		let (value, unit) = get_exact_byte_values(bytes, false);
+		if bytes < 1024 {
+			format!("{}{}", bytes, unit)
+		} else {
+			format!("{:.1}{}", value, unit)
+		}
+	} else {
+		String::new()
+	}
+}
+
+fn convert_io_rate(bytes_per_sec: Option<f64>) -> String {
+	if let Some(bytes_per_sec) = bytes_per_sec {
+		format!(
+			"{}/s",
+			convert_process_bytes(Some(bytes_per_sec.round() as u64))
+		)
+	} else {
+		String::new()
+	}
+}
+
+/// Shows the start time like `ps` does; the time if the process started today, else the date
+/// if it started this year, else the year.
+fn convert_start_time(start_time: Option<u64>) -> String {
+	use chrono::{Datelike, TimeZone};
+
+	if let Some(start_time) =
+		start_time.and_then(|start_time| chrono::Local.timestamp_opt(start_time as i64, 0).single())
+	{
+		let now = chrono::Local::now();
+		if start_time.date_naive() == now.date_naive() {
+			start_time.format("%H:%M").to_string()
+		} else if start_time.year() == now.year() {
+			start_time.format("%b%d").to_string()
+		} else {
+			start_time.format("%Y").to_string()
+		}
+	} else {
+		String::new()
+	}
+}
+
+pub fn convert_process_data(
+	process: &ProcessData, app_data: &data_collection::Data,
+) -> ConvertedProcessData {
+	ConvertedProcessData {
+		pid: process.pid,
+		name: process.name.to_string(),
+		command: process.command.to_string(),
+		cpu_usage: format!("{:.1}%", process.cpu_usage_percent),
+		mem_usage: format!("{:.1}%", get_mem_usage_percent(process, app_data)),
+		group: if let Some(pid_vec) = &process.pid_vec {
+			pid_vec.to_vec()
+		} else {
+			vec![]
+		},
+		tree_prefix: String::new(),
+		user: process.user.clone().unwrap_or_default(),
+		state: process.state.map(String::from).unwrap_or_default(),
+		threads: process
+			.threads
+			.map(|threads| threads.to_string())
+			.unwrap_or_default(),
+		rss: convert_process_bytes(process.rss_bytes),
+		vsz: convert_process_bytes(process.vsz_bytes),
+		priority: match (process.priority, process.nice) {
+			(Some(priority), Some(nice)) => format!("{}/{}", priority, nice),
+			_ => String::new(),
+		},
+		start_time: convert_start_time(process.start_time),
+		start_ticks: process.start_ticks,
+		read_rate: convert_io_rate(process.read_bytes_per_sec),
+		write_rate: convert_io_rate(process.write_bytes_per_sec),
+		container: process.container.clone().unwrap_or_default(),
+	}
+}
+
 pub fn update_process_row(
 	app_data: &data_collection::Data,
 ) -> (Vec<ConvertedProcessData>, Vec<ConvertedProcessData>) {
 	let process_vector: Vec<ConvertedProcessData> = app_data
 		.list_of_processes
 		.iter()
-		.map(|process| ConvertedProcessData {
-			pid: process.pid,
-			name: process.name.to_string(),
-			cpu_usage: format!("{:.1}%", process.cpu_usage_percent),
-			mem_usage: format!(
-				"{:.1}%",
-				if let Some(mem_usage) = process.mem_usage_percent {
-					mem_usage
-				} else if let Some(mem_usage_kb) = process.mem_usage_kb {
-					if let Some(mem_data) = app_data.memory.last() {
-						(mem_usage_kb / 1000) as f64 / mem_data.mem_total_in_mb as f64 * 100_f64
-					} else {
-						0_f64
-					}
-				} else {
-					0_f64
-				}
-			),
-			group: vec![],
-		})
+		.map(|process| convert_process_data(process, app_data))
 		.collect::<Vec<_>>();
 
 	let mut grouped_process_vector: Vec<ConvertedProcessData> = Vec::new();
 	if let Some(grouped_list_of_processes) = &app_data.grouped_list_of_processes {
 		grouped_process_vector = grouped_list_of_processes
 			.iter()
-			.map(|process| ConvertedProcessData {
-				pid: process.pid,
-				name: process.name.to_string(),
-				cpu_usage: format!("{:.1}%", process.cpu_usage_percent),
-				mem_usage: format!(
-					"{:.1}%",
-					if let Some(mem_usage) = process.mem_usage_percent {
-						mem_usage
-					} else if let Some(mem_usage_kb) = process.mem_usage_kb {
-						if let Some(mem_data) = app_data.memory.last() {
-							(mem_usage_kb / 1000) as f64 / mem_data.mem_total_in_mb as f64 * 100_f64
-						} else {
-							0_f64
-						}
+			.map(|process| convert_process_data(process, app_data))
+			.collect::<Vec<_>>();
+	}
+
+	(process_vector, grouped_process_vector)
+}
+
+/// A line of the process detail pane.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProcessDetailLine {
+	Heading(String),
+	Value(String),
+	/// A part that could not be read, such as one that belongs to another user.
+	Unavailable(String),
+}
+
+fn convert_read_error(err: &std::io::Error) -> ProcessDetailLine {
+	ProcessDetailLine::Unavailable(match err.kind() {
+		std::io::ErrorKind::PermissionDenied => "<permission denied>".to_string(),
+		std::io::ErrorKind::NotFound => "<not available>".to_string(),
+		_ => format!("<unable to read: {}>", err),
+	})
+}
+
+/// Adds a section to the detail pane, or marks it as unavailable if it couldn't be read.
+fn push_detail_section<T>(
+	lines: &mut Vec<ProcessDetailLine>, heading: String, part: &std::io::Result<T>,
+	convert: impl FnOnce(&T) -> Vec<String>,
+) {
+	lines.push(ProcessDetailLine::Heading(heading));
+	match part {
+		Ok(part) => lines.extend(convert(part).into_iter().map(ProcessDetailLine::Value)),
+		Err(err) => lines.push(convert_read_error(err)),
+	}
+	lines.push(ProcessDetailLine::Value(String::new()));
+}
+
+pub fn convert_process_details(details: &ProcessDetails) -> Vec<ProcessDetailLine> {
+	let mut lines = Vec::new();
+
+	push_detail_section(
+		&mut lines,
+		"Command line".to_string(),
+		&details.command,
+		|command| {
+			if command.is_empty() {
+				vec!["(none, as this is a kernel thread or a zombie)".to_string()]
+			} else {
+				vec![command.clone()]
+			}
+		},
+	);
+	push_detail_section(&mut lines, "Executable".to_string(), &details.exe, |exe| {
+		vec![exe.display().to_string()]
+	});
+	push_detail_section(
+		&mut lines,
+		"Working directory".to_string(),
+		&details.cwd,
+		|cwd| vec![cwd.display().to_string()],
+	);
+	push_detail_section(
+		&mut lines,
+		"Parent chain".to_string(),
+		&details.stat,
+		|stat| {
+			vec![details
+				.parent_chain
+				.iter()
+				.chain(std::iter::once(&(stat.pid, stat.comm.clone())))
+				.map(|(pid, name)| format!("{} ({})", name, pid))
+				.collect::<Vec<_>>()
+				.join(" → ")]
+		},
+	);
+	push_detail_section(
+		&mut lines,
+		"Cgroups".to_string(),
+		&details.cgroups,
+		|cgroups| {
+			cgroups
+				.iter()
+				.map(|cgroup| {
+					if cgroup.controllers.is_empty() {
+						cgroup.path.clone()
 					} else {
-						0_f64
+						format!("{}: {}", cgroup.controllers.join(","), cgroup.path)
 					}
+				})
+				.collect()
+		},
+	);
+	push_detail_section(
+		&mut lines,
+		"Memory maps".to_string(),
+		&details.maps,
+		|maps| {
+			vec![
+				format!(
+					"{} mappings, {} in total",
+					maps.num_mappings,
+					convert_process_bytes(Some(maps.total_bytes))
+				),
+				format!(
+					"{} from {} files, {} anonymous, {} heap, {} stack",
+					convert_process_bytes(Some(maps.file_bytes)),
+					maps.num_files,
+					convert_process_bytes(Some(maps.anonymous_bytes)),
+					convert_process_bytes(Some(maps.heap_bytes)),
+					convert_process_bytes(Some(maps.stack_bytes)),
 				),
-				group: if let Some(pid_vec) = &process.pid_vec {
-					pid_vec.to_vec()
-				} else {
-					vec![]
-				},
-			})
+			]
+		},
+	);
+	push_detail_section(
+		&mut lines,
+		"Limits (soft / hard)".to_string(),
+		&details.limits,
+		|limits| {
+			limits
+				.iter()
+				.map(|limit| {
+					format!(
+						"{:<24}{:>12} / {:<12}{}",
+						limit.name, limit.soft, limit.hard, limit.units
+					)
+				})
+				.collect()
+		},
+	);
+	push_detail_section(
+		&mut lines,
+		match &details.fds {
+			Ok(fds) => format!("Open file descriptors ({})", fds.len()),
+			Err(_) => "Open file descriptors".to_string(),
+		},
+		&details.fds,
+		|fds| {
+			fds.iter()
+				.map(|fd| format!("{:>4} -> {}", fd.fd, fd.target))
+				.collect()
+		},
+	);
+	push_detail_section(
+		&mut lines,
+		match &details.environ {
+			Ok(environ) => format!("Environment ({})", environ.len()),
+			Err(_) => "Environment".to_string(),
+		},
+		&details.environ,
+		|environ| environ.clone(),
+	);
+
+	lines
+}
+
+/// Builds the rows of the process tree, where each process is listed under its parent.
+struct ProcessTreeBuilder<'a> {
+	app_data: &'a data_collection::Data,
+	collapsed_pids: &'a HashSet<u32>,
+	/// The indices of the children of each PID, in the order of the process list.
+	children: HashMap<u32, Vec<usize>>,
+	/// Whether each process is shown, which it is if it or any of its descendants match.
+	is_shown: Vec<bool>,
+	/// Whether each process has been reached from a root yet.
+	is_visited: Vec<bool>,
+	rows: Vec<ConvertedProcessData>,
+}
+
+impl<'a> ProcessTreeBuilder<'a> {
+	fn get_children(&self, pid: u32) -> &[usize] {
+		self.children.get(&pid).map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	fn is_shown(&mut self, index: usize, is_match: &impl Fn(&ProcessData) -> bool) -> bool {
+		let process = &self.app_data.list_of_processes[index];
+		self.is_visited[index] = true;
+		let mut is_shown = is_match(process);
+		for child in self.get_children(process.pid).to_vec() {
+			// Every child has to be visited so that its own result is recorded.
+			is_shown = self.is_shown(child, is_match) || is_shown;
+		}
+
+		self.is_shown[index] = is_shown;
+		is_shown
+	}
+
+	/// Gets the indices of a process and all of its descendants, parents first.
+	fn get_subtree(&self, index: usize, subtree: &mut Vec<usize>) {
+		subtree.push(index);
+		for child in self.get_children(self.app_data.list_of_processes[index].pid) {
+			self.get_subtree(*child, subtree);
+		}
+	}
+
+	/// Adds the row for a process and, unless it is collapsed, the rows of its descendants.
+	/// `ancestor_prefix` is what is drawn for the levels above this one, and `branch` is what
+	/// connects this process to its parent.
+	fn add_rows(&mut self, index: usize, ancestor_prefix: &str, branch: &str) {
+		let process = &self.app_data.list_of_processes[index];
+		let mut row = convert_process_data(process, self.app_data);
+		let is_collapsed = self.collapsed_pids.contains(&process.pid)
+			&& !self.get_children(process.pid).is_empty();
+
+		if is_collapsed {
+			// Show the whole subtree as one row, including what the search may have hidden, as
+			// that is what killing it would affect.
+			let mut subtree = Vec::new();
+			self.get_subtree(index, &mut subtree);
+
+			let (mut cpu_usage, mut mem_usage) = (0_f64, 0_f64);
+			let (mut read_rate, mut write_rate) = (None, None);
+			for subtree_index in &subtree {
+				let process = &self.app_data.list_of_processes[*subtree_index];
+				cpu_usage += process.cpu_usage_percent;
+				mem_usage += get_mem_usage_percent(process, self.app_data);
+				if let Some(read_bytes_per_sec) = process.read_bytes_per_sec {
+					*read_rate.get_or_insert(0_f64) += read_bytes_per_sec;
+				}
+				if let Some(write_bytes_per_sec) = process.write_bytes_per_sec {
+					*write_rate.get_or_insert(0_f64) += write_bytes_per_sec;
+				}
+			}
+			row.cpu_usage = format!("{:.1}%", cpu_usage);
+			row.mem_usage = format!("{:.1}%", mem_usage);
+			row.read_rate = convert_io_rate(read_rate);
+			row.write_rate = convert_io_rate(write_rate);
+			row.group = subtree
+				.iter()
+				.map(|subtree_index| self.app_data.list_of_processes[*subtree_index].pid)
+				.collect();
+			row.tree_prefix = if branch.is_empty() {
+				"+ ".to_string()
+			} else {
+				format!("{}{}+ ", ancestor_prefix, branch)
+			};
+			self.rows.push(row);
+			return;
+		}
+
+		row.tree_prefix = if branch.is_empty() {
+			String::new()
+		} else {
+			format!("{}{} ", ancestor_prefix, branch)
+		};
+		self.rows.push(row);
+
+		let child_prefix = match branch {
+			"" => String::new(),
+			"├─" => format!("{}│  ", ancestor_prefix),
+			_ => format!("{}   ", ancestor_prefix),
+		};
+		let shown_children = self
+			.get_children(process.pid)
+			.iter()
+			.filter(|child| self.is_shown[**child])
+			.cloned()
 			.collect::<Vec<_>>();
+		for (child_index, child) in shown_children.iter().enumerate() {
+			let child_branch = if child_index == shown_children.len() - 1 {
+				"└─"
+			} else {
+				"├─"
+			};
+			self.add_rows(*child, &child_prefix, child_branch);
+		}
 	}
+}
 
-	(process_vector, grouped_process_vector)
+/// Converts the processes into a tree, where each process is listed under its parent.  The
+/// process list should already be sorted, which then sorts each process's children.  Processes
+/// that don't match are hidden unless one of their descendants does.  Collapsed processes have
+/// their subtree shown as one row, with the total usage and I/O rates of the subtree, and its
+/// PIDs as the group.  Processes whose parents form a cycle, which can be seen if PIDs are
+/// reused while the list is being read, are listed with one of the cycle's processes as their
+/// root.
+pub fn convert_process_tree(
+	app_data: &data_collection::Data, collapsed_pids: &HashSet<u32>,
+	is_match: impl Fn(&ProcessData) -> bool,
+) -> Vec<ConvertedProcessData> {
+	let processes = &app_data.list_of_processes;
+	let indices: HashMap<u32, usize> = processes
+		.iter()
+		.enumerate()
+		.map(|(index, process)| (process.pid, index))
+		.collect();
+
+	let mut children: HashMap<u32, Vec<usize>> = HashMap::new();
+	let mut roots: Vec<usize> = Vec::new();
+	for (index, process) in processes.iter().enumerate() {
+		match process.ppid {
+			Some(ppid) if ppid != process.pid && indices.contains_key(&ppid) => {
+				children.entry(ppid).or_default().push(index)
+			}
+			_ => roots.push(index),
+		}
+	}
+
+	let mut tree_builder = ProcessTreeBuilder {
+		app_data,
+		collapsed_pids,
+		children,
+		is_shown: vec![false; processes.len()],
+		is_visited: vec![false; processes.len()],
+		rows: Vec::new(),
+	};
+	for root in &roots {
+		if tree_builder.is_shown(*root, &is_match) {
+			tree_builder.add_rows(*root, "", "");
+		}
+	}
+
+	// Whatever wasn't reached is in, or descends from, a cycle of parents.  Walk up from it
+	// until a process comes up again, which is in the cycle, and cut the cycle there.  Nothing
+	// on the way up was reached either, so none of them are roots, and each has a parent.
+	for index in 0..processes.len() {
+		if tree_builder.is_visited[index] {
+			continue;
+		}
+
+		let get_ppid = |index: usize| processes[index].ppid.unwrap_or_default();
+		let mut seen = HashSet::new();
+		let mut root = index;
+		while seen.insert(root) {
+			root = indices[&get_ppid(root)];
+		}
+		if let Some(siblings) = tree_builder.children.get_mut(&get_ppid(root)) {
+			siblings.retain(|sibling| *sibling != root);
+		}
+		if tree_builder.is_shown(root, &is_match) {
+			tree_builder.add_rows(root, "", "");
+		}
+	}
+
+	tree_builder.rows
 }
 
 pub fn update_cpu_data_points(
@@ -457,3 +862,128 @@ pub fn convert_network_data_points(
 		total_tx_display,
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_process(pid: u32, ppid: Option<u32>, name: &str, usage: f64) -> ProcessData {
+		ProcessData {
+			pid,
+			ppid,
+			name: name.to_string(),
+			cpu_usage_percent: usage,
+			mem_usage_percent: Some(usage),
+			read_bytes_per_sec: Some(usage * 100.0),
+			..ProcessData::default()
+		}
+	}
+
+	fn get_test_data() -> data_collection::Data {
+		data_collection::Data {
+			list_of_processes: vec![
+				get_process(1, None, "init", 1.0),
+				get_process(10, Some(1), "bash", 2.0),
+				get_process(11, Some(10), "vim", 3.0),
+				get_process(12, Some(10), "grep", 4.0),
+				get_process(20, Some(1), "sshd", 0.5),
+			],
+			..data_collection::Data::default()
+		}
+	}
+
+	fn get_tree_rows(rows: &[ConvertedProcessData]) -> Vec<(u32, &str)> {
+		rows.iter()
+			.map(|row| (row.pid, row.tree_prefix.as_str()))
+			.collect()
+	}
+
+	#[test]
+	fn test_process_tree() {
+		let app_data = get_test_data();
+		let rows = convert_process_tree(&app_data, &HashSet::new(), |_| true);
+		assert_eq!(
+			get_tree_rows(&rows),
+			vec![
+				(1, ""),
+				(10, "├─ "),
+				(11, "│  ├─ "),
+				(12, "│  └─ "),
+				(20, "└─ "),
+			]
+		);
+	}
+
+	#[test]
+	fn test_collapsed_process_tree() {
+		let app_data = get_test_data();
+		let collapsed_pids = vec![10].into_iter().collect();
+		let rows = convert_process_tree(&app_data, &collapsed_pids, |_| true);
+		assert_eq!(
+			get_tree_rows(&rows),
+			vec![(1, ""), (10, "├─+ "), (20, "└─ ")]
+		);
+
+		// The collapsed row adds up its whole subtree.
+		let collapsed_row = &rows[1];
+		assert_eq!(collapsed_row.group, vec![10, 11, 12]);
+		assert_eq!(collapsed_row.cpu_usage, "9.0%");
+		assert_eq!(collapsed_row.mem_usage, "9.0%");
+		assert_eq!(collapsed_row.read_rate, convert_io_rate(Some(900.0)));
+
+		// Including the processes that the search would have hidden.
+		let rows =
+			convert_process_tree(&app_data, &collapsed_pids, |process| process.name == "bash");
+		assert_eq!(get_tree_rows(&rows), vec![(1, ""), (10, "└─+ ")]);
+		assert_eq!(rows[1].group, vec![10, 11, 12]);
+
+		// Collapsing a process with no children changes nothing.
+		let collapsed_pids = vec![20].into_iter().collect();
+		let rows = convert_process_tree(&app_data, &collapsed_pids, |_| true);
+		assert_eq!(rows[4].tree_prefix, "└─ ");
+	}
+
+	#[test]
+	fn test_searched_process_tree() {
+		let app_data = get_test_data();
+
+		// The ancestors of a match are kept, so that it is still shown where it is in the tree.
+		let rows =
+			convert_process_tree(&app_data, &HashSet::new(), |process| process.name == "grep");
+		assert_eq!(
+			get_tree_rows(&rows),
+			vec![(1, ""), (10, "└─ "), (12, "   └─ ")]
+		);
+
+		let rows = convert_process_tree(&app_data, &HashSet::new(), |process| {
+			process.name == "nothing"
+		});
+		assert!(rows.is_empty());
+	}
+
+	#[test]
+	fn test_process_tree_with_cycle() {
+		let mut app_data = get_test_data();
+		app_data.list_of_processes.extend(vec![
+			get_process(32, Some(31), "child", 1.0),
+			get_process(30, Some(31), "first", 1.0),
+			get_process(31, Some(30), "second", 1.0),
+		]);
+
+		// The processes in the cycle, and those under them, are still all listed once.
+		let rows = convert_process_tree(&app_data, &HashSet::new(), |_| true);
+		assert_eq!(
+			get_tree_rows(&rows)[5..],
+			[(31, ""), (32, "├─ "), (30, "└─ ")]
+		);
+
+		let collapsed_pids = vec![31].into_iter().collect();
+		let rows = convert_process_tree(&app_data, &collapsed_pids, |_| true);
+		assert_eq!(rows[5].group, vec![31, 32, 30]);
+
+		let rows = convert_process_tree(&app_data, &HashSet::new(), |process| {
+			process.name == "child"
+		});
+		assert_eq!(get_tree_rows(&rows), vec![(31, ""), (32, "└─ ")]);
+	}
+}
@@ -8,10 +8,7 @@ extern crate failure;
 extern crate lazy_static;
 
 use crossterm::{
-	event::{
-		self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, KeyCode, KeyEvent,
-		KeyModifiers, MouseEvent,
-	},
+	event::{self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, MouseEvent},
 	execute,
 	terminal::LeaveAlternateScreen,
 	terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen},
@@ -34,13 +31,13 @@ mod utils {
 mod canvas;
 mod constants;
 mod data_conversion;
+mod options;
 
 use app::data_collection;
-use app::data_collection::processes::ProcessData;
+use app::keymap::Action;
 use constants::TICK_RATE_IN_MILLISECONDS;
 use data_conversion::*;
-use std::collections::BTreeMap;
-use utils::error::{self, BottomError};
+use utils::error;
 
 enum Event<I, J> {
 	KeyInput(I),
@@ -70,30 +67,22 @@ fn main() -> error::Result<()> {
 		(@arg RATE_MILLIS: -r --rate +takes_value "Sets a refresh rate in milliseconds; the minimum is 250ms, defaults to 1000ms.  Smaller values may take more resources.")
 		(@arg LEFT_LEGEND: -l --left_legend "Puts external chart legends on the left side rather than the default right side.")
 		(@arg USE_CURR_USAGE: -u --current_usage "Within Linux, sets a process' CPU usage to be based on the total current CPU usage, rather than assuming 100% usage.")
-		//(@arg CONFIG_LOCATION: -co --config +takes_value "Sets the location of the config file.  Expects a config file in the JSON format.")
+		(@arg CONFIG_LOCATION: -C --config +takes_value "Sets the location of the config file.  Expects a config file in the TOML format.")
 		(@arg BASIC_MODE: -b --basic "Sets bottom to basic mode, not showing graphs and only showing basic tables.")
 		(@arg GROUP_PROCESSES: -g --group "Groups processes with the same name together on launch.")
+		(@arg LAYOUT: --layout +takes_value "Sets the widget layout to one of the built-in presets: default, processes or minimal.")
+		(@arg THEME: --theme +takes_value "Sets the colour theme: default, light, high-contrast or monochrome.")
+		(@arg READ_ONLY: --("read-only") "Only watches processes, disabling every action that would kill, renice or otherwise change them.  Actions refused this way are not recorded in the audit log.")
+		(@arg AUDIT_LOG: --audit_log +takes_value "Records every action taken on processes in an audit log at the given location.")
+		(@arg KILL_GRACE_PERIOD: --kill_grace_period +takes_value "Sends SIGKILL to processes terminated with dd that are still running this many seconds after SIGTERM.")
 	)
 	.get_matches();
 
-	let update_rate_in_milliseconds: u128 = if matches.is_present("RATE_MILLIS") {
-		matches
-			.value_of("RATE_MILLIS")
-			.unwrap_or(&constants::DEFAULT_REFRESH_RATE_IN_MILLISECONDS.to_string())
-			.parse::<u128>()?
-	} else {
-		constants::DEFAULT_REFRESH_RATE_IN_MILLISECONDS
-	};
-
-	if update_rate_in_milliseconds < 250 {
-		return Err(BottomError::InvalidArg {
-			message: "Please set your update rate to be greater than 250 milliseconds.".to_string(),
-		});
-	} else if update_rate_in_milliseconds > u128::from(std::u64::MAX) {
-		return Err(BottomError::InvalidArg {
-			message: "Please set your update rate to be less than unsigned INT_MAX.".to_string(),
-		});
-	}
+	// Read the config file first; command line arguments override anything set there
+	let config = options::read_config(matches.value_of("CONFIG_LOCATION"))?;
+
+	let update_rate_in_milliseconds: u128 =
+		options::get_update_rate_in_milliseconds(&matches.value_of("RATE_MILLIS"), &config)?;
 
 	// Attempt to create debugging...
 	let enable_debugging = matches.is_present("DEBUG");
@@ -102,17 +91,17 @@ fn main() -> error::Result<()> {
 	}
 
 	// Set other settings
-	let temperature_type = if matches.is_present("FAHRENHEIT") {
-		data_collection::temperature::TemperatureType::Fahrenheit
-	} else if matches.is_present("KELVIN") {
-		data_collection::temperature::TemperatureType::Kelvin
-	} else {
-		data_collection::temperature::TemperatureType::Celsius
-	};
-	let show_average_cpu = matches.is_present("AVG_CPU");
-	let use_dot = matches.is_present("DOT_MARKER");
-	let left_legend = matches.is_present("LEFT_LEGEND");
-	let use_current_cpu_total = matches.is_present("USE_CURR_USAGE");
+	let temperature_type = options::get_temperature_option(&matches, &config)?;
+	let show_average_cpu = options::get_avg_cpu_option(&matches, &config);
+	let use_dot = options::get_use_dot_option(&matches, &config);
+	let left_legend = options::get_use_left_legend_option(&matches, &config);
+	let use_current_cpu_total = options::get_use_current_cpu_total_option(&matches, &config);
+	let use_basic_mode = options::get_use_basic_mode_option(&matches, &config);
+	let layout = options::get_layout(&matches, &config)?;
+	let canvas_colours = options::get_canvas_colours(&matches, &config)?;
+	let keymap = options::get_keymap(&config)?;
+	let kill_grace_period =
+		options::get_kill_grace_period(&matches.value_of("KILL_GRACE_PERIOD"), &config)?;
 
 	// Create "app" struct, which will control most of the program and store settings/state
 	let mut app = app::App::new(
@@ -122,10 +111,19 @@ fn main() -> error::Result<()> {
 		use_dot,
 		left_legend,
 		use_current_cpu_total,
+		use_basic_mode,
 	);
 
+	app.set_layout(layout);
+	app.canvas_colours = canvas_colours;
+	app.keymap = keymap;
+	app.kill_grace_period = kill_grace_period;
+	app.read_only = options::get_read_only_option(&matches, &config);
+	app.audit_log =
+		options::get_audit_log_path(&matches, &config).map(app::audit_log::AuditLog::new);
+
 	// Enable grouping immediately if set.
-	if matches.is_present("GROUP_PROCESSES") {
+	if options::get_group_processes_option(&matches, &config) {
 		app.toggle_grouping();
 	}
 
@@ -210,78 +208,51 @@ fn main() -> error::Result<()> {
 		if let Ok(recv) = rx.recv_timeout(Duration::from_millis(TICK_RATE_IN_MILLISECONDS)) {
 			match recv {
 				Event::KeyInput(event) => {
-					if event.modifiers.is_empty() {
-						// If only a code, and no modifiers, don't bother...
-						match event.code {
-							KeyCode::Char('q') => break,
-							KeyCode::Char('G') | KeyCode::End => app.skip_to_last(),
-							KeyCode::Home => app.skip_to_first(),
-							KeyCode::Char('h') => app.on_left(),
-							KeyCode::Char('l') => app.on_right(),
-							KeyCode::Char('k') => app.on_up(),
-							KeyCode::Char('j') => app.on_down(),
-							KeyCode::Up => app.decrement_position_count(),
-							KeyCode::Down => app.increment_position_count(),
-							KeyCode::Char(uncaught_char) => app.on_char_key(uncaught_char),
-							KeyCode::Esc => app.reset(),
-							KeyCode::Enter => app.on_enter(),
-							KeyCode::Tab => app.on_tab(),
-							_ => {}
-						}
-					} else {
-						// Otherwise, track the modifier as well...
-						match event {
-							KeyEvent {
-								modifiers: KeyModifiers::CONTROL,
-								code: KeyCode::Char('c'),
-							} => break,
-							KeyEvent {
-								modifiers: KeyModifiers::CONTROL,
-								code: KeyCode::Left,
-							} => app.on_left(),
-							KeyEvent {
-								modifiers: KeyModifiers::CONTROL,
-								code: KeyCode::Right,
-							} => app.on_right(),
-							KeyEvent {
-								modifiers: KeyModifiers::CONTROL,
-								code: KeyCode::Up,
-							} => app.on_up(),
-							KeyEvent {
-								modifiers: KeyModifiers::CONTROL,
-								code: KeyCode::Down,
-							} => app.on_down(),
-							KeyEvent {
-								modifiers: KeyModifiers::CONTROL,
-								code: KeyCode::Char('r'),
-							} => {
+					if app.on_search_key_event(event) {
+						// Typed into the process search bar, so not an action.
+					} else if let Some(action) = app.keymap.on_key_event(event) {
+						match action {
+							Action::Quit => break,
+							Action::ResetData => {
 								while rtx.send(ResetEvent::Reset).is_err() {
 									debug!("Sent reset message.");
 								}
 								debug!("Resetting begins...");
 								app.reset();
 							}
-							_ => {}
+							_ => app.perform_action(action),
 						}
 					}
 
 					if app.to_be_resorted {
 						handle_process_sorting(&mut app);
 						app.to_be_resorted = false;
+					} else {
+						app.pin_selected_process();
 					}
 				}
-				Event::MouseInput(event) => match event {
-					MouseEvent::ScrollUp(_x, _y, _modifiers) => app.decrement_position_count(),
-					MouseEvent::ScrollDown(_x, _y, _modifiers) => app.increment_position_count(),
-					_ => {}
-				},
+				Event::MouseInput(event) => {
+					match event {
+						MouseEvent::ScrollUp(_x, _y, _modifiers) => app.decrement_position_count(),
+						MouseEvent::ScrollDown(_x, _y, _modifiers) => {
+							app.increment_position_count()
+						}
+						_ => {}
+					}
+					app.pin_selected_process();
+				}
 				Event::Update(data) => {
+					// Processes being terminated are checked on even while frozen.
+					app.update_kill_escalation(&data.list_of_processes);
+
 					// NOTE TO SELF - data is refreshed into app state HERE!  That means, if it is
 					// frozen, then, app.data is never refreshed, until unfrozen!
 					if !app.is_frozen {
 						app.data = *data;
 
+						app.refresh_threads();
 						handle_process_sorting(&mut app);
+						app.refresh_process_details();
 
 						// Convert all data into tui components
 						let network_data = update_network_data_points(&app.data);
@@ -308,7 +279,7 @@ fn main() -> error::Result<()> {
 
 		// Quick fix for tab updating the table headers
 		if let data_collection::processes::ProcessSorting::PID = &app.process_sorting_type {
-			if app.is_grouped() {
+			if app.is_showing_groups() {
 				app.process_sorting_type = data_collection::processes::ProcessSorting::CPU; // Go back to default, negate PID for group
 				app.process_sorting_reverse = true;
 			}
@@ -326,54 +297,24 @@ fn main() -> error::Result<()> {
 	Ok(())
 }
 
-type TempProcess = (f64, Option<f64>, Option<u64>, Vec<u32>);
-
 fn handle_process_sorting(app: &mut app::App) {
-	// Handle combining multi-pid processes to form one entry in table.
-	// This was done this way to save time and avoid code
-	// duplication... sorry future me.  Really.
-
-	// First, convert this all into a BTreeMap.  The key is by name.  This
-	// pulls double duty by allowing us to combine entries AND it sorts!
-
-	// Fields for tuple: CPU%, MEM%, MEM_KB, PID_VEC
-	let mut process_map: BTreeMap<String, TempProcess> = BTreeMap::new();
-	for process in &app.data.list_of_processes {
-		let entry_val =
-			process_map
-				.entry(process.name.clone())
-				.or_insert((0.0, None, None, vec![]));
-		if let Some(mem_usage) = process.mem_usage_percent {
-			entry_val.0 += process.cpu_usage_percent;
-			if let Some(m) = &mut entry_val.1 {
-				*m += mem_usage;
-			}
-			entry_val.3.push(process.pid);
-		} else if let Some(mem_usage_kb) = process.mem_usage_kb {
-			entry_val.0 += process.cpu_usage_percent;
-			if let Some(m) = &mut entry_val.2 {
-				*m += mem_usage_kb;
-			}
-			entry_val.3.push(process.pid);
-		}
-	}
-
-	// Now... turn this back into the exact same vector... but now with merged processes!
-	app.data.grouped_list_of_processes = Some(
-		process_map
-			.iter()
-			.map(|(name, data)| {
-				ProcessData {
-					pid: 0, // Irrelevant
-					cpu_usage_percent: data.0,
-					mem_usage_percent: data.1,
-					mem_usage_kb: data.2,
-					name: name.clone(),
-					pid_vec: Some(data.3.clone()),
-				}
-			})
-			.collect::<Vec<_>>(),
-	);
+	// Combine processes into one entry per group, if grouping; only what matches the search is
+	// grouped.
+	app.data.grouped_list_of_processes = app.get_grouping().map(|grouping| {
+		data_collection::processes::grouping::group_processes(
+			&app.data.list_of_processes,
+			grouping,
+			app.is_showing_full_command(),
+			|process| {
+				app.process_search_state.is_match(
+					process.pid,
+					&process.name,
+					&process.command,
+					process.container.as_deref().unwrap_or_default(),
+				)
+			},
+		)
+	});
 
 	if let Some(grouped_list_of_processes) = &mut app.data.grouped_list_of_processes {
 		if let data_collection::processes::ProcessSorting::PID = &app.process_sorting_type {
@@ -400,6 +341,65 @@ fn handle_process_sorting(app: &mut app::App) {
 	let tuple_results = update_process_row(&app.data);
 	app.canvas_data.process_data = tuple_results.0;
 	app.canvas_data.grouped_process_data = tuple_results.1;
+
+	// Only keep what matches the search; the grouped processes were already filtered above.
+	let process_search_state = &app.process_search_state;
+	app.canvas_data.process_data.retain(|process| {
+		process_search_state.is_match(
+			process.pid,
+			&process.name,
+			&process.command,
+			&process.container,
+		)
+	});
+
+	// Forget about marked processes that have since exited.
+	let list_of_processes = &app.data.list_of_processes;
+	app.marked_process_pids
+		.retain(|pid| list_of_processes.iter().any(|process| process.pid == *pid));
+
+	if app.is_tree_mode() {
+		// Forget about collapsed processes that have since exited.
+		let list_of_processes = &app.data.list_of_processes;
+		app.collapsed_process_pids
+			.retain(|pid| list_of_processes.iter().any(|process| process.pid == *pid));
+
+		app.canvas_data.tree_process_data =
+			convert_process_tree(&app.data, &app.collapsed_process_pids, |process| {
+				process_search_state.is_match(
+					process.pid,
+					&process.name,
+					&process.command,
+					process.container.as_deref().unwrap_or_default(),
+				)
+			});
+	}
+
+	// Threads are sorted like processes, but never searched, as they all belong to one process.
+	// Memory belongs to the whole process, so it isn't shown for each thread.
+	data_collection::processes::sort_processes(
+		&mut app.thread_list,
+		&app.process_sorting_type,
+		app.process_sorting_reverse,
+	);
+	app.canvas_data.thread_process_data = app
+		.thread_list
+		.iter()
+		.map(|thread| ConvertedProcessData {
+			mem_usage: String::new(),
+			..convert_process_data(thread, &app.data)
+		})
+		.collect();
+
+	// Keep the selection on the same process, wherever it has moved to.  Failing that, the list
+	// may have shrunk, so make sure the selection is still in it.
+	app.restore_selected_process();
+	let last_position = std::cmp::max(app.get_displayed_process_count() as i64 - 1, 0);
+	if app.currently_selected_process_position > last_position {
+		app.currently_selected_process_position = last_position;
+	}
+	app.clamp_process_scroll();
+	app.pin_selected_process();
 }
 
 fn cleanup(
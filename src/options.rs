@@ -0,0 +1,338 @@
+//! Handles reading the config file and merging it with the command line arguments.
+//! Command line arguments always take precedence over anything set in the config file.
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use crate::{
+	app::{
+		data_collection::temperature::TemperatureType, keymap::KeyMap, layout_manager::BottomLayout,
+	},
+	canvas::{CanvasColours, CanvasTheme},
+	constants::*,
+	utils::error::{self, BottomError},
+};
+
+mod colour_options;
+mod keymap_options;
+mod layout_options;
+use colour_options::*;
+use keymap_options::*;
+use layout_options::*;
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+	pub flags: Option<ConfigFlags>,
+	pub colors: Option<ConfigColours>,
+	pub keybindings: Option<BTreeMap<String, Vec<String>>>,
+	pub row: Option<Vec<Row>>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFlags {
+	pub avg_cpu: Option<bool>,
+	pub dot_marker: Option<bool>,
+	pub temperature_type: Option<String>,
+	pub rate: Option<u64>,
+	pub left_legend: Option<bool>,
+	pub current_usage: Option<bool>,
+	pub group_processes: Option<bool>,
+	pub basic: Option<bool>,
+	pub layout: Option<String>,
+	pub theme: Option<String>,
+	pub kill_grace_period: Option<u64>,
+	pub audit_log: Option<String>,
+	pub read_only: Option<bool>,
+}
+
+/// Reads the config file.  If a location was explicitly given, it must exist; otherwise
+/// we check the default location and fall back to an empty config if nothing is there.
+pub fn read_config(config_location: Option<&str>) -> error::Result<Config> {
+	let config_path = if let Some(conf_loc) = config_location {
+		Some(PathBuf::from(conf_loc))
+	} else if let Some(mut path) = dirs::config_dir() {
+		path.push(DEFAULT_CONFIG_FILE_PATH);
+		if path.exists() {
+			Some(path)
+		} else {
+			None
+		}
+	} else {
+		None
+	};
+
+	if let Some(config_path) = config_path {
+		let config_string =
+			std::fs::read_to_string(&config_path).map_err(|err| BottomError::ConfigError {
+				message: format!(
+					"Unable to read config file at {}: {}",
+					config_path.display(),
+					err
+				),
+			})?;
+		Ok(toml::from_str(&config_string)?)
+	} else {
+		Ok(Config::default())
+	}
+}
+
+pub fn get_update_rate_in_milliseconds(
+	update_rate: &Option<&str>, config: &Config,
+) -> error::Result<u128> {
+	let update_rate_in_milliseconds = if let Some(update_rate) = update_rate {
+		update_rate.parse::<u128>()?
+	} else if let Some(rate) = config.flags.as_ref().and_then(|flags| flags.rate) {
+		if u128::from(rate) < 250 {
+			return Err(BottomError::ConfigError {
+				message: "Config key `flags.rate` is invalid; please set your update rate to be greater than 250 milliseconds.".to_string(),
+			});
+		}
+		u128::from(rate)
+	} else {
+		DEFAULT_REFRESH_RATE_IN_MILLISECONDS
+	};
+
+	if update_rate_in_milliseconds < 250 {
+		return Err(BottomError::InvalidArg {
+			message: "Please set your update rate to be greater than 250 milliseconds.".to_string(),
+		});
+	} else if update_rate_in_milliseconds > u128::from(u64::MAX) {
+		return Err(BottomError::InvalidArg {
+			message: "Please set your update rate to be less than unsigned INT_MAX.".to_string(),
+		});
+	}
+
+	Ok(update_rate_in_milliseconds)
+}
+
+/// Gets how long processes terminated from the dd dialog get to exit before they are sent
+/// SIGKILL.  If it isn't set, they are only ever sent SIGTERM.
+pub fn get_kill_grace_period(
+	kill_grace_period: &Option<&str>, config: &Config,
+) -> error::Result<Option<Duration>> {
+	let kill_grace_period = if let Some(kill_grace_period) = kill_grace_period {
+		let kill_grace_period = kill_grace_period.parse::<u64>()?;
+		if kill_grace_period == 0 {
+			return Err(BottomError::InvalidArg {
+				message: "Please set your kill grace period to be at least 1 second.".to_string(),
+			});
+		}
+		kill_grace_period
+	} else if let Some(kill_grace_period) = config
+		.flags
+		.as_ref()
+		.and_then(|flags| flags.kill_grace_period)
+	{
+		if kill_grace_period == 0 {
+			return Err(BottomError::ConfigError {
+				message: "Config key `flags.kill_grace_period` is invalid; please set it to be at least 1 second.".to_string(),
+			});
+		}
+		kill_grace_period
+	} else {
+		return Ok(None);
+	};
+
+	Ok(Some(Duration::from_secs(kill_grace_period)))
+}
+
+/// Gets where to keep the audit log of actions taken on processes.  Auditing is opt-in, so there
+/// is no audit log unless a location is given.
+pub fn get_audit_log_path(matches: &clap::ArgMatches<'static>, config: &Config) -> Option<PathBuf> {
+	matches
+		.value_of("AUDIT_LOG")
+		.map(PathBuf::from)
+		.or_else(|| {
+			config
+				.flags
+				.as_ref()
+				.and_then(|flags| flags.audit_log.as_ref())
+				.map(PathBuf::from)
+		})
+}
+
+pub fn get_temperature_option(
+	matches: &clap::ArgMatches<'static>, config: &Config,
+) -> error::Result<TemperatureType> {
+	if matches.is_present("FAHRENHEIT") {
+		return Ok(TemperatureType::Fahrenheit);
+	} else if matches.is_present("KELVIN") {
+		return Ok(TemperatureType::Kelvin);
+	} else if matches.is_present("CELSIUS") {
+		return Ok(TemperatureType::Celsius);
+	}
+
+	if let Some(temp_type) = config
+		.flags
+		.as_ref()
+		.and_then(|flags| flags.temperature_type.as_ref())
+	{
+		match temp_type.to_lowercase().as_str() {
+			"fahrenheit" | "f" => Ok(TemperatureType::Fahrenheit),
+			"kelvin" | "k" => Ok(TemperatureType::Kelvin),
+			"celsius" | "c" => Ok(TemperatureType::Celsius),
+			_ => Err(BottomError::ConfigError {
+				message: format!(
+					"Config key `flags.temperature_type` has an invalid value \"{}\"; expected one of celsius, fahrenheit or kelvin.",
+					temp_type
+				),
+			}),
+		}
+	} else {
+		Ok(TemperatureType::Celsius)
+	}
+}
+
+/// Flags can only be turned on from the command line, so we only consult the config
+/// file when the flag was not passed.
+fn get_flag_option(
+	matches: &clap::ArgMatches<'static>, arg_name: &str, config_value: Option<bool>,
+) -> bool {
+	matches.is_present(arg_name) || config_value.unwrap_or(false)
+}
+
+pub fn get_avg_cpu_option(matches: &clap::ArgMatches<'static>, config: &Config) -> bool {
+	get_flag_option(
+		matches,
+		"AVG_CPU",
+		config.flags.as_ref().and_then(|flags| flags.avg_cpu),
+	)
+}
+
+pub fn get_read_only_option(matches: &clap::ArgMatches<'static>, config: &Config) -> bool {
+	get_flag_option(
+		matches,
+		"READ_ONLY",
+		config.flags.as_ref().and_then(|flags| flags.read_only),
+	)
+}
+
+pub fn get_use_dot_option(matches: &clap::ArgMatches<'static>, config: &Config) -> bool {
+	get_flag_option(
+		matches,
+		"DOT_MARKER",
+		config.flags.as_ref().and_then(|flags| flags.dot_marker),
+	)
+}
+
+pub fn get_use_left_legend_option(matches: &clap::ArgMatches<'static>, config: &Config) -> bool {
+	get_flag_option(
+		matches,
+		"LEFT_LEGEND",
+		config.flags.as_ref().and_then(|flags| flags.left_legend),
+	)
+}
+
+pub fn get_use_current_cpu_total_option(
+	matches: &clap::ArgMatches<'static>, config: &Config,
+) -> bool {
+	get_flag_option(
+		matches,
+		"USE_CURR_USAGE",
+		config.flags.as_ref().and_then(|flags| flags.current_usage),
+	)
+}
+
+pub fn get_group_processes_option(matches: &clap::ArgMatches<'static>, config: &Config) -> bool {
+	get_flag_option(
+		matches,
+		"GROUP_PROCESSES",
+		config
+			.flags
+			.as_ref()
+			.and_then(|flags| flags.group_processes),
+	)
+}
+
+pub fn get_use_basic_mode_option(matches: &clap::ArgMatches<'static>, config: &Config) -> bool {
+	get_flag_option(
+		matches,
+		"BASIC_MODE",
+		config.flags.as_ref().and_then(|flags| flags.basic),
+	)
+}
+
+/// Gets the widget layout.  A preset passed on the command line wins, followed by a preset set
+/// in the config file, followed by a custom layout in the config file.
+pub fn get_layout(
+	matches: &clap::ArgMatches<'static>, config: &Config,
+) -> error::Result<BottomLayout> {
+	if let Some(preset) = matches.value_of("LAYOUT") {
+		return BottomLayout::from_preset(preset).ok_or_else(|| BottomError::InvalidArg {
+			message: format!(
+				"\"{}\" is not a valid layout preset; expected one of default, processes or minimal.",
+				preset
+			),
+		});
+	}
+
+	let layout_preset = config
+		.flags
+		.as_ref()
+		.and_then(|flags| flags.layout.as_ref());
+	match (layout_preset, &config.row) {
+		(Some(_), Some(_)) => Err(BottomError::ConfigError {
+			message: "Config keys `flags.layout` and `row` cannot both be set; pick either a preset or a custom layout.".to_string(),
+		}),
+		(Some(preset), None) => {
+			BottomLayout::from_preset(preset).ok_or_else(|| BottomError::ConfigError {
+				message: format!(
+					"Config key `flags.layout` has an invalid value \"{}\"; expected one of default, processes or minimal.",
+					preset
+				),
+			})
+		}
+		(None, Some(rows)) => convert_rows_to_layout(rows),
+		(None, None) => Ok(BottomLayout::default()),
+	}
+}
+
+/// Gets the colours to draw with.  The theme can be set on the command line or in the config
+/// file, and individual colours can then be overridden in the config file.  If `NO_COLOR` is set,
+/// everything is drawn without colour regardless.
+pub fn get_canvas_colours(
+	matches: &clap::ArgMatches<'static>, config: &Config,
+) -> error::Result<CanvasColours> {
+	let theme = if let Some(theme) = matches.value_of("THEME") {
+		CanvasTheme::from_name(theme).ok_or_else(|| BottomError::InvalidArg {
+			message: format!(
+				"\"{}\" is not a valid theme; expected one of default, light, high-contrast or monochrome.",
+				theme
+			),
+		})?
+	} else if let Some(theme) = config.flags.as_ref().and_then(|flags| flags.theme.as_ref()) {
+		CanvasTheme::from_name(theme).ok_or_else(|| BottomError::ConfigError {
+			message: format!(
+				"Config key `flags.theme` has an invalid value \"{}\"; expected one of default, light, high-contrast or monochrome.",
+				theme
+			),
+		})?
+	} else {
+		CanvasTheme::Default
+	};
+
+	let mut canvas_colours = CanvasColours::from_theme(theme);
+	if let Some(config_colours) = &config.colors {
+		apply_colour_overrides(&mut canvas_colours, config_colours)?;
+	}
+
+	// See https://no-color.org/; we still check the rest of the config above so that mistakes in
+	// it are not hidden.
+	if std::env::var_os("NO_COLOR").map_or(false, |no_color| !no_color.is_empty()) {
+		canvas_colours = CanvasColours::from_theme(CanvasTheme::Monochrome);
+	}
+
+	Ok(canvas_colours)
+}
+
+/// Gets the keymap, which is the default one with any bindings from the config file applied.
+pub fn get_keymap(config: &Config) -> error::Result<KeyMap> {
+	let mut keymap = KeyMap::default();
+	if let Some(keybindings) = &config.keybindings {
+		apply_keybindings(&mut keymap, keybindings)?;
+	}
+
+	Ok(keymap)
+}
@@ -0,0 +1,212 @@
+use serde::Deserialize;
+use tui::style::{Color, Style};
+
+use crate::{
+	canvas::CanvasColours,
+	utils::error::{self, BottomError},
+};
+
+/// A colour as written in the config file; either a 256-colour index, or a string holding a
+/// colour name, a hex code or a 256-colour index.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ConfigColour {
+	Index(u8),
+	Named(String),
+}
+
+/// Per-element colour overrides, applied on top of the chosen theme.
+#[derive(Default, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigColours {
+	pub text_color: Option<ConfigColour>,
+	pub graph_color: Option<ConfigColour>,
+	pub border_color: Option<ConfigColour>,
+	pub highlighted_border_color: Option<ConfigColour>,
+	pub table_header_color: Option<ConfigColour>,
+	pub selected_text_color: Option<ConfigColour>,
+	pub selected_bg_color: Option<ConfigColour>,
+	pub ram_color: Option<ConfigColour>,
+	pub swap_color: Option<ConfigColour>,
+	pub rx_color: Option<ConfigColour>,
+	pub tx_color: Option<ConfigColour>,
+	pub invalid_query_color: Option<ConfigColour>,
+	pub cpu_core_colors: Option<Vec<ConfigColour>>,
+}
+
+fn convert_hex_to_colour(hex: &str) -> Option<Color> {
+	let hex = hex.trim_start_matches('#');
+	if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+		return None;
+	}
+
+	match hex.len() {
+		6 => Some(Color::Rgb(
+			u8::from_str_radix(&hex[0..2], 16).ok()?,
+			u8::from_str_radix(&hex[2..4], 16).ok()?,
+			u8::from_str_radix(&hex[4..6], 16).ok()?,
+		)),
+		3 => {
+			// Shorthand, so #abc is the same as #aabbcc
+			let mut channels = hex
+				.chars()
+				.map(|c| u8::from_str_radix(&c.to_string(), 16).map(|val| val * 17));
+			Some(Color::Rgb(
+				channels.next()?.ok()?,
+				channels.next()?.ok()?,
+				channels.next()?.ok()?,
+			))
+		}
+		_ => None,
+	}
+}
+
+fn convert_name_to_colour(name: &str) -> Option<Color> {
+	let name = name
+		.to_lowercase()
+		.chars()
+		.filter(|c| !c.is_whitespace() && *c != '_' && *c != '-')
+		.collect::<String>()
+		.replace("grey", "gray");
+
+	match name.as_str() {
+		"reset" | "default" => Some(Color::Reset),
+		"black" => Some(Color::Black),
+		"red" => Some(Color::Red),
+		"green" => Some(Color::Green),
+		"yellow" => Some(Color::Yellow),
+		"blue" => Some(Color::Blue),
+		"magenta" => Some(Color::Magenta),
+		"cyan" => Some(Color::Cyan),
+		"gray" => Some(Color::Gray),
+		"darkgray" => Some(Color::DarkGray),
+		"lightred" => Some(Color::LightRed),
+		"lightgreen" => Some(Color::LightGreen),
+		"lightyellow" => Some(Color::LightYellow),
+		"lightblue" => Some(Color::LightBlue),
+		"lightmagenta" => Some(Color::LightMagenta),
+		"lightcyan" => Some(Color::LightCyan),
+		"white" => Some(Color::White),
+		_ => None,
+	}
+}
+
+fn get_colour(colour: &ConfigColour, key: &str) -> error::Result<Color> {
+	let converted_colour = match colour {
+		ConfigColour::Index(index) => Some(Color::Indexed(*index)),
+		ConfigColour::Named(name) => {
+			let name = name.trim();
+			if name.starts_with('#') {
+				convert_hex_to_colour(name)
+			} else if let Ok(index) = name.parse::<u8>() {
+				Some(Color::Indexed(index))
+			} else {
+				convert_name_to_colour(name)
+			}
+		}
+	};
+
+	converted_colour.ok_or_else(|| BottomError::ConfigError {
+		message: format!(
+			"Config key `{}` has an invalid colour {}; expected a colour name, a hex code like \"#1a2b3c\", or a 256-colour index from 0 to 255.",
+			key,
+			match colour {
+				ConfigColour::Index(index) => index.to_string(),
+				ConfigColour::Named(name) => format!("\"{}\"", name),
+			}
+		),
+	})
+}
+
+/// Overrides the foreground of the given style if a colour was set.
+fn set_fg(style: &mut Style, colour: &Option<ConfigColour>, key: &str) -> error::Result<()> {
+	if let Some(colour) = colour {
+		*style = style.fg(get_colour(colour, key)?);
+	}
+	Ok(())
+}
+
+/// Applies the colours set in the config file on top of the given (theme) colours.
+pub fn apply_colour_overrides(
+	canvas_colours: &mut CanvasColours, config_colours: &ConfigColours,
+) -> error::Result<()> {
+	set_fg(
+		&mut canvas_colours.text_style,
+		&config_colours.text_color,
+		"colors.text_color",
+	)?;
+	set_fg(
+		&mut canvas_colours.graph_style,
+		&config_colours.graph_color,
+		"colors.graph_color",
+	)?;
+	set_fg(
+		&mut canvas_colours.border_style,
+		&config_colours.border_color,
+		"colors.border_color",
+	)?;
+	set_fg(
+		&mut canvas_colours.highlighted_border_style,
+		&config_colours.highlighted_border_color,
+		"colors.highlighted_border_color",
+	)?;
+	set_fg(
+		&mut canvas_colours.table_header_style,
+		&config_colours.table_header_color,
+		"colors.table_header_color",
+	)?;
+	set_fg(
+		&mut canvas_colours.selected_text_style,
+		&config_colours.selected_text_color,
+		"colors.selected_text_color",
+	)?;
+	if let Some(colour) = &config_colours.selected_bg_color {
+		canvas_colours.selected_text_style = canvas_colours
+			.selected_text_style
+			.bg(get_colour(colour, "colors.selected_bg_color")?);
+	}
+	set_fg(
+		&mut canvas_colours.ram_style,
+		&config_colours.ram_color,
+		"colors.ram_color",
+	)?;
+	set_fg(
+		&mut canvas_colours.swap_style,
+		&config_colours.swap_color,
+		"colors.swap_color",
+	)?;
+	set_fg(
+		&mut canvas_colours.rx_style,
+		&config_colours.rx_color,
+		"colors.rx_color",
+	)?;
+	set_fg(
+		&mut canvas_colours.tx_style,
+		&config_colours.tx_color,
+		"colors.tx_color",
+	)?;
+	set_fg(
+		&mut canvas_colours.invalid_query_style,
+		&config_colours.invalid_query_color,
+		"colors.invalid_query_color",
+	)?;
+
+	if let Some(cpu_core_colours) = &config_colours.cpu_core_colors {
+		if cpu_core_colours.is_empty() {
+			return Err(BottomError::ConfigError {
+				message: "Config key `colors.cpu_core_colors` must contain at least one colour."
+					.to_string(),
+			});
+		}
+
+		canvas_colours.cpu_colour_styles = cpu_core_colours
+			.iter()
+			.map(|colour| {
+				get_colour(colour, "colors.cpu_core_colors")
+					.map(|colour| Style::default().fg(colour))
+			})
+			.collect::<error::Result<Vec<_>>>()?;
+	}
+
+	Ok(())
+}
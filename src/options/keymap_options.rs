@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use crate::{
+	app::keymap::*,
+	utils::error::{self, BottomError},
+};
+
+/// Rebinds the actions set in the config file.  Any action set replaces all of its default key
+/// sequences; actions that are not set keep their defaults.
+pub fn apply_keybindings(
+	keymap: &mut KeyMap, keybindings: &BTreeMap<String, Vec<String>>,
+) -> error::Result<()> {
+	for (action_name, sequences) in keybindings {
+		let action = Action::from_name(action_name).ok_or_else(|| BottomError::ConfigError {
+			message: format!(
+				"Config key `keybindings.{}` is not a valid action; expected one of {}.",
+				action_name,
+				ALL_ACTIONS
+					.iter()
+					.map(|action| action.name())
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+		})?;
+
+		if action == Action::Quit && sequences.is_empty() {
+			return Err(BottomError::ConfigError {
+				message: "Config key `keybindings.quit` must have at least one key sequence, otherwise bottom cannot be closed.".to_string(),
+			});
+		}
+
+		let key_sequences = sequences
+			.iter()
+			.map(|sequence| {
+				parse_key_sequence(sequence).ok_or_else(|| BottomError::ConfigError {
+					message: format!(
+						"Config key `keybindings.{}` has an invalid key sequence \"{}\".",
+						action_name, sequence
+					),
+				})
+			})
+			.collect::<error::Result<Vec<_>>>()?;
+
+		keymap.set_bindings(action, key_sequences);
+	}
+
+	if let Some((sequence, action, other_sequence, other_action)) = keymap.find_conflict() {
+		return Err(BottomError::ConfigError {
+			message: format!(
+				"Config key `keybindings` binds \"{}\" to {} and \"{}\" to {}, so one of them can never be used.",
+				key_sequence_to_string(sequence),
+				action.name(),
+				key_sequence_to_string(other_sequence),
+				other_action.name()
+			),
+		});
+	}
+
+	Ok(())
+}
@@ -0,0 +1,127 @@
+use serde::Deserialize;
+
+use crate::{
+	app::{layout_manager::*, ApplicationPosition},
+	utils::error::{self, BottomError},
+};
+
+/// Represents a row.  This has a ratio relative to the other rows (defaults to 1) and a list
+/// of children, which are either widgets or columns of widgets.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Row {
+	pub ratio: Option<u32>,
+	pub child: Option<Vec<RowChildren>>,
+}
+
+/// A child of a row is either a single widget, or a column of widgets stacked on top of each
+/// other.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum RowChildren {
+	Widget(FinalWidget),
+	Col {
+		ratio: Option<u32>,
+		child: Vec<FinalWidget>,
+	},
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FinalWidget {
+	pub ratio: Option<u32>,
+	#[serde(rename = "type")]
+	pub widget_type: String,
+}
+
+fn get_ratio(ratio: Option<u32>, key: &str) -> error::Result<u32> {
+	match ratio {
+		Some(0) => Err(BottomError::ConfigError {
+			message: format!("Config key `{}` must be greater than 0.", key),
+		}),
+		Some(ratio) => Ok(ratio),
+		None => Ok(1),
+	}
+}
+
+fn get_widget_type(widget_type: &str, key: &str) -> error::Result<ApplicationPosition> {
+	match widget_type.to_lowercase().as_str() {
+		"cpu" => Ok(ApplicationPosition::Cpu),
+		"mem" | "memory" => Ok(ApplicationPosition::Mem),
+		"net" | "network" => Ok(ApplicationPosition::Network),
+		"temp" | "temperature" => Ok(ApplicationPosition::Temp),
+		"disk" => Ok(ApplicationPosition::Disk),
+		"proc" | "process" | "processes" => Ok(ApplicationPosition::Process),
+		_ => Err(BottomError::ConfigError {
+			message: format!(
+				"Config key `{}` has an invalid widget type \"{}\"; expected one of cpu, mem, net, temp, disk or proc.",
+				key, widget_type
+			),
+		}),
+	}
+}
+
+fn convert_widget(widget: &FinalWidget, key: &str) -> error::Result<BottomWidget> {
+	Ok(BottomWidget::new(
+		get_widget_type(&widget.widget_type, &format!("{}.type", key))?,
+		get_ratio(widget.ratio, &format!("{}.ratio", key))?,
+	))
+}
+
+/// Converts the rows read from the config file into a layout, validating them along the way.
+pub fn convert_rows_to_layout(rows: &[Row]) -> error::Result<BottomLayout> {
+	if rows.is_empty() {
+		return Err(BottomError::ConfigError {
+			message: "Config key `row` must contain at least one row.".to_string(),
+		});
+	}
+
+	let mut bottom_rows = Vec::new();
+	for row in rows {
+		let children = match &row.child {
+			Some(children) if !children.is_empty() => children,
+			_ => {
+				return Err(BottomError::ConfigError {
+					message:
+						"Config key `row.child` must contain at least one widget for every row."
+							.to_string(),
+				});
+			}
+		};
+
+		let mut bottom_cols = Vec::new();
+		for child in children {
+			bottom_cols.push(match child {
+				RowChildren::Widget(widget) => {
+					// A lone widget is treated as a column with only that widget in it.
+					let bottom_widget = convert_widget(widget, "row.child")?;
+					let ratio = bottom_widget.ratio;
+					BottomCol::new(vec![bottom_widget], ratio)
+				}
+				RowChildren::Col { ratio, child } => {
+					if child.is_empty() {
+						return Err(BottomError::ConfigError {
+							message: "Config key `row.child.child` must contain at least one widget for every column."
+								.to_string(),
+						});
+					}
+
+					BottomCol::new(
+						child
+							.iter()
+							.map(|widget| convert_widget(widget, "row.child.child"))
+							.collect::<error::Result<Vec<_>>>()?,
+						get_ratio(*ratio, "row.child.ratio")?,
+					)
+				}
+			});
+		}
+
+		bottom_rows.push(BottomRow::new(
+			bottom_cols,
+			get_ratio(row.ratio, "row.ratio")?,
+		));
+	}
+
+	Ok(BottomLayout::new(bottom_rows))
+}
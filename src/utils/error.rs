@@ -40,6 +40,24 @@ pub enum BottomError {
 	/// The data provided is the error found.
 	#[fail(display = "ERROR: Invalid fern error: {}", message)]
 	FernError { message: String },
+	/// An error to represent problems with the config file
+	///
+	/// The data provided is the error found.
+	#[fail(display = "ERROR: Invalid config file: {}", message)]
+	ConfigError { message: String },
+	/// An error when a signal could not be sent to a process
+	///
+	/// The data provided is the error found.
+	#[fail(display = "ERROR: Unable to signal a process: {}", message)]
+	SignalError { message: String },
+	/// An error when the niceness or CPU affinity of a process could not be changed
+	///
+	/// The data provided is the error found.
+	#[fail(
+		display = "ERROR: Unable to change the scheduling of a process: {}",
+		message
+	)]
+	SchedulingError { message: String },
 }
 
 impl From<std::io::Error> for BottomError {
@@ -87,3 +105,11 @@ impl From<fern::InitError> for BottomError {
 		}
 	}
 }
+
+impl From<toml::de::Error> for BottomError {
+	fn from(err: toml::de::Error) -> Self {
+		BottomError::ConfigError {
+			message: err.to_string(),
+		}
+	}
+}
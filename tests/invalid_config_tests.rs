@@ -1 +1,273 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
 
+// These tests are here to ensure that invalid config files will be caught before bottom starts...
+
+//======================CONFIGS======================//
+
+fn get_os_binary_loc() -> String {
+	if cfg!(target_os = "linux") {
+		"./target/x86_64-unknown-linux-gnu/debug/btm".to_string()
+	} else if cfg!(target_os = "windows") {
+		"./target/x86_64-pc-windows-msvc/debug/btm".to_string()
+	} else if cfg!(target_os = "macos") {
+		"./target/x86_64-apple-darwin/debug/btm".to_string()
+	} else {
+		"".to_string()
+	}
+}
+
+#[test]
+fn test_missing_config() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/this_file_does_not_exist.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("Unable to read config file"));
+	Ok(())
+}
+
+#[test]
+fn test_invalid_toml() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/invalid_toml.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("ConfigError"));
+	Ok(())
+}
+
+#[test]
+fn test_toml_mismatch_type() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/toml_mismatch_type.toml")
+		.assert()
+		.failure()
+		.stderr(
+			predicate::str::contains("invalid type").and(predicate::str::contains("flags.avg_cpu")),
+		);
+	Ok(())
+}
+
+#[test]
+fn test_unknown_key() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/unknown_key.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("unknown field `avgcpu`"));
+	Ok(())
+}
+
+#[test]
+fn test_unknown_table() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/unknown_table.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("unknown field `flag`"));
+	Ok(())
+}
+
+#[test]
+fn test_invalid_temperature_type() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/invalid_temperature_type.toml")
+		.assert()
+		.failure()
+		.stderr(
+			predicate::str::contains("ConfigError")
+				.and(predicate::str::contains("flags.temperature_type")),
+		);
+	Ok(())
+}
+
+#[test]
+fn test_small_rate() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/small_rate.toml")
+		.assert()
+		.failure()
+		.stderr(
+			predicate::str::contains("ConfigError")
+				.and(predicate::str::contains("flags.rate")),
+		);
+	Ok(())
+}
+
+#[test]
+fn test_zero_kill_grace_period() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/zero_kill_grace_period.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("flags.kill_grace_period"));
+	Ok(())
+}
+
+#[test]
+fn test_cli_rate_overrides_config() -> Result<(), Box<dyn std::error::Error>> {
+	// Both rates are invalid, but the command line one should be the one that is reported.
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/small_rate.toml")
+		.arg("-r")
+		.arg("249")
+		.assert()
+		.failure()
+		.stderr(
+			predicate::str::contains("rate to be greater than 250")
+				.and(predicate::str::contains("flags.rate").not()),
+		);
+	Ok(())
+}
+
+#[test]
+fn test_invalid_widget_type() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/invalid_widget_type.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("row.child.type").and(predicate::str::contains("gpu")));
+	Ok(())
+}
+
+#[test]
+fn test_zero_ratio() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/zero_ratio.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("row.ratio"));
+	Ok(())
+}
+
+#[test]
+fn test_invalid_layout_preset() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/invalid_layout_preset.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("flags.layout"));
+	Ok(())
+}
+
+#[test]
+fn test_layout_preset_and_rows() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/layout_preset_and_rows.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("cannot both be set"));
+	Ok(())
+}
+
+#[test]
+fn test_invalid_cli_layout_preset() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("--layout")
+		.arg("everything")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("is not a valid layout preset"));
+	Ok(())
+}
+
+#[test]
+fn test_invalid_theme() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/invalid_theme.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("flags.theme"));
+	Ok(())
+}
+
+#[test]
+fn test_invalid_colour() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/invalid_colour.toml")
+		.assert()
+		.failure()
+		.stderr(
+			predicate::str::contains("colors.text_color")
+				.and(predicate::str::contains("not_a_colour")),
+		);
+	Ok(())
+}
+
+#[test]
+fn test_invalid_hex_colour() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/invalid_hex_colour.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("colors.cpu_core_colors"));
+	Ok(())
+}
+
+#[test]
+fn test_invalid_keybinding_action() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/invalid_action.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("keybindings.sort_by_colour"));
+	Ok(())
+}
+
+#[test]
+fn test_invalid_key_sequence() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/invalid_key_sequence.toml")
+		.assert()
+		.failure()
+		.stderr(
+			predicate::str::contains("keybindings.freeze")
+				.and(predicate::str::contains("Ctrl-foo")),
+		);
+	Ok(())
+}
+
+#[test]
+fn test_conflicting_keybindings() -> Result<(), Box<dyn std::error::Error>> {
+	// "d" for sorting would stop the default "dd" for killing from ever being used.
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/conflicting_keybindings.toml")
+		.assert()
+		.failure()
+		.stderr(
+			predicate::str::contains("kill_process").and(predicate::str::contains("sort_by_cpu")),
+		);
+	Ok(())
+}
+
+#[test]
+fn test_empty_quit_keybinding() -> Result<(), Box<dyn std::error::Error>> {
+	Command::new(get_os_binary_loc())
+		.arg("-C")
+		.arg("./tests/invalid_configs/empty_quit_keybinding.toml")
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("keybindings.quit"));
+	Ok(())
+}